@@ -0,0 +1,4 @@
+// Миграции встраиваются в бинарник, поэтому при их изменении нужна пересборка
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,7 @@
+-- Исходная схема. IF NOT EXISTS позволяет принять под управление базы,
+-- созданные до появления миграций
+CREATE TABLE IF NOT EXISTS employees (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    salary INT NOT NULL
+);
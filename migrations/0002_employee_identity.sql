@@ -0,0 +1,5 @@
+-- Нормализованное имя заполняется приложением, уникальный индекс по нему
+-- создается в зависимости от политики уникальности
+ALTER TABLE employees ADD COLUMN IF NOT EXISTS normalized_name VARCHAR(255);
+ALTER TABLE employees ADD COLUMN IF NOT EXISTS personnel_number VARCHAR(64);
+CREATE UNIQUE INDEX IF NOT EXISTS employees_personnel_number_key ON employees (personnel_number);
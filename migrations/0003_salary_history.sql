@@ -0,0 +1,24 @@
+-- Журнал изменений зарплаты. Записи только добавляются: изменение и удаление
+-- запрещены триггером. Внешнего ключа нет намеренно, чтобы история
+-- переживала удаление сотрудника
+CREATE TABLE IF NOT EXISTS salary_history (
+    id BIGSERIAL PRIMARY KEY,
+    employee_id INT NOT NULL,
+    old_salary INT NOT NULL,
+    new_salary INT NOT NULL,
+    percentage INT NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    actor VARCHAR(255) NOT NULL
+);
+CREATE INDEX IF NOT EXISTS salary_history_employee_idx ON salary_history (employee_id, id DESC);
+
+CREATE OR REPLACE FUNCTION salary_history_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'salary_history is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS salary_history_append_only ON salary_history;
+CREATE TRIGGER salary_history_append_only
+    BEFORE UPDATE OR DELETE ON salary_history
+    FOR EACH ROW EXECUTE FUNCTION salary_history_append_only();
@@ -0,0 +1,8 @@
+-- Зарплата хранится десятичным числом с двумя знаками после запятой и кодом валюты ISO 4217.
+-- Существующие зарплаты считаются рублевыми
+ALTER TABLE employees ALTER COLUMN salary TYPE NUMERIC(18,2);
+ALTER TABLE employees ADD COLUMN IF NOT EXISTS currency CHAR(3) NOT NULL DEFAULT 'RUB';
+
+ALTER TABLE salary_history ALTER COLUMN old_salary TYPE NUMERIC(18,2);
+ALTER TABLE salary_history ALTER COLUMN new_salary TYPE NUMERIC(18,2);
+ALTER TABLE salary_history ADD COLUMN IF NOT EXISTS currency CHAR(3) NOT NULL DEFAULT 'RUB';
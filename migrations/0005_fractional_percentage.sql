@@ -0,0 +1,4 @@
+-- Процент повышения задается с точностью до сотых, а способ округления
+-- результата записывается в журнал. Прежние повышения округлялись вверх
+ALTER TABLE salary_history ALTER COLUMN percentage TYPE NUMERIC(10,2);
+ALTER TABLE salary_history ADD COLUMN IF NOT EXISTS rounding VARCHAR(16) NOT NULL DEFAULT 'ceil';
@@ -0,0 +1,6 @@
+-- Зарплату можно не только повысить на процент, но и изменить на сумму или установить.
+-- Процент и способ округления заполняются только для изменений на процент
+ALTER TABLE salary_history ADD COLUMN IF NOT EXISTS operation VARCHAR(16) NOT NULL DEFAULT 'percentage';
+ALTER TABLE salary_history ALTER COLUMN percentage DROP NOT NULL;
+ALTER TABLE salary_history ALTER COLUMN rounding DROP NOT NULL;
+ALTER TABLE salary_history ALTER COLUMN rounding DROP DEFAULT;
@@ -0,0 +1,4 @@
+-- Уволенные сотрудники не удаляются, а помечаются датой увольнения и пропадают из обычных запросов.
+-- Уникальный индекс по именам пересоздается при старте частичным, только по работающим сотрудникам
+ALTER TABLE employees ADD COLUMN IF NOT EXISTS terminated_at TIMESTAMPTZ;
+DROP INDEX IF EXISTS employees_normalized_name_key;
@@ -0,0 +1,5 @@
+-- Индексы для постраничного списка работающих сотрудников:
+-- сортировка по имени и зарплате, поиск по началу нормализованного имени
+CREATE INDEX IF NOT EXISTS employees_active_name_idx ON employees (normalized_name, id) WHERE terminated_at IS NULL;
+CREATE INDEX IF NOT EXISTS employees_active_salary_idx ON employees (salary, id) WHERE terminated_at IS NULL;
+CREATE INDEX IF NOT EXISTS employees_active_name_prefix_idx ON employees (normalized_name text_pattern_ops) WHERE terminated_at IS NULL;
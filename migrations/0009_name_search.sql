@@ -0,0 +1,3 @@
+-- Нечеткий поиск сотрудников по нормализованному имени на триграммах
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE INDEX IF NOT EXISTS employees_active_name_trgm_idx ON employees USING gin (normalized_name gin_trgm_ops) WHERE terminated_at IS NULL;
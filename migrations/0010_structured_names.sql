@@ -0,0 +1,11 @@
+-- Структурированное имя: фамилия, имя, отчество и отображаемое имя.
+-- Колонка name остается отображаемой формой, по которой работают запросы по имени.
+-- Части имени существующих записей и нормализованная фамилия заполняются приложением при старте
+ALTER TABLE employees ADD COLUMN IF NOT EXISTS surname VARCHAR(255);
+ALTER TABLE employees ADD COLUMN IF NOT EXISTS given_name VARCHAR(255);
+ALTER TABLE employees ADD COLUMN IF NOT EXISTS patronymic VARCHAR(255);
+ALTER TABLE employees ADD COLUMN IF NOT EXISTS display_name VARCHAR(255);
+ALTER TABLE employees ADD COLUMN IF NOT EXISTS normalized_surname VARCHAR(255);
+
+CREATE INDEX IF NOT EXISTS employees_active_surname_idx ON employees (normalized_surname, normalized_name, id) WHERE terminated_at IS NULL;
+CREATE INDEX IF NOT EXISTS employees_active_surname_prefix_idx ON employees (normalized_surname text_pattern_ops) WHERE terminated_at IS NULL;
@@ -0,0 +1,363 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use crate::auth::Principal;
+use crate::errors::AppError;
+use crate::models::{Employee, SalaryAdjustment, SalaryBatch, SalaryBatchTarget};
+use crate::money::{Money, Percentage};
+
+
+/// Роль пользователя
+///
+/// Выдается в файле ключей API или в утверждении roles токена JWT
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role{
+    /// Видит только собственную зарплату
+    Viewer,
+    /// Ведет сотрудников и повышает зарплаты в пределах auth.max_raise_percentage
+    Hr,
+    /// Не ограничен в изменениях зарплаты и может удалять сотрудников
+    PayrollAdmin,
+    /// Читает только метрики /metrics, например для Prometheus
+    Monitoring,
+}
+
+impl Role{
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Hr => "hr",
+            Role::PayrollAdmin => "payroll_admin",
+            Role::Monitoring => "monitoring",
+        }
+    }
+
+    /// Права, которые дает роль
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Viewer => &[Permission::ReadOwnSalary],
+            Role::Hr => &[
+                Permission::ReadOwnSalary,
+                Permission::ReadSalaries,
+                Permission::ManageEmployees,
+                Permission::ChangeSalaries,
+            ],
+            Role::PayrollAdmin => &[
+                Permission::ReadOwnSalary,
+                Permission::ReadSalaries,
+                Permission::ManageEmployees,
+                Permission::ChangeSalaries,
+                Permission::UnlimitedRaises,
+                Permission::DeleteEmployees,
+                Permission::ReadMetrics,
+            ],
+            Role::Monitoring => &[Permission::ReadMetrics],
+        }
+    }
+}
+
+impl FromStr for Role{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "hr" => Ok(Role::Hr),
+            "payroll_admin" => Ok(Role::PayrollAdmin),
+            "monitoring" => Ok(Role::Monitoring),
+            _ => Err(AppError::validation("roles", "role must be one of \"viewer\", \"hr\", \"payroll_admin\", \"monitoring\"")),
+        }
+    }
+}
+
+impl Display for Role{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+
+/// Право на группу операций
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission{
+    /// Читать свою запись, зарплату и журнал ее изменений
+    ReadOwnSalary,
+    /// Читать записи и зарплаты любых сотрудников
+    ReadSalaries,
+    /// Добавлять, переименовывать и увольнять сотрудников
+    ManageEmployees,
+    /// Изменять зарплаты
+    ChangeSalaries,
+    /// Повышать зарплаты больше чем на auth.max_raise_percentage
+    UnlimitedRaises,
+    /// Удалять сотрудников безвозвратно
+    DeleteEmployees,
+    /// Читать метрики Prometheus
+    ReadMetrics,
+}
+
+impl Permission{
+    pub fn name(&self) -> &'static str {
+        match self {
+            Permission::ReadOwnSalary => "read_own_salary",
+            Permission::ReadSalaries => "read_salaries",
+            Permission::ManageEmployees => "manage_employees",
+            Permission::ChangeSalaries => "change_salaries",
+            Permission::UnlimitedRaises => "unlimited_raises",
+            Permission::DeleteEmployees => "delete_employees",
+            Permission::ReadMetrics => "read_metrics",
+        }
+    }
+}
+
+
+/// Правила доступа, которые зависят от данных запроса
+///
+/// Права на сами эндпоинты проверяются через Principal::require
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessPolicy{
+    /// Наибольшее повышение без права UnlimitedRaises
+    max_raise: Percentage,
+}
+
+impl AccessPolicy{
+    pub fn new(max_raise: Percentage) -> AccessPolicy {
+        AccessPolicy{max_raise}
+    }
+
+    /// Прочитать зарплату сотрудника можно с правом ReadSalaries, а свою - и с правом ReadOwnSalary
+    pub fn check_salary_read(&self, principal: &Principal, employee_id: i32) -> Result<(), AppError> {
+        if principal.has(Permission::ReadSalaries) {
+            return Ok(());
+        }
+        principal.require(Permission::ReadOwnSalary)?;
+        match principal.employee_id == Some(employee_id) {
+            true => Ok(()),
+            false => Err(self.own_salary_only(principal)),
+        }
+    }
+
+    /// Можно ли искать зарплату по имени
+    ///
+    /// Без права ReadSalaries искать имеет смысл только пользователю, связанному с записью о сотруднике
+    pub fn check_salary_search(&self, principal: &Principal) -> Result<(), AppError> {
+        if principal.has(Permission::ReadSalaries) {
+            return Ok(());
+        }
+        principal.require(Permission::ReadOwnSalary)?;
+        match principal.employee_id {
+            Some(_) => Ok(()),
+            None => Err(self.own_salary_only(principal)),
+        }
+    }
+
+    /// Проверить результат поиска зарплаты по имени
+    ///
+    /// Без права ReadSalaries любой исход, кроме своей записи, в том числе отсутствие сотрудника
+    /// и несколько совпадений, дает одинаковый отказ: по ответу нельзя узнать о чужих записях
+    pub fn check_salary_found(&self, principal: &Principal, found: Result<Employee, AppError>) -> Result<Employee, AppError> {
+        if principal.has(Permission::ReadSalaries) {
+            return found;
+        }
+        match found {
+            Ok(employee) if principal.employee_id == Some(employee.id) => Ok(employee),
+            Err(e @ (AppError::StorageUnavailable(_) | AppError::Storage(_))) => Err(e),
+            _ => Err(self.own_salary_only(principal)),
+        }
+    }
+
+    /// Повышение больше предела требует права UnlimitedRaises, понижение ограничено только правом ChangeSalaries
+    ///
+    /// Запрошенный процент сравнивается с пределом сразу. Округление может сделать повышение больше
+    /// запрошенного, поэтому возвращается и предел, который проверяется по итоговой зарплате
+    /// при изменении после блокировки строки сотрудника. None - изменение не ограничено
+    pub fn check_raise(&self, principal: &Principal, percentage: &Percentage) -> Result<Option<RaiseLimit>, AppError> {
+        principal.require(Permission::ChangeSalaries)?;
+        if percentage.hundredths > self.max_raise.hundredths && !principal.has(Permission::UnlimitedRaises) {
+            return Err(self.raise_denied(principal));
+        }
+        Ok(self.limit(principal))
+    }
+
+    /// Проверить изменение зарплаты
+    ///
+    /// Процент сравнивается с пределом сразу. Сумму и новое значение можно сравнить с ним только
+    /// в процентах от текущей зарплаты, поэтому для них, как и для итога повышения на процент,
+    /// возвращается предел, который проверяется при изменении. None - изменение не ограничено
+    pub fn raise_limit(&self, principal: &Principal, adjustment: &SalaryAdjustment) -> Result<Option<RaiseLimit>, AppError> {
+        principal.require(Permission::ChangeSalaries)?;
+        match adjustment {
+            SalaryAdjustment::Percentage{percentage, ..} => self.check_raise(principal, percentage),
+            SalaryAdjustment::Delta{..} | SalaryAdjustment::Set{..} => Ok(self.limit(principal)),
+        }
+    }
+
+    /// Пакет отклоняется целиком, если хотя бы одно повышение больше предела
+    ///
+    /// Возвращает предел для итоговых зарплат, как и check_raise
+    pub fn check_batch(&self, principal: &Principal, batch: &SalaryBatch) -> Result<Option<RaiseLimit>, AppError> {
+        match &batch.target {
+            SalaryBatchTarget::Items(items) => items.iter()
+                .try_for_each(|item| self.check_raise(principal, &item.percentage.percentage)
+                    .map(|_| ())
+                    .map_err(|e| e.for_employee(item.id.id)))?,
+            SalaryBatchTarget::Filter{percentage, ..} => {
+                self.check_raise(principal, &percentage.percentage)?;
+            },
+        }
+        Ok(self.limit(principal))
+    }
+
+    /// Предел для итоговых зарплат, None при праве UnlimitedRaises
+    fn limit(&self, principal: &Principal) -> Option<RaiseLimit> {
+        match principal.has(Permission::UnlimitedRaises) {
+            true => None,
+            false => Some(RaiseLimit{principal: principal.name.clone(), max_raise: self.max_raise}),
+        }
+    }
+
+    fn own_salary_only(&self, principal: &Principal) -> AppError {
+        AppError::Forbidden(format!("{} may read only their own salary", principal.name))
+    }
+
+    fn raise_denied(&self, principal: &Principal) -> AppError {
+        raise_denied(&principal.name, &self.max_raise)
+    }
+}
+
+
+/// Предел повышения зарплаты
+///
+/// Выдается AccessPolicy::check_raise и AccessPolicy::raise_limit и проверяется
+/// по зарплате, прочитанной в той же транзакции, что и изменение
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaiseLimit{
+    pub principal: String,
+    pub max_raise: Percentage,
+}
+
+impl RaiseLimit{
+    /// Проверить изменение зарплаты с old на new, сделанное изменением adjustment
+    ///
+    /// Повышение на процент сравнивается с повышением на max_raise с тем же округлением,
+    /// поэтому повышение ровно на предел не отклоняется из-за округления вверх.
+    /// Повышение на сумму или установкой значения сравнивается с точным процентом
+    pub fn check(&self, adjustment: &SalaryAdjustment, old: &Money, new: &Money) -> Result<(), AppError> {
+        if let SalaryAdjustment::Percentage{rounding, ..} = adjustment {
+            // Если предел не помещается в i64, то меньшее повышение new точно его не превышает
+            return match old.add_percentage(self.max_raise, *rounding) {
+                Ok(ceiling) if new.amount_minor > ceiling.amount_minor => Err(raise_denied(&self.principal, &self.max_raise)),
+                _ => Ok(()),
+            };
+        }
+        let delta = i128::from(new.amount_minor) - i128::from(old.amount_minor);
+        // delta / old > max_raise / 100, без деления
+        if delta * 10_000 > i128::from(self.max_raise.hundredths) * i128::from(old.amount_minor) {
+            return Err(raise_denied(&self.principal, &self.max_raise));
+        }
+        Ok(())
+    }
+}
+
+fn raise_denied(principal: &str, max_raise: &Percentage) -> AppError {
+    AppError::Forbidden(format!("{principal} may raise salaries by at most {max_raise}%, larger raises require the {} role", Role::PayrollAdmin))
+}
+
+
+#[cfg(test)]
+mod tests{
+    use crate::auth::{AuthMethod, Principal};
+    use crate::errors::AppError;
+    use crate::models::{BatchMode, Employee, EmployeeId, PersonName, SalaryAdjustment, SalaryBatch, SalaryBatchItem, SalaryBatchTarget, SalaryPercentage};
+    use crate::money::{Currency, Money, Percentage, RoundingMode};
+    use super::{AccessPolicy, Permission, RaiseLimit, Role};
+
+    fn principal(roles: &[Role], employee_id: Option<i32>) -> Principal {
+        Principal{name: "ivanov".to_owned(), method: AuthMethod::ApiKey, roles: roles.to_vec(), employee_id}
+    }
+
+    fn forbidden<T>(result: Result<T, AppError>) -> bool {
+        matches!(result, Err(AppError::Forbidden(_)))
+    }
+
+    #[test]
+    fn roles_test(){
+        assert_eq!(Role::PayrollAdmin, "payroll_admin".parse::<Role>().unwrap());
+        assert!("admin".parse::<Role>().is_err());
+        let viewer = principal(&[Role::Viewer], Some(1));
+        assert!(viewer.has(Permission::ReadOwnSalary));
+        assert!(forbidden(viewer.require(Permission::ReadSalaries)));
+        let hr = principal(&[Role::Viewer, Role::Hr], None);
+        assert!(hr.has(Permission::ManageEmployees));
+        assert!(!hr.has(Permission::DeleteEmployees));
+        assert!(forbidden(principal(&[], None).require(Permission::ReadOwnSalary)));
+        let monitoring = principal(&["monitoring".parse::<Role>().unwrap()], None);
+        assert!(monitoring.has(Permission::ReadMetrics));
+        assert!(forbidden(monitoring.require(Permission::ReadOwnSalary)));
+        assert!(!hr.has(Permission::ReadMetrics));
+    }
+
+    #[test]
+    fn salary_read_test(){
+        let policy = AccessPolicy::new(Percentage::whole(10));
+        assert!(policy.check_salary_read(&principal(&[Role::Viewer], Some(1)), 1).is_ok());
+        assert!(forbidden(policy.check_salary_read(&principal(&[Role::Viewer], Some(1)), 2)));
+        assert!(forbidden(policy.check_salary_read(&principal(&[Role::Viewer], None), 1)));
+        assert!(policy.check_salary_read(&principal(&[Role::Hr], None), 2).is_ok());
+
+        let viewer = principal(&[Role::Viewer], Some(1));
+        assert!(policy.check_salary_search(&viewer).is_ok());
+        assert!(forbidden(policy.check_salary_search(&principal(&[Role::Viewer], None))));
+        let employee = |id| Employee{id, name: "Test Employee".to_owned(), name_parts: PersonName::parse_legacy("Test Employee"), salary: Money::new(10000, Currency::Rub), personnel_number: None};
+        assert!(policy.check_salary_found(&viewer, Ok(employee(1))).is_ok());
+        let denied = policy.check_salary_found(&viewer, Ok(employee(2))).unwrap_err();
+        assert_eq!(denied, policy.check_salary_found(&viewer, Err(AppError::NotFound("employee does not exist".to_owned()))).unwrap_err());
+        assert_eq!(denied, policy.check_salary_found(&viewer, Err(AppError::Ambiguous{message: "several".to_owned(), candidates: vec![2, 3]})).unwrap_err());
+        assert!(matches!(policy.check_salary_found(&viewer, Err(AppError::StorageUnavailable("down".to_owned()))), Err(AppError::StorageUnavailable(_))));
+        assert!(matches!(policy.check_salary_found(&principal(&[Role::Hr], None), Err(AppError::NotFound("x".to_owned()))), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn raise_test(){
+        let policy = AccessPolicy::new(Percentage::whole(10));
+        let hr = principal(&[Role::Hr], None);
+        let admin = principal(&[Role::PayrollAdmin], None);
+        let limit = RaiseLimit{principal: "ivanov".to_owned(), max_raise: Percentage::whole(10)};
+        assert_eq!(Some(limit.clone()), policy.check_raise(&hr, &Percentage::whole(10)).unwrap());
+        assert!(policy.check_raise(&hr, &Percentage::whole(-50)).is_ok());
+        assert!(forbidden(policy.check_raise(&hr, &Percentage::new(1001))));
+        assert_eq!(None, policy.check_raise(&admin, &Percentage::whole(200)).unwrap());
+        assert!(forbidden(policy.check_raise(&principal(&[Role::Viewer], Some(1)), &Percentage::whole(1))));
+
+        let set = SalaryAdjustment::Set{amount_minor: 1, currency: None};
+        let percent = |percent| SalaryAdjustment::Percentage{percentage: Percentage::whole(percent), rounding: RoundingMode::default()};
+        assert_eq!(Some(limit.clone()), policy.raise_limit(&hr, &set).unwrap());
+        assert_eq!(None, policy.raise_limit(&admin, &set).unwrap());
+        assert_eq!(Some(limit.clone()), policy.raise_limit(&hr, &percent(5)).unwrap());
+        assert_eq!(None, policy.raise_limit(&admin, &percent(50)).unwrap());
+        assert!(matches!(policy.raise_limit(&hr, &percent(20)), Err(AppError::Forbidden(_))));
+        assert!(matches!(policy.raise_limit(&principal(&[Role::Viewer], Some(1)), &set), Err(AppError::Forbidden(_))));
+
+        let current = Money::new(100_000, Currency::Rub);
+        let salary = |amount_minor| Money::new(amount_minor, Currency::Rub);
+        assert!(limit.check(&set, &current, &salary(110_000)).is_ok());
+        assert!(limit.check(&set, &current, &salary(1)).is_ok());
+        assert!(forbidden(limit.check(&set, &current, &salary(110_001))));
+        assert!(forbidden(limit.check(&set, &salary(0), &salary(1))));
+        // Повышение ровно на 10% с округлением вверх укладывается в предел, посчитанный с тем же округлением
+        let ceil = |percent| SalaryAdjustment::Percentage{percentage: Percentage::whole(percent), rounding: RoundingMode::Ceil};
+        let floor = SalaryAdjustment::Percentage{percentage: Percentage::whole(10), rounding: RoundingMode::Floor};
+        assert!(limit.check(&ceil(10), &salary(10_001), &salary(11_002)).is_ok());
+        assert!(forbidden(limit.check(&floor, &salary(10_001), &salary(11_002))));
+        assert!(forbidden(limit.check(&ceil(11), &salary(10_001), &salary(11_102))));
+        assert!(forbidden(limit.check(&set, &salary(10_001), &salary(11_002))));
+        assert!(limit.check(&ceil(5), &salary(i64::MAX - 1), &salary(i64::MAX)).is_ok());
+
+        let batch = |percent| SalaryBatch{mode: BatchMode::default(), target: SalaryBatchTarget::Items(vec![
+            SalaryBatchItem{id: EmployeeId{id: 1}, percentage: SalaryPercentage{percentage: Percentage::whole(5), rounding: RoundingMode::default()}},
+            SalaryBatchItem{id: EmployeeId{id: 2}, percentage: SalaryPercentage{percentage: Percentage::whole(percent), rounding: RoundingMode::default()}},
+        ])};
+        assert_eq!(Some(limit), policy.check_batch(&hr, &batch(10)).unwrap());
+        let error = policy.check_batch(&hr, &batch(20)).unwrap_err();
+        assert!(error.to_string().contains("employee 2"), "{error}");
+    }
+}
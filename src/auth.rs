@@ -0,0 +1,557 @@
+use std::fs;
+use std::future::{ready, Future, Ready};
+use std::path::Path;
+use actix_service::Service;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, AUTHORIZATION};
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use futures::future::Either;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use log::{debug, warn};
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::{Context, KeyValue};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use crate::access::{Permission, Role};
+use crate::config::AuthConfig;
+use crate::errors::AppError;
+use crate::logging;
+
+
+/// Заголовок с ключом API
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Имя, от которого выполняются запросы при выключенной проверке подлинности
+pub const ANONYMOUS: &str = "anonymous";
+
+/// Наибольшая длина имени пользователя, как у инициатора в журнале аудита
+const MAX_PRINCIPAL_LENGTH: usize = 255;
+
+
+/// Способ, которым пользователь подтвердил подлинность
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthMethod{
+    /// Проверка подлинности выключена
+    Anonymous,
+    /// Ключ API в заголовке X-Api-Key
+    ApiKey,
+    /// Токен JWT в заголовке Authorization: Bearer
+    Jwt,
+}
+
+
+/// Пользователь или сервис, от имени которого выполняется запрос
+///
+/// Кладется в расширения запроса промежуточным обработчиком authenticate
+/// и извлекается обработчиками как аргумент. Без authenticate извлечь пользователя
+/// нельзя: обработчик вне проверенного набора эндпоинтов отвечает 401
+#[derive(Debug, Clone, PartialEq)]
+pub struct Principal{
+    pub name: String,
+    pub method: AuthMethod,
+    pub roles: Vec<Role>,
+    /// Запись о самом пользователе, если он сотрудник
+    pub employee_id: Option<i32>,
+}
+
+impl Principal{
+    /// Пользователь при выключенной проверке подлинности, ему разрешено все
+    ///
+    /// Создается только промежуточным обработчиком authenticate
+    pub fn anonymous() -> Principal {
+        Principal{name: ANONYMOUS.to_owned(), method: AuthMethod::Anonymous, roles: vec![Role::PayrollAdmin], employee_id: None}
+    }
+
+    fn new(name: &str, method: AuthMethod, roles: Vec<Role>, employee_id: Option<i32>) -> Result<Principal, AppError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Unauthorized("credentials do not name a principal".to_owned()));
+        }
+        Ok(Principal{name: name.chars().take(MAX_PRINCIPAL_LENGTH).collect(), method, roles, employee_id})
+    }
+
+    /// Дает ли право хотя бы одна из ролей
+    pub fn has(&self, permission: Permission) -> bool {
+        self.roles.iter().any(|role| role.permissions().contains(&permission))
+    }
+
+    pub fn require(&self, permission: Permission) -> Result<(), AppError> {
+        match self.has(permission) {
+            true => Ok(()),
+            false => Err(AppError::Forbidden(format!("{} lacks the {} permission", self.name, permission.name()))),
+        }
+    }
+}
+
+impl FromRequest for Principal{
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Principal, actix_web::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<Principal>()
+            .cloned()
+            .ok_or_else(|| {
+                warn!("{} {} is served without authentication", req.method(), req.path());
+                AppError::Unauthorized("request was not authenticated".to_owned()).into()
+            }))
+    }
+}
+
+
+/// Файл с ключами API
+///
+/// Ключи хранятся только в виде SHA-256, так что утечка файла не раскрывает сами ключи
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeysFile{
+    #[serde(default)]
+    key: Vec<ApiKeyEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeyEntry{
+    principal: String,
+    sha256: String,
+    #[serde(default)]
+    roles: Vec<String>,
+    employee_id: Option<i32>,
+}
+
+struct ApiKey{
+    principal: String,
+    digest: [u8; 32],
+    roles: Vec<Role>,
+    employee_id: Option<i32>,
+}
+
+/// SHA-256 ключа из шестнадцатеричной записи
+fn parse_digest(value: &str) -> Option<[u8; 32]> {
+    if value.len() != 64 || !value.is_ascii() {
+        return None;
+    }
+    let mut digest = [0u8; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(digest)
+}
+
+/// Сравнение, время которого не зависит от места первого различия
+fn digests_equal(left: &[u8; 32], right: &[u8; 32]) -> bool {
+    left.iter().zip(right).fold(0, |diff, (l, r)| diff | (l ^ r)) == 0
+}
+
+fn load_api_keys(path: &Path) -> Result<Vec<ApiKey>, AppError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| AppError::validation("auth.api_keys_file", &format!("cannot read API keys file {}: {e}", path.display())))?;
+    let file: ApiKeysFile = toml::from_str(&content)
+        .map_err(|e| AppError::validation("auth.api_keys_file", &format!("{} is not a valid API keys file: {e}", path.display())))?;
+    file.key.into_iter()
+        .map(|entry| {
+            let digest = parse_digest(&entry.sha256)
+                .ok_or(AppError::validation("auth.api_keys_file", &format!("key of \"{}\" must be a hex encoded SHA-256", entry.principal)))?;
+            let roles = entry.roles.iter()
+                .map(|role| role.parse::<Role>()
+                    .map_err(|_| AppError::validation("auth.api_keys_file", &format!("key of \"{}\" has unknown role \"{role}\"", entry.principal))))
+                .collect::<Result<_, _>>()?;
+            Ok(ApiKey{principal: entry.principal, digest, roles, employee_id: entry.employee_id})
+        })
+        .collect()
+}
+
+
+/// Открытый ключ из JWKS
+struct JwksKey{
+    kid: Option<String>,
+    key: DecodingKey,
+}
+
+fn load_jwks(path: &Path) -> Result<Vec<JwksKey>, AppError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| AppError::validation("auth.jwks_file", &format!("cannot read JWKS file {}: {e}", path.display())))?;
+    let jwks: JwkSet = serde_json::from_str(&content)
+        .map_err(|e| AppError::validation("auth.jwks_file", &format!("{} is not a valid JWKS: {e}", path.display())))?;
+    jwks.keys.iter()
+        .map(|jwk| {
+            let kid = jwk.common.key_id.clone();
+            let name = kid.as_deref().unwrap_or("without kid");
+            if !matches!(jwk.algorithm, AlgorithmParameters::RSA(_)) {
+                return Err(AppError::validation("auth.jwks_file", &format!("key {name} is not an RSA key")));
+            }
+            let key = DecodingKey::from_jwk(jwk)
+                .map_err(|e| AppError::validation("auth.jwks_file", &format!("key {name} is invalid: {e}")))?;
+            Ok(JwksKey{kid, key})
+        })
+        .collect()
+}
+
+
+/// Утверждения токена, которые нужны приложению
+#[derive(Deserialize)]
+struct Claims{
+    sub: String,
+    #[serde(default)]
+    roles: Vec<String>,
+    employee_id: Option<i32>,
+}
+
+/// Причина отказа в токене, понятная клиенту
+fn token_error(kind: &ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::ExpiredSignature => "token has expired",
+        ErrorKind::ImmatureSignature => "token is not valid yet",
+        ErrorKind::InvalidSignature => "token signature is invalid",
+        ErrorKind::InvalidIssuer => "token issuer is not accepted",
+        ErrorKind::InvalidAudience => "token audience is not accepted",
+        ErrorKind::MissingRequiredClaim(_) => "token lacks a required claim",
+        _ => "token is malformed",
+    }
+}
+
+
+/// Проверка подлинности запросов
+///
+/// Принимает ключи API и токены JWT с подписью HS256 (общий секрет) или RS256 (ключи из JWKS).
+/// Другие алгоритмы, в том числе none, отклоняются
+pub struct Authenticator{
+    enabled: bool,
+    api_keys: Vec<ApiKey>,
+    secret: Option<DecodingKey>,
+    jwks: Vec<JwksKey>,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl Authenticator{
+    /// Собрать проверку по настройкам, прочитав файлы ключей
+    ///
+    /// Включенная проверка без единого источника учетных данных считается ошибкой конфигурации
+    pub fn new(config: &AuthConfig) -> Result<Authenticator, AppError> {
+        if !config.enabled {
+            return Ok(Authenticator::disabled());
+        }
+        if config.api_keys_file.is_none() && config.jwt_secret.is_none() && config.jwks_file.is_none() {
+            return Err(AppError::validation("auth", "authentication is enabled, but none of auth.api_keys_file, auth.jwt_secret, auth.jwks_file is set"));
+        }
+        Ok(Authenticator{
+            enabled: true,
+            api_keys: config.api_keys_file.as_deref().map(load_api_keys).transpose()?.unwrap_or_default(),
+            secret: config.jwt_secret.as_ref().map(|secret| DecodingKey::from_secret(secret.as_bytes())),
+            jwks: config.jwks_file.as_deref().map(load_jwks).transpose()?.unwrap_or_default(),
+            issuer: config.jwt_issuer.clone(),
+            audience: config.jwt_audience.clone(),
+        })
+    }
+
+    /// Проверка выключена, все запросы выполняются от имени anonymous
+    pub fn disabled() -> Authenticator {
+        Authenticator{enabled: false, api_keys: Vec::new(), secret: None, jwks: Vec::new(), issuer: None, audience: None}
+    }
+
+    /// Определить пользователя по заголовкам запроса
+    pub fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, AppError> {
+        if !self.enabled {
+            return Ok(Principal::anonymous());
+        }
+        if let Some(key) = headers.get(API_KEY_HEADER) {
+            let key = key.to_str()
+                .map_err(|_| AppError::Unauthorized("API key is malformed".to_owned()))?;
+            return self.verify_api_key(key.trim());
+        }
+        if let Some(authorization) = headers.get(AUTHORIZATION) {
+            let token = authorization.to_str().ok()
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .ok_or(AppError::Unauthorized("Authorization header must carry a Bearer token".to_owned()))?;
+            return self.verify_token(token.trim());
+        }
+        Err(AppError::Unauthorized("credentials are required: pass X-Api-Key or Authorization: Bearer".to_owned()))
+    }
+
+    fn verify_api_key(&self, key: &str) -> Result<Principal, AppError> {
+        let digest: [u8; 32] = Sha256::digest(key.as_bytes()).into();
+        // Перебираются все ключи, чтобы время ответа не зависело от позиции найденного
+        let found = self.api_keys.iter()
+            .fold(None, |found, candidate| match digests_equal(&candidate.digest, &digest) {
+                true => Some(candidate),
+                false => found,
+            });
+        match found {
+            Some(key) => Principal::new(&key.principal, AuthMethod::ApiKey, key.roles.clone(), key.employee_id),
+            None => Err(AppError::Unauthorized("API key is not valid".to_owned())),
+        }
+    }
+
+    /// Ключ для проверки подписи токена
+    ///
+    /// Алгоритм берется из заголовка токена, поэтому каждому алгоритму сопоставлен
+    /// только свой тип ключа: RS256 никогда не проверяется общим секретом и наоборот
+    fn decoding_key(&self, algorithm: Algorithm, kid: Option<&str>) -> Option<&DecodingKey> {
+        match algorithm {
+            Algorithm::HS256 => self.secret.as_ref(),
+            Algorithm::RS256 => match kid {
+                Some(kid) => self.jwks.iter().find(|key| key.kid.as_deref() == Some(kid)),
+                None if self.jwks.len() == 1 => self.jwks.first(),
+                None => None,
+            }.map(|key| &key.key),
+            _ => None,
+        }
+    }
+
+    fn verify_token(&self, token: &str) -> Result<Principal, AppError> {
+        let header = decode_header(token)
+            .map_err(|e| AppError::Unauthorized(token_error(e.kind()).to_owned()))?;
+        let key = self.decoding_key(header.alg, header.kid.as_deref())
+            .ok_or(AppError::Unauthorized(format!("no accepted key for a {:?} token", header.alg)))?;
+        let mut validation = Validation::new(header.alg);
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        let token = decode::<Claims>(token, key, &validation)
+            .map_err(|e| AppError::Unauthorized(token_error(e.kind()).to_owned()))?;
+        // Токен может нести роли других систем, они не дают прав в этой
+        let roles = token.claims.roles.iter()
+            .filter_map(|role| role.parse().ok())
+            .collect();
+        Principal::new(&token.claims.sub, AuthMethod::Jwt, roles, token.claims.employee_id)
+    }
+}
+
+
+/// Проверить подлинность запроса
+///
+/// Подключается через wrap_fn на уровне набора эндпоинтов. Запрос без действительных
+/// учетных данных отклоняется с ответом 401, иначе пользователь становится доступен
+/// обработчикам через Principal и попадает в журнал и в спан запроса
+pub fn authenticate<S, B>(request: ServiceRequest, service: &S) -> impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    // Без настроенной проверки запросы отклоняются, а не пропускаются
+    let principal = match request.app_data::<web::Data<Authenticator>>() {
+        Some(authenticator) => authenticator.authenticate(request.headers()),
+        None => Err(AppError::Unauthorized("authentication is not configured".to_owned())),
+    };
+    match principal {
+        Ok(principal) => {
+            debug!("{} {} is authenticated as {} by {:?}", request.method(), request.path(), principal.name, principal.method);
+            Context::current().span().set_attribute(KeyValue::new("enduser.id", principal.name.clone()));
+            let name = principal.name.clone();
+            request.extensions_mut().insert(principal);
+            Either::Left(logging::with_principal(name, || service.call(request)))
+        },
+        Err(e) => {
+            warn!("{} {} is rejected: {e}", request.method(), request.path());
+            Either::Right(ready(Err(e.into())))
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod tests{
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use actix_service::Service;
+    use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App};
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use serde_json::json;
+    use crate::access::Role;
+    use crate::config::AuthConfig;
+    use crate::errors::AppError;
+    use crate::logging::current_principal;
+    use super::{authenticate, parse_digest, AuthMethod, Authenticator, Principal, API_KEY_HEADER};
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    fn testdata(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/auth").join(name)
+    }
+
+    fn config() -> AuthConfig {
+        AuthConfig{
+            enabled: true,
+            api_keys_file: Some(testdata("api_keys.toml")),
+            jwt_secret: Some(SECRET.to_owned()),
+            jwks_file: Some(testdata("jwks.json")),
+            jwt_issuer: None,
+            jwt_audience: None,
+            ..AuthConfig::default()
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn hs256(claims: serde_json::Value) -> String {
+        encode(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_secret(SECRET.as_bytes())).unwrap()
+    }
+
+    fn rs256(kid: Option<&str>, claims: serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = kid.map(str::to_owned);
+        let key = EncodingKey::from_rsa_pem(include_bytes!("../testdata/auth/rs256_private.pem")).unwrap();
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    fn headers(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(HeaderName::from_static(name), HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {token}")).unwrap());
+        headers
+    }
+
+    fn rejected(result: Result<Principal, AppError>) -> String {
+        match result {
+            Err(AppError::Unauthorized(msg)) => msg,
+            other => panic!("expected unauthorized, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn api_key_test(){
+        let authenticator = Authenticator::new(&config()).unwrap();
+        let principal = authenticator.authenticate(&headers(API_KEY_HEADER, "test-api-key")).unwrap();
+        assert_eq!(Principal{name: "payroll-bot".to_owned(), method: AuthMethod::ApiKey, roles: vec![Role::PayrollAdmin], employee_id: None}, principal);
+        let principal = authenticator.authenticate(&headers(API_KEY_HEADER, "hr-portal-key")).unwrap();
+        assert_eq!("hr-portal", principal.name);
+        assert_eq!(vec![Role::Hr], principal.roles);
+        let principal = authenticator.authenticate(&headers(API_KEY_HEADER, "viewer-key")).unwrap();
+        assert_eq!((vec![Role::Viewer], Some(1)), (principal.roles, principal.employee_id));
+        assert_eq!("API key is not valid", rejected(authenticator.authenticate(&headers(API_KEY_HEADER, "guessed-key"))));
+        rejected(authenticator.authenticate(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn jwt_test(){
+        let authenticator = Authenticator::new(&config()).unwrap();
+        let exp = now() + 600;
+
+        let principal = authenticator.authenticate(&bearer(&hs256(json!({"sub": "alice", "exp": exp})))).unwrap();
+        assert_eq!(Principal{name: "alice".to_owned(), method: AuthMethod::Jwt, roles: Vec::new(), employee_id: None}, principal);
+        // Незнакомые роли пропускаются
+        let principal = authenticator.authenticate(&bearer(&hs256(json!({"sub": "alice", "exp": exp, "roles": ["hr", "billing_admin"], "employee_id": 7})))).unwrap();
+        assert_eq!((vec![Role::Hr], Some(7)), (principal.roles, principal.employee_id));
+        let principal = authenticator.authenticate(&bearer(&rs256(Some("test-key"), json!({"sub": "bob", "exp": exp})))).unwrap();
+        assert_eq!("bob", principal.name);
+        // Единственный ключ JWKS подходит и токену без kid
+        let principal = authenticator.authenticate(&bearer(&rs256(None, json!({"sub": "bob", "exp": exp})))).unwrap();
+        assert_eq!("bob", principal.name);
+
+        assert_eq!("token has expired", rejected(authenticator.authenticate(&bearer(&hs256(json!({"sub": "alice", "exp": now() - 3600}))))));
+        rejected(authenticator.authenticate(&bearer(&hs256(json!({"sub": "alice"})))));
+        rejected(authenticator.authenticate(&bearer(&hs256(json!({"sub": " ", "exp": exp})))));
+        rejected(authenticator.authenticate(&bearer(&rs256(Some("other-key"), json!({"sub": "bob", "exp": exp})))));
+        rejected(authenticator.authenticate(&bearer("not.a.token")));
+        rejected(authenticator.authenticate(&headers("authorization", "Basic YWxpY2U6c2VjcmV0")));
+
+        let forged = encode(&Header::new(Algorithm::HS256), &json!({"sub": "alice", "exp": exp}), &EncodingKey::from_secret(b"another secret of the same length!")).unwrap();
+        assert_eq!("token signature is invalid", rejected(authenticator.authenticate(&bearer(&forged))));
+        let hs512 = encode(&Header::new(Algorithm::HS512), &json!({"sub": "alice", "exp": exp}), &EncodingKey::from_secret(SECRET.as_bytes())).unwrap();
+        rejected(authenticator.authenticate(&bearer(&hs512)));
+    }
+
+    #[test]
+    fn issuer_and_audience_test(){
+        let authenticator = Authenticator::new(&AuthConfig{
+            jwt_issuer: Some("https://sso.example.com".to_owned()),
+            jwt_audience: Some("salaries".to_owned()),
+            ..config()
+        }).unwrap();
+        let exp = now() + 600;
+        let token = hs256(json!({"sub": "alice", "exp": exp, "iss": "https://sso.example.com", "aud": "salaries"}));
+        assert_eq!("alice", authenticator.authenticate(&bearer(&token)).unwrap().name);
+        let token = hs256(json!({"sub": "alice", "exp": exp, "iss": "https://evil.example.com", "aud": "salaries"}));
+        assert_eq!("token issuer is not accepted", rejected(authenticator.authenticate(&bearer(&token))));
+        let token = hs256(json!({"sub": "alice", "exp": exp, "iss": "https://sso.example.com", "aud": "billing"}));
+        assert_eq!("token audience is not accepted", rejected(authenticator.authenticate(&bearer(&token))));
+    }
+
+    /// Обработчики получают пользователя, подтвержденного промежуточным обработчиком
+    #[actix_web::test]
+    async fn middleware_test(){
+        let whoami = || web::get().to(|principal: Principal| async move {
+            format!("{} {}", principal.name, current_principal().unwrap_or_default())
+        });
+        let app = actix_web::test::init_service(App::new()
+            .app_data(web::Data::new(Authenticator::new(&config()).unwrap()))
+            .service(web::scope("/whoami")
+                .wrap_fn(authenticate)
+                .route("", whoami()))
+            .route("/unprotected", whoami())
+        ).await;
+        let request = actix_web::test::TestRequest::get()
+            .uri("/whoami")
+            .insert_header((API_KEY_HEADER, "test-api-key"))
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("payroll-bot payroll-bot", actix_web::test::read_body(response).await);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/whoami")
+            .to_request();
+        let error = app.call(request).await.err().unwrap();
+        let response = error.error_response();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        assert_eq!("Bearer", response.headers().get(WWW_AUTHENTICATE).unwrap());
+
+        // Обработчик без authenticate не получает пользователя даже с верным ключом
+        let request = actix_web::test::TestRequest::get()
+            .uri("/unprotected")
+            .insert_header((API_KEY_HEADER, "test-api-key"))
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        // При выключенной проверке анонимного пользователя кладет authenticate
+        let app = actix_web::test::init_service(App::new()
+            .app_data(web::Data::new(Authenticator::disabled()))
+            .service(web::scope("/whoami")
+                .wrap_fn(authenticate)
+                .route("", whoami()))
+            .route("/unprotected", whoami())
+        ).await;
+        let request = actix_web::test::TestRequest::get()
+            .uri("/whoami")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!("anonymous anonymous", actix_web::test::read_body(response).await);
+        let request = actix_web::test::TestRequest::get()
+            .uri("/unprotected")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+
+    #[test]
+    fn config_test(){
+        let authenticator = Authenticator::new(&AuthConfig{enabled: false, ..AuthConfig::default()}).unwrap();
+        assert_eq!(Principal::anonymous(), authenticator.authenticate(&HeaderMap::new()).unwrap());
+
+        let error = Authenticator::new(&AuthConfig::default()).err().unwrap();
+        assert_eq!("auth", error.field_errors()[0].field);
+        let error = Authenticator::new(&AuthConfig{api_keys_file: Some(testdata("missing.toml")), ..AuthConfig::default()}).err().unwrap();
+        assert_eq!("auth.api_keys_file", error.field_errors()[0].field);
+        let error = Authenticator::new(&AuthConfig{jwks_file: Some(testdata("api_keys.toml")), ..AuthConfig::default()}).err().unwrap();
+        assert_eq!("auth.jwks_file", error.field_errors()[0].field);
+
+        assert_eq!(Some([0xab; 32]), parse_digest(&"AB".repeat(32)));
+        assert_eq!(None, parse_digest("abcd"));
+        assert_eq!(None, parse_digest(&"zz".repeat(32)));
+    }
+}
@@ -0,0 +1,524 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use crate::auth::Authenticator;
+use crate::config::{Config, ConfigArgs};
+use crate::errors::AppError;
+use crate::models::{PersonName, UncheckedEmployeeData, UncheckedEmployeeId, UncheckedEmployeeListQuery, UncheckedEmployeeName, UncheckedSalaryPercentage};
+use crate::money::Money;
+use crate::names::NamePolicy;
+use crate::postgres_client::{DBClient, DBClientPostgres};
+use crate::server::Server;
+
+
+/// Размер страницы при выгрузке сотрудников
+const EXPORT_PAGE_SIZE: i64 = 100;
+
+
+/// Сервис учета зарплат сотрудников
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli{
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    /// Без команды запускается HTTP-сервер
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command{
+    /// Запустить HTTP-сервер
+    Serve,
+    /// Миграции схемы базы данных
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Операции с сотрудниками напрямую через базу данных
+    #[command(subcommand)]
+    Employee(EmployeeCommand),
+    /// Добавить сотрудников из файла JSON Lines
+    Import(ImportArgs),
+    /// Выгрузить работающих сотрудников в формате JSON Lines
+    Export(ExportArgs),
+    /// Проверить конфигурацию и вывести ее без секретов
+    CheckConfig,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand{
+    /// Применить недостающие миграции
+    Apply,
+    /// Вывести примененные и ожидающие миграции
+    Status,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum EmployeeCommand{
+    /// Добавить сотрудника
+    Add(AddArgs),
+    /// Найти сотрудника по идентификатору или имени
+    Get(GetArgs),
+    /// Повысить зарплату сотрудника на процент
+    Raise(RaiseArgs),
+    /// Вывести страницу списка работающих сотрудников
+    List(ListArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct AddArgs{
+    /// Имя одной строкой
+    #[arg(long, required_unless_present = "surname")]
+    pub name: Option<String>,
+    /// Фамилия, вместе с --given-name заменяет --name
+    #[arg(long, conflicts_with = "name")]
+    pub surname: Option<String>,
+    #[arg(long, requires = "surname")]
+    pub given_name: Option<String>,
+    #[arg(long, requires = "surname")]
+    pub patronymic: Option<String>,
+    #[arg(long, requires = "surname")]
+    pub display_name: Option<String>,
+    /// Зарплата, например 8000.50
+    #[arg(long)]
+    pub salary: String,
+    /// Валюта: RUB, KZT или BYN
+    #[arg(long)]
+    pub currency: Option<String>,
+    #[arg(long)]
+    pub personnel_number: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct GetArgs{
+    /// Идентификатор сотрудника
+    #[arg(required_unless_present = "name")]
+    pub id: Option<i32>,
+    /// Имя сотрудника вместо идентификатора
+    #[arg(long, conflicts_with = "id")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct RaiseArgs{
+    /// Идентификатор сотрудника
+    pub id: i32,
+    /// Процент повышения, например 3.5
+    #[arg(long)]
+    pub percentage: String,
+    /// Способ округления: ceil, floor, half_up или half_even
+    #[arg(long)]
+    pub rounding: Option<String>,
+    /// Инициатор изменения для журнала зарплаты
+    #[arg(long, default_value = "cli")]
+    pub actor: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs{
+    #[arg(long)]
+    pub page: Option<i64>,
+    #[arg(long)]
+    pub per_page: Option<i64>,
+    /// Порядок: id, name, surname или salary, минус означает обратный порядок
+    #[arg(long, allow_hyphen_values = true)]
+    pub sort: Option<String>,
+    #[arg(long)]
+    pub name_prefix: Option<String>,
+    #[arg(long)]
+    pub surname_prefix: Option<String>,
+    #[arg(long)]
+    pub currency: Option<String>,
+    #[arg(long)]
+    pub min_salary: Option<String>,
+    #[arg(long)]
+    pub max_salary: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs{
+    /// Файл JSON Lines, "-" - стандартный ввод
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs{
+    /// Файл для выгрузки, по умолчанию стандартный вывод
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+
+/// Собрать непроверенную модель из аргументов командной строки
+///
+/// Аргументы проходят ту же десериализацию и проверку, что и параметры HTTP-запроса
+fn params<T: DeserializeOwned>(fields: Vec<(&str, Option<Value>)>) -> Result<T, AppError> {
+    let fields: Map<String, Value> = fields.into_iter()
+        .filter_map(|(field, value)| value.map(|value| (field.to_owned(), value)))
+        .collect();
+    serde_json::from_value(Value::Object(fields))
+        .map_err(|e| AppError::validation("arguments", &e.to_string()))
+}
+
+fn text(value: Option<String>) -> Option<Value> {
+    value.map(Value::String)
+}
+
+fn print_json(out: &mut dyn Write, value: &impl serde::Serialize) -> Result<(), Box<dyn Error>> {
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+
+/// Выполнить команду над сотрудниками
+///
+/// Результат выводится в out в том же виде, что и поле data ответа API
+pub async fn run_employee(command: EmployeeCommand, names: &NamePolicy, db_client: &dyn DBClient, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    match command {
+        EmployeeCommand::Add(args) => {
+            let data: UncheckedEmployeeData = params(vec![
+                ("name", text(args.name)),
+                ("surname", text(args.surname)),
+                ("given_name", text(args.given_name)),
+                ("patronymic", text(args.patronymic)),
+                ("display_name", text(args.display_name)),
+                ("salary", text(Some(args.salary))),
+                ("currency", text(args.currency)),
+                ("personnel_number", text(args.personnel_number)),
+            ])?;
+            let employee = db_client.add_new_employee(data.check_with(names)?).await?;
+            print_json(out, &employee)
+        },
+        EmployeeCommand::Get(GetArgs{id: Some(id), ..}) => {
+            let id: UncheckedEmployeeId = params(vec![("id", Some(Value::from(id)))])?;
+            let employee = db_client.get_employee_by_id(id.check()?).await?;
+            print_json(out, &employee)
+        },
+        EmployeeCommand::Get(GetArgs{name, ..}) => {
+            let name: UncheckedEmployeeName = params(vec![("name", text(name))])?;
+            let employee = db_client.get_employee_salary(name.check()?).await?;
+            print_json(out, &employee)
+        },
+        EmployeeCommand::Raise(args) => {
+            let id: UncheckedEmployeeId = params(vec![("id", Some(Value::from(args.id)))])?;
+            let percentage: UncheckedSalaryPercentage = params(vec![
+                ("percentage", text(Some(args.percentage))),
+                ("rounding", text(args.rounding)),
+            ])?;
+            let change = db_client.increase_employee_salary_by_id(id.check()?, percentage.check()?, None, args.actor).await?;
+            print_json(out, &change)
+        },
+        EmployeeCommand::List(args) => {
+            let query: UncheckedEmployeeListQuery = params(vec![
+                ("page", args.page.map(Value::from)),
+                ("per_page", args.per_page.map(Value::from)),
+                ("sort", text(args.sort)),
+                ("name_prefix", text(args.name_prefix)),
+                ("surname_prefix", text(args.surname_prefix)),
+                ("currency", text(args.currency)),
+                ("min_salary", text(args.min_salary)),
+                ("max_salary", text(args.max_salary)),
+            ])?;
+            let page = db_client.list_employees(query.check()?).await?;
+            print_json(out, &page)
+        },
+    }
+}
+
+
+/// Запись файла импорта
+///
+/// Совпадает с записью о сотруднике, которую выгружает export, идентификатор не учитывается
+#[derive(serde::Deserialize)]
+struct ImportRecord{
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    name_parts: Option<PersonName>,
+    salary: Money,
+    #[serde(default)]
+    personnel_number: Option<String>,
+}
+
+impl ImportRecord{
+    fn into_data(self) -> Result<UncheckedEmployeeData, AppError> {
+        let (name, parts) = match self.name_parts {
+            Some(parts) => (None, Some(parts)),
+            None => (self.name, None),
+        };
+        params(vec![
+            ("name", text(name)),
+            ("surname", text(parts.as_ref().map(|parts| parts.surname.clone()))),
+            ("given_name", text(parts.as_ref().and_then(|parts| parts.given_name.clone()))),
+            ("patronymic", text(parts.as_ref().and_then(|parts| parts.patronymic.clone()))),
+            ("display_name", text(parts.as_ref().and_then(|parts| parts.display_name.clone()))),
+            ("salary", text(Some(self.salary.format_amount()))),
+            ("currency", text(Some(self.salary.currency.code().to_owned()))),
+            ("personnel_number", text(self.personnel_number)),
+        ])
+    }
+}
+
+/// Ошибка в строке файла импорта
+#[derive(Debug, serde::Serialize, PartialEq)]
+pub struct ImportFailure{
+    pub line: usize,
+    pub error: String,
+}
+
+/// Итог импорта
+#[derive(Debug, serde::Serialize, PartialEq)]
+pub struct ImportReport{
+    pub imported: usize,
+    pub failed: usize,
+    pub failures: Vec<ImportFailure>,
+}
+
+/// Добавить сотрудников из JSON Lines
+///
+/// Каждая строка добавляется отдельно, ошибка в одной строке не мешает остальным. Пустые строки пропускаются
+pub async fn import(input: impl BufRead, names: &NamePolicy, db_client: &dyn DBClient) -> Result<ImportReport, Box<dyn Error>> {
+    let mut report = ImportReport{imported: 0, failed: 0, failures: Vec::new()};
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match serde_json::from_str::<ImportRecord>(&line) {
+            Ok(record) => match record.into_data().and_then(|data| data.check_with(names)) {
+                Ok(data) => db_client.add_new_employee(data).await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(AppError::validation("line", &e.to_string())),
+        };
+        match result {
+            Ok(_) => report.imported += 1,
+            Err(e) => {
+                report.failed += 1;
+                report.failures.push(ImportFailure{line: index + 1, error: e.to_string()});
+            },
+        }
+    }
+    Ok(report)
+}
+
+/// Выгрузить работающих сотрудников в JSON Lines
+///
+/// Сотрудники выбираются страницами по возрастанию идентификатора, каждая следующая страница
+/// начинается после последнего выгруженного сотрудника. Поэтому добавление и увольнение
+/// во время выгрузки не сдвигают страницы и не приводят к пропускам и повторам.
+/// Возвращает число выгруженных записей
+pub async fn export(db_client: &dyn DBClient, out: &mut dyn Write) -> Result<i64, Box<dyn Error>> {
+    let query: UncheckedEmployeeListQuery = params(vec![
+        ("per_page", Some(Value::from(EXPORT_PAGE_SIZE))),
+        ("sort", text(Some("id".to_owned()))),
+    ])?;
+    let mut query = query.check()?;
+    let mut exported = 0;
+    loop {
+        let page = db_client.list_employees(query.clone()).await?;
+        for employee in &page.employees {
+            serde_json::to_writer(&mut *out, employee)?;
+            writeln!(out)?;
+        }
+        exported += page.employees.len() as i64;
+        match page.employees.last() {
+            Some(last) if page.employees.len() as i64 == page.per_page => query.filter.after_id = Some(last.id),
+            _ => break,
+        }
+    }
+    out.flush()?;
+    Ok(exported)
+}
+
+
+/// Подключиться к базе данных и подготовить схему
+///
+/// Миграции применяются или проверяются согласно настройке database.migrations, как при старте сервера
+async fn connect(config: &Config) -> Result<DBClientPostgres, AppError> {
+    let db_client = DBClientPostgres::new(&config.database).await?;
+    db_client.init_db().await?;
+    Ok(db_client)
+}
+
+/// Выполнить команду
+pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let config = Config::load(&cli.config)?;
+    // Стандартный вывод не блокируется на все время команды: в него пишет журнал рабочих потоков сервера
+    let mut stdout = io::stdout();
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            Server::builder().config(config).build().start().await?;
+        },
+        Command::Migrate(MigrateCommand::Apply) => {
+            let db_client = DBClientPostgres::new(&config.database).await?
+                .with_migration_mode(crate::migrations::MigrationMode::Apply);
+            db_client.init_db().await?;
+            print_json(&mut stdout, &db_client.migration_status().await?)?;
+        },
+        Command::Migrate(MigrateCommand::Status) => {
+            let db_client = DBClientPostgres::new(&config.database).await?;
+            print_json(&mut stdout, &db_client.migration_status().await?)?;
+        },
+        Command::Employee(command) => {
+            let db_client = connect(&config).await?;
+            run_employee(command, &config.names, &db_client, &mut stdout).await?;
+        },
+        Command::Import(args) => {
+            let db_client = connect(&config).await?;
+            let report = match args.file.to_str() {
+                Some("-") => import(io::stdin().lock(), &config.names, &db_client).await?,
+                _ => import(BufReader::new(File::open(&args.file)?), &config.names, &db_client).await?,
+            };
+            print_json(&mut stdout, &report)?;
+            if report.failed > 0 {
+                Err(format!("{} of {} records were not imported", report.failed, report.failed + report.imported))?;
+            }
+        },
+        Command::Export(args) => {
+            let db_client = connect(&config).await?;
+            match args.output {
+                Some(path) => {
+                    let exported = export(&db_client, &mut BufWriter::new(File::create(&path)?)).await?;
+                    eprintln!("Exported {exported} employees to {}", path.display());
+                },
+                None => {
+                    export(&db_client, &mut stdout).await?;
+                },
+            }
+        },
+        Command::CheckConfig => {
+            // Файлы ключей читаются только при старте сервера, поэтому проверяются отдельно
+            Authenticator::new(&config.auth)?;
+            writeln!(stdout, "Configuration is valid")?;
+            writeln!(stdout, "{config:#?}")?;
+        },
+    }
+    Ok(())
+}
+
+/// Вывести ошибку команды в стандартный поток ошибок
+///
+/// Ошибки проверки, в том числе ошибки конфигурации, перечисляются по одной на строку
+pub fn report(error: &(dyn Error + 'static)) {
+    match error.downcast_ref::<AppError>() {
+        Some(error @ (AppError::Validation{..} | AppError::InvalidFields(_))) => {
+            eprintln!("Invalid input:");
+            for error in error.field_errors() {
+                eprintln!("  {}: {}", error.field, error.message);
+            }
+        },
+        _ => eprintln!("Error: {error}"),
+    }
+}
+
+
+#[cfg(test)]
+mod tests{
+    use std::sync::{Arc, Mutex};
+    use clap::{CommandFactory, Parser};
+    use crate::errors::AppError;
+    use crate::models::{Employee, EmployeePage, PersonName};
+    use crate::money::{Currency, Money};
+    use crate::names::NamePolicy;
+    use crate::postgres_client::MockDBClient;
+    use super::{export, import, run_employee, Cli, Command, EmployeeCommand};
+
+    fn employee(id: i32, name: &str) -> Employee {
+        Employee{id, name: name.to_owned(), name_parts: PersonName::parse_legacy(name), salary: Money::new(800000, Currency::Rub), personnel_number: None}
+    }
+
+    #[test]
+    fn cli_test(){
+        Cli::command().debug_assert();
+        let cli = Cli::try_parse_from(["app", "--port", "9000", "employee", "raise", "7", "--percentage", "3.5"]).unwrap();
+        assert_eq!(Some("9000".to_owned()), cli.config.port);
+        assert!(matches!(cli.command, Some(Command::Employee(EmployeeCommand::Raise(ref args))) if args.id == 7 && args.actor == "cli"));
+        // Флаги конфигурации допускаются и после команды
+        let cli = Cli::try_parse_from(["app", "employee", "list", "--sort", "-salary", "--set", "logging.stdout=false"]).unwrap();
+        assert_eq!(vec!["logging.stdout=false".to_owned()], cli.config.settings);
+        assert!(Cli::try_parse_from(["app", "employee", "get"]).is_err());
+        assert!(Cli::try_parse_from(["app", "employee", "add", "--name", "Иван Петров", "--surname", "Петров", "--salary", "100"]).is_err());
+        assert!(Cli::try_parse_from(["app"]).unwrap().command.is_none());
+    }
+
+    #[actix_web::test]
+    async fn employee_add_test(){
+        let mut db_client = MockDBClient::new();
+        db_client.expect_add_new_employee()
+            .times(1)
+            .returning(|data| Ok(Employee{id: 1, name: data.name.clone(), name_parts: data.name_parts, salary: data.salary, personnel_number: None}));
+        let cli = Cli::try_parse_from(["app", "employee", "add", "--surname", "Петров", "--given-name", "Василий", "--salary", "8000.50"]).unwrap();
+        let Some(Command::Employee(command)) = cli.command else { panic!("employee command expected") };
+        let mut out = Vec::new();
+        run_employee(command, &NamePolicy::default(), &db_client, &mut out).await.unwrap();
+        let added: Employee = serde_json::from_slice(&out).unwrap();
+        assert_eq!("Петров Василий", added.name);
+        assert_eq!(Money::new(800050, Currency::Rub), added.salary);
+
+        // Неверные аргументы отклоняются до обращения к базе
+        let cli = Cli::try_parse_from(["app", "employee", "add", "--name", "Иван 2", "--salary", "0"]).unwrap();
+        let Some(Command::Employee(command)) = cli.command else { panic!("employee command expected") };
+        let error = run_employee(command, &NamePolicy::default(), &db_client, &mut Vec::new()).await.unwrap_err();
+        let error = error.downcast_ref::<AppError>().unwrap();
+        assert_eq!(2, error.field_errors().len());
+    }
+
+    #[actix_web::test]
+    async fn import_test(){
+        let mut db_client = MockDBClient::new();
+        db_client.expect_add_new_employee()
+            .returning(|data| match data.name.as_str() {
+                "Занятое Имя" => Err(AppError::Conflict("employee already exists".to_owned())),
+                _ => Ok(Employee{id: 1, name: data.name.clone(), name_parts: data.name_parts, salary: data.salary, personnel_number: None}),
+            });
+        let input = [
+            r#"{"id": 5, "name": "Петров Василий", "name_parts": {"surname": "Петров", "given_name": "Василий", "patronymic": null, "display_name": null}, "salary": {"amount": "8000.00", "currency": "RUB"}, "personnel_number": null}"#,
+            "",
+            r#"{"name": "Занятое Имя", "salary": {"amount": "100", "currency": "KZT"}}"#,
+            r#"{"name": "Без Зарплаты"}"#,
+            r#"{"name": "Иван Сидоров", "salary": {"amount": "1.5", "currency": "BYN"}}"#,
+        ].join("\n");
+        let report = import(input.as_bytes(), &NamePolicy::default(), &db_client).await.unwrap();
+        assert_eq!(2, report.imported);
+        assert_eq!(2, report.failed);
+        assert_eq!(vec![3, 4], report.failures.iter().map(|failure| failure.line).collect::<Vec<usize>>());
+    }
+
+    #[actix_web::test]
+    async fn export_test(){
+        // Во время выгрузки после первой страницы увольняют уже выгруженного сотрудника и добавляют нового
+        let staff = Arc::new(Mutex::new((1..=250).collect::<Vec<i32>>()));
+        let mut db_client = MockDBClient::new();
+        let calls = Arc::new(Mutex::new(0));
+        db_client.expect_list_employees()
+            .times(3)
+            .returning(move |query| {
+                let mut staff = staff.lock().unwrap();
+                let mut calls = calls.lock().unwrap();
+                *calls += 1;
+                if *calls == 2 {
+                    staff.retain(|&id| id != 5);
+                    staff.push(251);
+                }
+                assert_eq!(1, query.page.page);
+                let employees: Vec<Employee> = staff.iter()
+                    .filter(|&&id| query.filter.after_id.is_none_or(|after| id > after))
+                    .take(query.page.per_page as usize)
+                    .map(|&id| employee(id, "Петров Василий"))
+                    .collect();
+                Ok(EmployeePage{employees, page: query.page.page, per_page: query.page.per_page, total: staff.len() as i64})
+            });
+        let mut out = Vec::new();
+        assert_eq!(251, export(&db_client, &mut out).await.unwrap());
+        let ids: Vec<i32> = String::from_utf8(out).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Employee>(line).unwrap().id)
+            .collect();
+        assert_eq!((1..=251).collect::<Vec<i32>>(), ids);
+    }
+}
@@ -0,0 +1,962 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use std::{env, fs};
+use log::LevelFilter;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use crate::errors::AppError;
+use crate::logging::{parse_module_levels, LogFormat, RotationPeriod};
+use crate::migrations::MigrationMode;
+use crate::money::Percentage;
+use crate::names::{NamePolicy, MAX_NAME_LENGTH};
+use crate::postgres_client::UniquenessPolicy;
+use crate::telemetry::TraceExporter;
+
+
+/// Настройка приложения
+///
+/// Ключ задает настройку в файле конфигурации и флаге --set, переменная окружения - в окружении
+struct Setting{
+    key: &'static str,
+    env: &'static str,
+    /// Значение не выводится в сообщениях об ошибках
+    secret: bool,
+}
+
+const fn setting(key: &'static str, env: &'static str) -> Setting {
+    Setting{key, env, secret: false}
+}
+
+const fn secret(key: &'static str, env: &'static str) -> Setting {
+    Setting{key, env, secret: true}
+}
+
+/// Все настройки приложения
+const SETTINGS: &[Setting] = &[
+    setting("http.host", "HOST"),
+    setting("http.port", "PORT"),
+    setting("http.workers", "HTTP_WORKERS"),
+    setting("http.docs", "API_DOCS"),
+    setting("http.metrics", "API_METRICS"),
+    setting("http.shutdown_delay", "SHUTDOWN_DELAY"),
+    setting("http.shutdown_timeout", "SHUTDOWN_TIMEOUT"),
+    secret("database.url", "DATABASE_URL"),
+    setting("database.host", "DB_CONTAINER_NAME"),
+    setting("database.port", "DB_PORT"),
+    setting("database.name", "DB_NAME"),
+    setting("database.username", "DB_USERNAME"),
+    secret("database.password", "DB_PASSWORD"),
+    setting("database.max_connections", "DB_MAX_CONNECTIONS"),
+    setting("database.min_connections", "DB_MIN_CONNECTIONS"),
+    setting("database.connect_timeout", "DB_CONNECT_TIMEOUT"),
+    setting("database.idle_timeout", "DB_IDLE_TIMEOUT"),
+    setting("database.migrations", "DB_MIGRATIONS"),
+    setting("database.uniqueness", "EMPLOYEE_UNIQUENESS"),
+    setting("logging.level", "LOG_LEVEL"),
+    setting("logging.modules", "LOG_MODULES"),
+    setting("logging.format", "LOG_FORMAT"),
+    setting("logging.file", "LOG_FILE"),
+    setting("logging.max_size", "LOG_MAX_SIZE"),
+    setting("logging.rotation", "LOG_ROTATION"),
+    setting("logging.keep", "LOG_KEEP"),
+    setting("logging.stdout", "LOG_STDOUT"),
+    setting("tracing.exporter", "TRACING_EXPORTER"),
+    setting("tracing.endpoint", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"),
+    setting("tracing.service_name", "OTEL_SERVICE_NAME"),
+    setting("tracing.sample_ratio", "TRACING_SAMPLE_RATIO"),
+    setting("auth.enabled", "AUTH_ENABLED"),
+    setting("auth.api_keys_file", "AUTH_API_KEYS_FILE"),
+    secret("auth.jwt_secret", "AUTH_JWT_SECRET"),
+    setting("auth.jwks_file", "AUTH_JWKS_FILE"),
+    setting("auth.jwt_issuer", "AUTH_JWT_ISSUER"),
+    setting("auth.jwt_audience", "AUTH_JWT_AUDIENCE"),
+    setting("auth.max_raise_percentage", "AUTH_MAX_RAISE_PERCENTAGE"),
+    setting("names.max_length", "NAME_MAX_LENGTH"),
+    setting("names.mixed_scripts", "NAME_MIXED_SCRIPTS"),
+    setting("names.digits", "NAME_DIGITS"),
+    setting("names.punctuation", "NAME_PUNCTUATION"),
+];
+
+fn find_setting(key: &str) -> Option<&'static Setting> {
+    SETTINGS.iter().find(|setting| setting.key == key)
+}
+
+
+/// Значение настройки и источник, из которого оно взято
+#[derive(Debug, Clone, PartialEq)]
+struct Value{
+    raw: String,
+    source: String,
+}
+
+
+/// Значения настроек из одного источника
+///
+/// Источники накладываются друг на друга: файл конфигурации, переменные окружения, флаги командной строки
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigLayer{
+    values: BTreeMap<&'static str, Value>,
+}
+
+impl ConfigLayer{
+    /// Задать значение настройки по ключу
+    pub fn set(&mut self, key: &str, value: &str, source: &str) -> Result<(), AppError> {
+        let setting = find_setting(key)
+            .ok_or(AppError::validation("config", &format!("unknown setting \"{key}\" in {source}")))?;
+        self.values.insert(setting.key, Value{raw: value.to_owned(), source: source.to_owned()});
+        Ok(())
+    }
+
+    /// Значения из файла конфигурации в формате TOML
+    ///
+    /// Настройки сгруппированы по разделам: [http], [database], [logging], [tracing], [auth], [names]
+    pub fn from_toml(content: &str, path: &Path) -> Result<ConfigLayer, AppError> {
+        let source = format!("config file {}", path.display());
+        let table: toml::Table = content.parse()
+            .map_err(|e| AppError::validation("config", &format!("{source} is not valid TOML: {e}")))?;
+        let mut layer = ConfigLayer::default();
+        let mut violations = Vec::new();
+        for (section, values) in table {
+            let Some(values) = values.as_table() else {
+                violations.push(AppError::validation("config", &format!("unknown setting \"{section}\" in {source}")));
+                continue;
+            };
+            for (name, value) in values {
+                let raw = match value {
+                    toml::Value::String(value) => value.clone(),
+                    toml::Value::Integer(value) => value.to_string(),
+                    toml::Value::Boolean(value) => value.to_string(),
+                    toml::Value::Float(value) => value.to_string(),
+                    _ => {
+                        violations.push(AppError::validation("config", &format!("setting \"{section}.{name}\" in {source} must be a string, number or boolean")));
+                        continue;
+                    },
+                };
+                if let Err(e) = layer.set(&format!("{section}.{name}"), &raw, &source) {
+                    violations.push(e);
+                }
+            }
+        }
+        match AppError::from_violations(violations) {
+            Some(error) => Err(error),
+            None => Ok(layer),
+        }
+    }
+
+    /// Значения из файла конфигурации
+    pub fn from_file(path: &Path) -> Result<ConfigLayer, AppError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| AppError::validation("config", &format!("cannot read config file {}: {e}", path.display())))?;
+        ConfigLayer::from_toml(&content, path)
+    }
+
+    /// Значения из переменных окружения
+    pub fn from_env() -> ConfigLayer {
+        ConfigLayer::from_vars(|name| env::var(name).ok())
+    }
+
+    /// Значения из переменных, которые возвращает var
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> ConfigLayer {
+        let mut layer = ConfigLayer::default();
+        for setting in SETTINGS {
+            if let Some(value) = var(setting.env) {
+                layer.values.insert(setting.key, Value{raw: value, source: format!("environment variable {}", setting.env)});
+            }
+        }
+        layer
+    }
+
+    /// Значения из флагов командной строки
+    pub fn from_args(args: &ConfigArgs) -> Result<ConfigLayer, AppError> {
+        let mut layer = ConfigLayer::default();
+        let mut violations = Vec::new();
+        for (key, flag, value) in [
+            ("http.host", "--host", &args.host),
+            ("http.port", "--port", &args.port),
+            ("database.url", "--database-url", &args.database_url),
+            ("logging.level", "--log-level", &args.log_level),
+        ] {
+            if let Some(value) = value {
+                layer.set(key, value, &format!("flag {flag}"))?;
+            }
+        }
+        for setting in &args.settings {
+            let result = match setting.split_once('=') {
+                Some((key, value)) => layer.set(key.trim(), value, "flag --set"),
+                None => Err(AppError::validation("config", &format!("flag --set expects KEY=VALUE, got \"{setting}\""))),
+            };
+            if let Err(e) = result {
+                violations.push(e);
+            }
+        }
+        match AppError::from_violations(violations) {
+            Some(error) => Err(error),
+            None => Ok(layer),
+        }
+    }
+}
+
+
+/// Флаги командной строки с настройками
+///
+/// Перекрывают файл конфигурации и переменные окружения
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct ConfigArgs{
+    /// Файл конфигурации в формате TOML, по умолчанию берется из переменной APP_CONFIG
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+    /// Адрес, на котором слушает HTTP-сервер
+    #[arg(long, global = true)]
+    pub host: Option<String>,
+    /// Порт HTTP-сервера
+    #[arg(long, global = true)]
+    pub port: Option<String>,
+    /// Строка подключения к базе данных postgres://...
+    #[arg(long, value_name = "URL", global = true)]
+    pub database_url: Option<String>,
+    /// Уровень логирования: off, error, warn, info, debug или trace
+    #[arg(long, value_name = "LEVEL", global = true)]
+    pub log_level: Option<String>,
+    /// Любая настройка в виде ключ=значение, например --set database.max_connections=20
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    pub settings: Vec<String>,
+}
+
+
+/// Разбор значений настроек
+///
+/// Копит ошибки, чтобы сообщить обо всех неверных и недостающих настройках разом
+struct Resolver{
+    values: BTreeMap<&'static str, Value>,
+    violations: Vec<AppError>,
+}
+
+impl Resolver{
+    /// Разобрать значение настройки, если оно задано
+    fn get<T>(&mut self, key: &'static str, parse: impl Fn(&str) -> Result<T, String>) -> Option<T> {
+        let value = self.values.get(key)?;
+        match parse(value.raw.trim()) {
+            Ok(value) => Some(value),
+            Err(message) => {
+                let shown = match find_setting(key) {
+                    Some(setting) if setting.secret => String::new(),
+                    _ => format!(" {:?}", value.raw),
+                };
+                self.violations.push(AppError::validation(key, &format!("invalid value{shown} from {}: {message}", value.source)));
+                None
+            },
+        }
+    }
+
+    /// Разобрать обязательную настройку
+    fn required<T>(&mut self, key: &'static str, parse: impl Fn(&str) -> Result<T, String>) -> Option<T> {
+        if !self.values.contains_key(key) {
+            self.missing(key);
+        }
+        self.get(key, parse)
+    }
+
+    fn missing(&mut self, key: &'static str) {
+        let env = find_setting(key).map(|setting| setting.env).unwrap_or_default();
+        self.violations.push(AppError::validation(key, &format!("setting is required: set {env}, --set {key}=... or {key} in the config file")));
+    }
+
+    fn invalid(&mut self, key: &'static str, message: &str) {
+        self.violations.push(AppError::validation(key, message));
+    }
+}
+
+fn parse_text(value: &str) -> Result<String, String> {
+    match value {
+        "" => Err("value cannot be empty".to_owned()),
+        value => Ok(value.to_owned()),
+    }
+}
+
+fn parse_number<T: FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| "value must be a non-negative integer".to_owned())
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err("value must be either \"true\" or \"false\"".to_owned()),
+    }
+}
+
+fn parse_switch(value: &str) -> Result<bool, String> {
+    match value {
+        "allow" => Ok(true),
+        "reject" => Ok(false),
+        _ => Err("value must be either \"allow\" or \"reject\"".to_owned()),
+    }
+}
+
+/// Длительность в целых секундах
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value.parse().map(Duration::from_secs).map_err(|_| "value must be a whole number of seconds".to_owned())
+}
+
+/// Размер в байтах, допускаются суффиксы KB, MB и GB
+fn parse_size(value: &str) -> Result<u64, String> {
+    let upper = value.to_uppercase();
+    let (number, multiplier) = [("KB", 1 << 10), ("MB", 1 << 20), ("GB", 1 << 30)].into_iter()
+        .find_map(|(suffix, multiplier)| upper.strip_suffix(suffix).map(|number| (number.trim(), multiplier)))
+        .unwrap_or((upper.as_str(), 1));
+    number.parse::<u64>().ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .filter(|size| *size > 0)
+        .ok_or("value must be a positive size like 10485760, 512KB or 10MB".to_owned())
+}
+
+/// Разобрать значение перечисления, сообщение об ошибке берется из FromStr
+fn parse_enum<T: FromStr<Err = AppError>>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|e: AppError| match e {
+        AppError::Validation{message, ..} => message,
+        e => e.to_string(),
+    })
+}
+
+/// Секрет, скопированный из примера вместо сгенерированного
+///
+/// Такой секрет известен всем, кто видел пример, и позволяет выпустить токен с любыми ролями
+fn is_placeholder_secret(secret: &str) -> bool {
+    let normalized: String = secret.to_lowercase().chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    ["changeme", "replaceme", "yoursecret", "secretkey", "placeholder"].iter().any(|marker| normalized.contains(marker))
+        || normalized.chars().all(|c| c == normalized.chars().next().unwrap_or_default())
+}
+
+/// Неотрицательный процент, не больше двух знаков после точки
+fn parse_percentage(value: &str) -> Result<Percentage, String> {
+    Percentage::parse(value, "percentage").ok()
+        .filter(|percentage| percentage.hundredths >= 0)
+        .ok_or("value must be a non-negative percentage with at most 2 digits after the point".to_owned())
+}
+
+/// Пустое значение означает, что настройка выключена
+fn parse_optional<T>(parse: impl Fn(&str) -> Result<T, String>) -> impl Fn(&str) -> Result<Option<T>, String> {
+    move |value| match value {
+        "" => Ok(None),
+        value => parse(value).map(Some),
+    }
+}
+
+
+/// Настройки HTTP-сервера
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpConfig{
+    pub host: String,
+    pub port: u16,
+    /// Число рабочих потоков, по умолчанию по числу ядер
+    pub workers: Option<usize>,
+    /// Отдавать ли спецификацию OpenAPI и страницу документации
+    pub docs: bool,
+    /// Отдавать ли метрики Prometheus по адресу /metrics
+    pub metrics: bool,
+    /// Сколько после сигнала остановки проверка готовности отвечает 503, прежде чем сервер перестанет принимать соединения
+    pub shutdown_delay: Duration,
+    /// Сколько ждать завершения начатых запросов при остановке
+    pub shutdown_timeout: Duration,
+}
+
+impl Default for HttpConfig{
+    fn default() -> Self {
+        HttpConfig{
+            host: "0.0.0.0".to_owned(),
+            port: 8080,
+            workers: None,
+            docs: true,
+            metrics: true,
+            shutdown_delay: Duration::from_secs(5),
+            shutdown_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+
+/// Настройки подключения к базе данных
+///
+/// Строка подключения url задает подключение целиком, отдельные параметры перекрывают ее части.
+/// Без строки подключения имя пользователя и пароль обязательны
+#[derive(Clone, PartialEq)]
+pub struct DatabaseConfig{
+    pub url: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub name: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// Наибольшее время ожидания соединения из пула
+    pub connect_timeout: Duration,
+    /// Через сколько простоя соединение закрывается, None - никогда
+    pub idle_timeout: Option<Duration>,
+    pub migrations: MigrationMode,
+    pub uniqueness: UniquenessPolicy,
+}
+
+impl Default for DatabaseConfig{
+    fn default() -> Self {
+        DatabaseConfig{
+            url: None,
+            host: None,
+            port: None,
+            name: None,
+            username: None,
+            password: None,
+            max_connections: 7,
+            min_connections: 0,
+            connect_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+            migrations: MigrationMode::default(),
+            uniqueness: UniquenessPolicy::default(),
+        }
+    }
+}
+
+impl Debug for DatabaseConfig{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hidden = |value: &Option<String>| value.as_ref().map(|_| "<hidden>");
+        f.debug_struct("DatabaseConfig")
+            .field("url", &hidden(&self.url))
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("name", &self.name)
+            .field("username", &self.username)
+            .field("password", &hidden(&self.password))
+            .field("max_connections", &self.max_connections)
+            .field("min_connections", &self.min_connections)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("migrations", &self.migrations)
+            .field("uniqueness", &self.uniqueness)
+            .finish()
+    }
+}
+
+impl DatabaseConfig{
+    /// Параметры подключения к базе данных
+    pub fn connect_options(&self) -> Result<PgConnectOptions, AppError> {
+        let mut options = match &self.url {
+            Some(url) => PgConnectOptions::from_str(url)
+                .map_err(|e| AppError::validation("database.url", &format!("invalid connection string: {e}")))?,
+            None => PgConnectOptions::new(),
+        };
+        if let Some(host) = &self.host {
+            options = options.host(host);
+        }
+        if let Some(port) = self.port {
+            options = options.port(port);
+        }
+        if let Some(name) = &self.name {
+            options = options.database(name);
+        }
+        if let Some(username) = &self.username {
+            options = options.username(username);
+        }
+        if let Some(password) = &self.password {
+            options = options.password(password);
+        }
+        Ok(options)
+    }
+
+    /// Параметры пула соединений
+    pub fn pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(self.connect_timeout)
+            .idle_timeout(self.idle_timeout)
+    }
+}
+
+
+/// Настройки логирования
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggingConfig{
+    pub level: LevelFilter,
+    /// Уровни отдельных модулей, перекрывающие level
+    pub modules: Vec<(String, LevelFilter)>,
+    pub format: LogFormat,
+    /// Файл журнала, None - журнал в файл не пишется
+    pub file: Option<PathBuf>,
+    /// Размер файла журнала, после которого он ротируется, None - без ограничения
+    pub max_size: Option<u64>,
+    pub rotation: RotationPeriod,
+    /// Сколько архивных файлов журнала хранить
+    pub keep: usize,
+    /// Дублировать ли журнал в стандартный вывод
+    pub stdout: bool,
+}
+
+impl Default for LoggingConfig{
+    fn default() -> Self {
+        LoggingConfig{
+            level: LevelFilter::Debug,
+            modules: Vec::new(),
+            format: LogFormat::Text,
+            file: Some(PathBuf::from("app.log")),
+            max_size: Some(10 << 20),
+            rotation: RotationPeriod::Daily,
+            keep: 7,
+            stdout: true,
+        }
+    }
+}
+
+
+/// Настройки трассировки
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracingConfig{
+    pub exporter: TraceExporter,
+    /// Адрес коллектора для экспортера otlp, принимающего спаны по OTLP/HTTP
+    pub endpoint: String,
+    /// Имя сервиса, под которым спаны видны в коллекторе
+    pub service_name: String,
+    /// Доля трасс, начатых этим сервисом, которые записываются, от 0 до 1.
+    /// Для запросов с traceparent решение принимает вызывающий сервис
+    pub sample_ratio: f64,
+}
+
+impl Default for TracingConfig{
+    fn default() -> Self {
+        TracingConfig{
+            exporter: TraceExporter::None,
+            endpoint: "http://localhost:4318/v1/traces".to_owned(),
+            service_name: env!("CARGO_PKG_NAME").to_owned(),
+            sample_ratio: 1.0,
+        }
+    }
+}
+
+
+/// Настройки проверки подлинности и прав доступа к /employee и /admin
+///
+/// Файлы ключей читаются при старте сервера, см. auth::Authenticator
+#[derive(Clone, PartialEq)]
+pub struct AuthConfig{
+    /// Требовать ли учетные данные, без проверки все запросы выполняются от имени anonymous
+    pub enabled: bool,
+    /// Файл TOML с хешами ключей API
+    pub api_keys_file: Option<PathBuf>,
+    /// Общий секрет для токенов JWT с подписью HS256
+    pub jwt_secret: Option<String>,
+    /// Файл JWKS с открытыми ключами для токенов JWT с подписью RS256
+    pub jwks_file: Option<PathBuf>,
+    /// Ожидаемый издатель токенов (iss), None - не проверяется
+    pub jwt_issuer: Option<String>,
+    /// Ожидаемый получатель токенов (aud), None - не проверяется
+    pub jwt_audience: Option<String>,
+    /// Наибольшее повышение зарплаты без роли payroll_admin
+    pub max_raise_percentage: Percentage,
+}
+
+impl Default for AuthConfig{
+    fn default() -> Self {
+        AuthConfig{
+            enabled: true,
+            api_keys_file: None,
+            jwt_secret: None,
+            jwks_file: None,
+            jwt_issuer: None,
+            jwt_audience: None,
+            max_raise_percentage: Percentage::whole(10),
+        }
+    }
+}
+
+impl Debug for AuthConfig{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("enabled", &self.enabled)
+            .field("api_keys_file", &self.api_keys_file)
+            .field("jwt_secret", &self.jwt_secret.as_ref().map(|_| "<hidden>"))
+            .field("jwks_file", &self.jwks_file)
+            .field("jwt_issuer", &self.jwt_issuer)
+            .field("jwt_audience", &self.jwt_audience)
+            .field("max_raise_percentage", &self.max_raise_percentage)
+            .finish()
+    }
+}
+
+
+/// Конфигурация приложения
+///
+/// Собирается из значений по умолчанию, файла конфигурации, переменных окружения и флагов
+/// командной строки, каждый следующий источник перекрывает предыдущие
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config{
+    pub http: HttpConfig,
+    pub database: DatabaseConfig,
+    pub logging: LoggingConfig,
+    pub tracing: TracingConfig,
+    pub auth: AuthConfig,
+    pub names: NamePolicy,
+}
+
+impl Config{
+    /// Загрузить конфигурацию при старте приложения
+    ///
+    /// Файл конфигурации берется из флага --config или переменной окружения APP_CONFIG.
+    /// Ошибка перечисляет все неверные и недостающие настройки
+    pub fn load(args: &ConfigArgs) -> Result<Config, AppError> {
+        let mut layers = Vec::new();
+        let mut violations = Vec::new();
+        let path = args.config.clone().or(env::var_os("APP_CONFIG").map(PathBuf::from));
+        if let Some(path) = path {
+            match ConfigLayer::from_file(&path) {
+                Ok(layer) => layers.push(layer),
+                Err(e) => violations.push(e),
+            }
+        }
+        layers.push(ConfigLayer::from_env());
+        match ConfigLayer::from_args(args) {
+            Ok(layer) => layers.push(layer),
+            Err(e) => violations.push(e),
+        }
+        match Config::resolve(&layers) {
+            Ok(config) if violations.is_empty() => Ok(config),
+            Ok(_) => Err(AppError::from_violations(violations).unwrap()),
+            Err(e) => {
+                violations.push(e);
+                Err(AppError::from_violations(violations).unwrap())
+            },
+        }
+    }
+
+    /// Конфигурация для тестов с базой данных
+    ///
+    /// Без переменных окружения подключается к тестовой базе из docker-compose
+    pub fn test() -> Result<Config, AppError> {
+        let mut defaults = ConfigLayer::default();
+        for (key, value) in [("database.host", "localhost"), ("database.name", "username"), ("database.username", "username"), ("database.password", "password")] {
+            defaults.set(key, value, "test defaults")?;
+        }
+        Config::resolve(&[defaults, ConfigLayer::from_env()])
+    }
+
+    /// Собрать конфигурацию из источников
+    ///
+    /// Источники перечисляются по возрастанию приоритета
+    pub fn resolve(layers: &[ConfigLayer]) -> Result<Config, AppError> {
+        let mut values = BTreeMap::new();
+        for layer in layers {
+            values.extend(layer.values.clone());
+        }
+        let mut r = Resolver{values, violations: Vec::new()};
+        let defaults = Config::default();
+
+        let http = HttpConfig{
+            host: r.get("http.host", parse_text).unwrap_or(defaults.http.host),
+            port: r.get("http.port", parse_number).unwrap_or(defaults.http.port),
+            workers: r.get("http.workers", parse_optional(parse_number)).unwrap_or(defaults.http.workers),
+            docs: r.get("http.docs", parse_bool).unwrap_or(defaults.http.docs),
+            metrics: r.get("http.metrics", parse_bool).unwrap_or(defaults.http.metrics),
+            shutdown_delay: r.get("http.shutdown_delay", parse_seconds).unwrap_or(defaults.http.shutdown_delay),
+            shutdown_timeout: r.get("http.shutdown_timeout", parse_seconds).unwrap_or(defaults.http.shutdown_timeout),
+        };
+        if http.workers == Some(0) {
+            r.invalid("http.workers", "at least one worker is required");
+        }
+
+        let url = r.get("database.url", |value| match value.starts_with("postgres://") || value.starts_with("postgresql://") {
+            true => Ok(value.to_owned()),
+            false => Err("connection string must start with postgres:// or postgresql://".to_owned()),
+        });
+        let (username, password) = match url {
+            Some(_) => (r.get("database.username", parse_text), r.get("database.password", parse_text)),
+            None => (r.required("database.username", parse_text), r.required("database.password", parse_text)),
+        };
+        let database = DatabaseConfig{
+            url,
+            host: r.get("database.host", parse_text),
+            port: r.get("database.port", parse_number),
+            name: r.get("database.name", parse_optional(parse_text)).flatten(),
+            username,
+            password,
+            max_connections: r.get("database.max_connections", parse_number).unwrap_or(defaults.database.max_connections),
+            min_connections: r.get("database.min_connections", parse_number).unwrap_or(defaults.database.min_connections),
+            connect_timeout: r.get("database.connect_timeout", parse_seconds).unwrap_or(defaults.database.connect_timeout),
+            idle_timeout: r.get("database.idle_timeout", parse_optional(parse_seconds)).unwrap_or(defaults.database.idle_timeout),
+            migrations: r.get("database.migrations", parse_enum).unwrap_or(defaults.database.migrations),
+            uniqueness: r.get("database.uniqueness", parse_enum).unwrap_or(defaults.database.uniqueness),
+        };
+        if database.max_connections == 0 {
+            r.invalid("database.max_connections", "pool needs at least one connection");
+        }
+        if database.min_connections > database.max_connections {
+            r.invalid("database.min_connections", "minimal pool size cannot exceed database.max_connections");
+        }
+        if let Err(e) = database.connect_options() {
+            r.violations.push(e);
+        }
+
+        let logging = LoggingConfig{
+            level: r.get("logging.level", |value| value.parse().map_err(|_| "level must be one of off, error, warn, info, debug, trace".to_owned()))
+                .unwrap_or(defaults.logging.level),
+            modules: r.get("logging.modules", parse_module_levels).unwrap_or(defaults.logging.modules),
+            format: r.get("logging.format", parse_enum).unwrap_or(defaults.logging.format),
+            file: r.get("logging.file", parse_optional(|value| Ok(PathBuf::from(value)))).unwrap_or(defaults.logging.file),
+            max_size: r.get("logging.max_size", parse_optional(parse_size)).unwrap_or(defaults.logging.max_size),
+            rotation: r.get("logging.rotation", parse_enum).unwrap_or(defaults.logging.rotation),
+            keep: r.get("logging.keep", parse_number).unwrap_or(defaults.logging.keep),
+            stdout: r.get("logging.stdout", parse_bool).unwrap_or(defaults.logging.stdout),
+        };
+
+        let tracing = TracingConfig{
+            exporter: r.get("tracing.exporter", parse_enum).unwrap_or(defaults.tracing.exporter),
+            endpoint: r.get("tracing.endpoint", |value| match value.starts_with("http://") || value.starts_with("https://") {
+                true => Ok(value.to_owned()),
+                false => Err("collector endpoint must start with http:// or https://".to_owned()),
+            }).unwrap_or(defaults.tracing.endpoint),
+            service_name: r.get("tracing.service_name", parse_text).unwrap_or(defaults.tracing.service_name),
+            sample_ratio: r.get("tracing.sample_ratio", |value| value.parse::<f64>()
+                .ok()
+                .filter(|ratio| (0.0..=1.0).contains(ratio))
+                .ok_or("sample ratio must be a number between 0 and 1".to_owned()))
+                .unwrap_or(defaults.tracing.sample_ratio),
+        };
+
+        let path = |value: &str| Ok(PathBuf::from(value));
+        let auth = AuthConfig{
+            enabled: r.get("auth.enabled", parse_bool).unwrap_or(defaults.auth.enabled),
+            api_keys_file: r.get("auth.api_keys_file", parse_optional(path)).unwrap_or(defaults.auth.api_keys_file),
+            jwt_secret: r.get("auth.jwt_secret", parse_optional(parse_text)).unwrap_or(defaults.auth.jwt_secret),
+            jwks_file: r.get("auth.jwks_file", parse_optional(path)).unwrap_or(defaults.auth.jwks_file),
+            jwt_issuer: r.get("auth.jwt_issuer", parse_optional(parse_text)).unwrap_or(defaults.auth.jwt_issuer),
+            jwt_audience: r.get("auth.jwt_audience", parse_optional(parse_text)).unwrap_or(defaults.auth.jwt_audience),
+            max_raise_percentage: r.get("auth.max_raise_percentage", parse_percentage).unwrap_or(defaults.auth.max_raise_percentage),
+        };
+        if auth.jwt_secret.as_ref().is_some_and(|secret| secret.len() < 32) {
+            r.invalid("auth.jwt_secret", "HS256 secret must be at least 32 bytes long");
+        } else if auth.jwt_secret.as_deref().is_some_and(is_placeholder_secret) {
+            r.invalid("auth.jwt_secret", "HS256 secret looks like a placeholder from an example, generate a random one");
+        }
+
+        let names = NamePolicy{
+            max_length: r.get("names.max_length", |value| parse_number(value)
+                .ok()
+                .filter(|length| (1..=MAX_NAME_LENGTH).contains(length))
+                .ok_or(format!("maximal name length must be between 1 and {MAX_NAME_LENGTH}")))
+                .unwrap_or(defaults.names.max_length),
+            mixed_scripts: r.get("names.mixed_scripts", parse_switch).unwrap_or(defaults.names.mixed_scripts),
+            digits: r.get("names.digits", parse_switch).unwrap_or(defaults.names.digits),
+            // Пробелы в списке знаков препинания значимы, поэтому значение берется без обрезки
+            punctuation: r.values.get("names.punctuation").map(|value| value.raw.clone()).unwrap_or(defaults.names.punctuation),
+        };
+
+        match AppError::from_violations(r.violations) {
+            Some(error) => Err(error),
+            None => Ok(Config{http, database, logging, tracing, auth, names}),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests{
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::time::Duration;
+    use log::LevelFilter;
+    use crate::errors::AppError;
+    use crate::logging::{LogFormat, RotationPeriod};
+    use crate::migrations::MigrationMode;
+    use crate::money::Percentage;
+    use crate::postgres_client::UniquenessPolicy;
+    use crate::telemetry::TraceExporter;
+    use super::{Config, ConfigArgs, ConfigLayer};
+
+    fn vars(values: &[(&str, &str)]) -> ConfigLayer {
+        let values: HashMap<String, String> = values.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        ConfigLayer::from_vars(|name| values.get(name).cloned())
+    }
+
+    fn credentials() -> ConfigLayer {
+        vars(&[("DB_USERNAME", "app"), ("DB_PASSWORD", "secret")])
+    }
+
+    #[test]
+    fn defaults_test(){
+        let config = Config::resolve(&[credentials()]).unwrap();
+        assert_eq!("0.0.0.0", config.http.host);
+        assert_eq!(8080, config.http.port);
+        assert_eq!(7, config.database.max_connections);
+        assert_eq!(MigrationMode::Apply, config.database.migrations);
+        assert_eq!(Some("app".to_owned()), config.database.username);
+        assert_eq!(LevelFilter::Debug, config.logging.level);
+    }
+
+    #[test]
+    fn precedence_test(){
+        let file = ConfigLayer::from_toml(r#"
+            [http]
+            port = 9000
+            host = "127.0.0.1"
+            docs = false
+            metrics = false
+
+            [database]
+            username = "file_user"
+            password = "file_password"
+            max_connections = 20
+            idle_timeout = ""
+
+            [logging]
+            level = "info"
+            file = ""
+        "#, Path::new("app.toml")).unwrap();
+        let env = vars(&[("PORT", "9100"), ("DB_USERNAME", "env_user"), ("EMPLOYEE_UNIQUENESS", "personnel_number")]);
+        let args = ConfigLayer::from_args(&ConfigArgs{
+            port: Some("9200".to_owned()),
+            settings: vec!["database.connect_timeout=5".to_owned()],
+            ..ConfigArgs::default()
+        }).unwrap();
+        let config = Config::resolve(&[file, env, args]).unwrap();
+        assert_eq!("127.0.0.1", config.http.host);
+        assert_eq!(9200, config.http.port);
+        assert!(!config.http.docs);
+        assert!(!config.http.metrics);
+        assert_eq!(Some("env_user".to_owned()), config.database.username);
+        assert_eq!(Some("file_password".to_owned()), config.database.password);
+        assert_eq!(20, config.database.max_connections);
+        assert_eq!(Duration::from_secs(5), config.database.connect_timeout);
+        assert_eq!(None, config.database.idle_timeout);
+        assert_eq!(UniquenessPolicy::PersonnelNumber, config.database.uniqueness);
+        assert_eq!(LevelFilter::Info, config.logging.level);
+        assert_eq!(None, config.logging.file);
+    }
+
+    #[test]
+    fn missing_credentials_test(){
+        let error = Config::resolve(&[]).unwrap_err();
+        let fields: Vec<&str> = error.field_errors().iter().map(|error| error.field).collect();
+        assert_eq!(vec!["database.username", "database.password"], fields);
+        assert!(error.to_string().contains("DB_PASSWORD"));
+
+        let config = Config::resolve(&[vars(&[("DATABASE_URL", "postgres://app:secret@db:5433/salaries")])]).unwrap();
+        assert_eq!(None, config.database.password);
+        let options = config.database.connect_options().unwrap();
+        assert_eq!(Some("salaries"), options.get_database());
+    }
+
+    #[test]
+    fn invalid_settings_test(){
+        let env = vars(&[
+            ("PORT", "http"),
+            ("DB_PASSWORD", "secret"),
+            ("DB_MAX_CONNECTIONS", "2"),
+            ("DB_MIN_CONNECTIONS", "5"),
+            ("DB_MIGRATIONS", "skip"),
+            ("NAME_MAX_LENGTH", "1000"),
+        ]);
+        let error = Config::resolve(&[env]).unwrap_err();
+        let errors = error.field_errors();
+        let fields: Vec<&str> = errors.iter().map(|error| error.field).collect();
+        assert_eq!(vec!["http.port", "database.username", "database.migrations", "database.min_connections", "names.max_length"], fields);
+        assert!(errors[0].message.contains("\"http\" from environment variable PORT"), "{}", errors[0].message);
+
+        let error = Config::resolve(&[vars(&[("DATABASE_URL", "mysql://root:hunter2@db")])]).unwrap_err();
+        assert!(!error.to_string().contains("hunter2"));
+    }
+
+    #[test]
+    fn logging_settings_test(){
+        let env = vars(&[
+            ("DB_USERNAME", "app"),
+            ("DB_PASSWORD", "secret"),
+            ("LOG_FORMAT", "json"),
+            ("LOG_MODULES", "sqlx=warn,wildberries_test::server=trace"),
+            ("LOG_MAX_SIZE", "5MB"),
+            ("LOG_ROTATION", "hourly"),
+            ("LOG_KEEP", "3"),
+        ]);
+        let config = Config::resolve(&[env]).unwrap();
+        assert_eq!(LogFormat::Json, config.logging.format);
+        assert_eq!(vec![("sqlx".to_owned(), LevelFilter::Warn), ("wildberries_test::server".to_owned(), LevelFilter::Trace)], config.logging.modules);
+        assert_eq!(Some(5 << 20), config.logging.max_size);
+        assert_eq!(RotationPeriod::Hourly, config.logging.rotation);
+        assert_eq!(3, config.logging.keep);
+
+        let config = Config::resolve(&[credentials(), vars(&[("LOG_MAX_SIZE", "")])]).unwrap();
+        assert_eq!(None, config.logging.max_size);
+
+        let env = vars(&[("LOG_FORMAT", "xml"), ("LOG_MODULES", "sqlx"), ("LOG_MAX_SIZE", "10TB"), ("LOG_ROTATION", "weekly")]);
+        let error = Config::resolve(&[credentials(), env]).unwrap_err();
+        let fields: Vec<&str> = error.field_errors().iter().map(|error| error.field).collect();
+        assert_eq!(vec!["logging.modules", "logging.format", "logging.max_size", "logging.rotation"], fields);
+    }
+
+    #[test]
+    fn tracing_settings_test(){
+        let config = Config::resolve(&[credentials()]).unwrap();
+        assert_eq!(TraceExporter::None, config.tracing.exporter);
+
+        let file = ConfigLayer::from_toml(r#"
+            [tracing]
+            exporter = "otlp"
+            endpoint = "http://collector:4318/v1/traces"
+            sample_ratio = 0.25
+        "#, Path::new("app.toml")).unwrap();
+        let config = Config::resolve(&[credentials(), file, vars(&[("OTEL_SERVICE_NAME", "salaries")])]).unwrap();
+        assert_eq!(TraceExporter::Otlp, config.tracing.exporter);
+        assert_eq!("http://collector:4318/v1/traces", config.tracing.endpoint);
+        assert_eq!("salaries", config.tracing.service_name);
+        assert_eq!(0.25, config.tracing.sample_ratio);
+
+        let env = vars(&[("TRACING_EXPORTER", "jaeger"), ("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "collector:4318"), ("TRACING_SAMPLE_RATIO", "2")]);
+        let error = Config::resolve(&[credentials(), env]).unwrap_err();
+        let fields: Vec<&str> = error.field_errors().iter().map(|error| error.field).collect();
+        assert_eq!(vec!["tracing.exporter", "tracing.endpoint", "tracing.sample_ratio"], fields);
+    }
+
+    #[test]
+    fn auth_settings_test(){
+        let config = Config::resolve(&[credentials()]).unwrap();
+        assert!(config.auth.enabled);
+        assert_eq!(None, config.auth.jwt_secret);
+
+        let env = vars(&[
+            ("AUTH_API_KEYS_FILE", "keys.toml"),
+            ("AUTH_JWT_SECRET", "0123456789abcdef0123456789abcdef"),
+            ("AUTH_JWT_ISSUER", "https://sso.example.com"),
+            ("AUTH_MAX_RAISE_PERCENTAGE", "12.5"),
+        ]);
+        let config = Config::resolve(&[credentials(), env]).unwrap();
+        assert_eq!(Some(Path::new("keys.toml").to_path_buf()), config.auth.api_keys_file);
+        assert_eq!(Some("https://sso.example.com".to_owned()), config.auth.jwt_issuer);
+        assert_eq!(Percentage::new(1250), config.auth.max_raise_percentage);
+        assert!(!format!("{config:?}").contains("0123456789abcdef"));
+
+        let error = Config::resolve(&[credentials(), vars(&[("AUTH_JWT_SECRET", "hunter2"), ("AUTH_ENABLED", "maybe"), ("AUTH_MAX_RAISE_PERCENTAGE", "-5")])]).unwrap_err();
+        let fields: Vec<&str> = error.field_errors().iter().map(|error| error.field).collect();
+        assert_eq!(vec!["auth.enabled", "auth.max_raise_percentage", "auth.jwt_secret"], fields);
+
+        for placeholder in ["change-me-to-a-random-string-of-32-bytes", "CHANGE_ME_CHANGE_ME_CHANGE_ME_123", &"x".repeat(40)] {
+            let error = Config::resolve(&[credentials(), vars(&[("AUTH_JWT_SECRET", placeholder)])]).unwrap_err();
+            assert_eq!("auth.jwt_secret", error.field_errors()[0].field, "{placeholder}");
+        }
+        assert!(!error.to_string().contains("hunter2"));
+    }
+
+    #[test]
+    fn unknown_settings_test(){
+        let error = ConfigLayer::from_toml("[database]\npasword = \"secret\"\n", Path::new("app.toml")).unwrap_err();
+        assert!(matches!(error, AppError::Validation{field: "config", ..}));
+        assert!(error.to_string().contains("database.pasword"));
+        assert!(ConfigLayer::from_toml("port = 8080", Path::new("app.toml")).is_err());
+        assert!(ConfigLayer::from_args(&ConfigArgs{settings: vec!["http.port".to_owned()], ..ConfigArgs::default()}).is_err());
+    }
+
+    #[test]
+    fn debug_hides_secrets_test(){
+        let config = Config::resolve(&[credentials(), vars(&[("AUTH_JWT_SECRET", "0123456789abcdef0123456789abcdef")])]).unwrap();
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("\"secret\""));
+        assert!(!debug.contains("0123456789abcdef"));
+    }
+}
@@ -0,0 +1,319 @@
+use std::error::Error;
+use std::fmt::Display;
+use actix_web::http::header::WWW_AUTHENTICATE;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use crate::models::{ErrorDetails, ErrorResponse, FieldErrorDetails};
+
+
+/// Ошибка проверки одного поля
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError{
+    pub field: &'static str,
+    pub message: String,
+}
+
+
+/// Ошибка приложения
+///
+/// Общий тип ошибки для моделей, клиента базы данных и эндпоинтов.
+/// Каждый вариант однозначно соответствует HTTP статусу ответа
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError{
+    /// Входные данные не прошли проверку
+    Validation{
+        field: &'static str,
+        message: String,
+    },
+    /// Несколько полей или правил не прошли проверку
+    ///
+    /// Позволяет вернуть все нарушения разом, а не только первое
+    InvalidFields(Vec<FieldError>),
+    /// Запрошенный сотрудник не найден
+    NotFound(String),
+    /// Операция конфликтует с уже существующими данными
+    Conflict(String),
+    /// Имени соответствует несколько сотрудников, нужно обратиться по идентификатору
+    Ambiguous{
+        message: String,
+        candidates: Vec<i32>,
+    },
+    /// Результат вычислений не помещается в допустимый диапазон
+    Overflow(String),
+    /// Тело запроса передано в неподдерживаемом формате
+    UnsupportedMediaType(String),
+    /// Запрос без учетных данных или с неверными учетными данными
+    Unauthorized(String),
+    /// У пользователя нет прав на операцию
+    Forbidden(String),
+    /// Хранилище временно недоступно, запрос можно повторить
+    StorageUnavailable(String),
+    /// Непредвиденная ошибка хранилища
+    Storage(String),
+}
+
+impl AppError{
+    pub fn validation(field: &'static str, message: &str) -> AppError {
+        AppError::Validation{field, message: message.to_owned()}
+    }
+
+    /// Объединить ошибки проверки
+    ///
+    /// Возвращает None, если ошибок нет, и саму ошибку, если она одна.
+    /// Ошибки, не относящиеся к проверке полей, отбрасываются
+    pub fn from_violations(violations: Vec<AppError>) -> Option<AppError> {
+        let mut errors: Vec<FieldError> = violations.iter()
+            .flat_map(AppError::field_errors)
+            .collect();
+        errors.dedup();
+        match errors.len() {
+            0 => None,
+            1 => errors.pop().map(|error| AppError::Validation{field: error.field, message: error.message}),
+            _ => Some(AppError::InvalidFields(errors)),
+        }
+    }
+
+    /// Ошибки проверки по полям
+    pub fn field_errors(&self) -> Vec<FieldError> {
+        match self {
+            AppError::Validation{field, message} => vec![FieldError{field, message: message.to_owned()}],
+            AppError::InvalidFields(errors) => errors.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Уточнить, к какому сотруднику относится ошибка
+    ///
+    /// Используется в пакетных операциях, где одна ошибка отменяет весь пакет
+    pub fn for_employee(self, id: i32) -> AppError {
+        let prefix = |message: String| format!("employee {id}: {message}");
+        match self {
+            AppError::Validation{field, message} => AppError::Validation{field, message: prefix(message)},
+            AppError::InvalidFields(errors) => AppError::InvalidFields(errors.into_iter()
+                .map(|error| FieldError{field: error.field, message: prefix(error.message)})
+                .collect()),
+            AppError::NotFound(msg) => AppError::NotFound(prefix(msg)),
+            AppError::Conflict(msg) => AppError::Conflict(prefix(msg)),
+            AppError::Ambiguous{message, candidates} => AppError::Ambiguous{message: prefix(message), candidates},
+            AppError::Overflow(msg) => AppError::Overflow(prefix(msg)),
+            AppError::UnsupportedMediaType(msg) => AppError::UnsupportedMediaType(msg),
+            AppError::Unauthorized(msg) => AppError::Unauthorized(msg),
+            AppError::Forbidden(msg) => AppError::Forbidden(prefix(msg)),
+            AppError::StorageUnavailable(msg) => AppError::StorageUnavailable(msg),
+            AppError::Storage(msg) => AppError::Storage(msg),
+        }
+    }
+
+    /// Машиночитаемый код ошибки
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Validation{..} | AppError::InvalidFields(_) => "validation_error",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Ambiguous{..} => "ambiguous",
+            AppError::Overflow(_) => "overflow",
+            AppError::UnsupportedMediaType(_) => "unsupported_media_type",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::StorageUnavailable(_) => "storage_unavailable",
+            AppError::Storage(_) => "storage_error",
+        }
+    }
+
+    /// Сообщение об ошибке для клиента
+    ///
+    /// Подробности ошибок хранилища остаются в журнале сервера, клиент получает только код и общее сообщение
+    pub fn client_message(&self) -> String {
+        match self {
+            AppError::Validation{message, ..} | AppError::Ambiguous{message, ..} => message.to_owned(),
+            AppError::InvalidFields(errors) => errors.iter().map(|error| error.message.as_str()).collect::<Vec<&str>>().join("; "),
+            AppError::NotFound(msg)
+            | AppError::Conflict(msg)
+            | AppError::Overflow(msg)
+            | AppError::UnsupportedMediaType(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg) => msg.to_owned(),
+            AppError::StorageUnavailable(_) => "database unavailable".to_owned(),
+            AppError::Storage(_) => "storage error".to_owned(),
+        }
+    }
+
+    /// Тело ответа с ошибкой
+    pub fn to_response(&self) -> ErrorResponse {
+        let field = match self {
+            AppError::Validation{field, ..} => Some(field.to_string()),
+            AppError::InvalidFields(errors) => errors.first().map(|error| error.field.to_string()),
+            _ => None,
+        };
+        ErrorResponse{
+            error: ErrorDetails{
+                code: self.code().to_owned(),
+                message: self.client_message(),
+                field,
+                candidates: match self {
+                    AppError::Ambiguous{candidates, ..} => Some(candidates.clone()),
+                    _ => None,
+                },
+                errors: match self {
+                    AppError::Validation{..} | AppError::InvalidFields(_) => Some(self.field_errors()
+                        .into_iter()
+                        .map(|error| FieldErrorDetails{field: error.field.to_owned(), message: error.message})
+                        .collect()),
+                    _ => None,
+                },
+            }
+        }
+    }
+}
+
+impl Display for AppError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Validation{field, message} => write!(f, "invalid {field}: {message}"),
+            AppError::InvalidFields(errors) => write!(f, "invalid fields: {}", errors.iter()
+                .map(|error| format!("{}: {}", error.field, error.message))
+                .collect::<Vec<String>>()
+                .join("; ")),
+            AppError::NotFound(msg) => write!(f, "not found: {msg}"),
+            AppError::Conflict(msg) => write!(f, "conflict: {msg}"),
+            AppError::Ambiguous{message, candidates} => write!(f, "ambiguous: {message} (candidates: {candidates:?})"),
+            AppError::Overflow(msg) => write!(f, "overflow: {msg}"),
+            AppError::UnsupportedMediaType(msg) => write!(f, "unsupported media type: {msg}"),
+            AppError::Unauthorized(msg) => write!(f, "unauthorized: {msg}"),
+            AppError::Forbidden(msg) => write!(f, "forbidden: {msg}"),
+            AppError::StorageUnavailable(msg) => write!(f, "storage unavailable: {msg}"),
+            AppError::Storage(msg) => write!(f, "storage error: {msg}"),
+        }
+    }
+}
+
+impl Error for AppError{
+}
+
+impl From<sqlx::Error> for AppError{
+    fn from(value: sqlx::Error) -> Self {
+        match value {
+            sqlx::Error::RowNotFound => AppError::NotFound("employee does not exist".to_owned()),
+            sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed
+            | sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_) => AppError::StorageUnavailable(value.to_string()),
+            sqlx::Error::Database(ref db_error) => match db_error.code().as_deref() {
+                // unique_violation
+                Some("23505") => AppError::Conflict(db_error.message().to_owned()),
+                // numeric_value_out_of_range
+                Some("22003") => AppError::Overflow(db_error.message().to_owned()),
+                // admin_shutdown, crash_shutdown, cannot_connect_now
+                Some("57P01") | Some("57P02") | Some("57P03") => AppError::StorageUnavailable(db_error.message().to_owned()),
+                _ => AppError::Storage(value.to_string()),
+            },
+            _ => AppError::Storage(value.to_string()),
+        }
+    }
+}
+
+impl ResponseError for AppError{
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Validation{..} | AppError::InvalidFields(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) | AppError::Ambiguous{..} => StatusCode::CONFLICT,
+            AppError::Overflow(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::StorageUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let AppError::Unauthorized(_) = self {
+            // Клиент узнает, какие учетные данные ожидаются
+            response.insert_header((WWW_AUTHENTICATE, "Bearer"));
+        }
+        response.json(self.to_response())
+    }
+}
+
+
+#[cfg(test)]
+mod tests{
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+    use super::{AppError, FieldError};
+
+    #[test]
+    fn status_code_mapping_test(){
+        assert_eq!(StatusCode::BAD_REQUEST, AppError::validation("name", "bad").status_code());
+        assert_eq!(StatusCode::NOT_FOUND, AppError::NotFound("x".to_owned()).status_code());
+        assert_eq!(StatusCode::CONFLICT, AppError::Conflict("x".to_owned()).status_code());
+        assert_eq!(StatusCode::CONFLICT, AppError::Ambiguous{message: "x".to_owned(), candidates: vec![1, 2]}.status_code());
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, AppError::Overflow("x".to_owned()).status_code());
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, AppError::UnsupportedMediaType("x".to_owned()).status_code());
+        assert_eq!(StatusCode::UNAUTHORIZED, AppError::Unauthorized("x".to_owned()).status_code());
+        assert_eq!(StatusCode::FORBIDDEN, AppError::Forbidden("x".to_owned()).status_code());
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, AppError::StorageUnavailable("x".to_owned()).status_code());
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, AppError::Storage("x".to_owned()).status_code());
+    }
+
+    #[test]
+    fn error_response_body_test(){
+        let body = AppError::validation("salary", "bad salary").to_response();
+        assert_eq!("validation_error", body.error.code);
+        assert_eq!("bad salary", body.error.message);
+        assert_eq!(Some("salary".to_owned()), body.error.field);
+        let body = AppError::NotFound("no such employee".to_owned()).to_response();
+        assert_eq!("not_found", body.error.code);
+        assert_eq!(None, body.error.field);
+        assert_eq!(None, body.error.errors);
+    }
+
+    #[test]
+    fn from_violations_test(){
+        assert_eq!(None, AppError::from_violations(Vec::new()));
+        assert_eq!(Some(AppError::validation("name", "bad")), AppError::from_violations(vec![AppError::validation("name", "bad")]));
+        let error = AppError::from_violations(vec![
+            AppError::validation("name", "too long"),
+            AppError::validation("salary", "negative"),
+            AppError::NotFound("x".to_owned()),
+        ]).unwrap();
+        assert_eq!(AppError::InvalidFields(vec![
+            FieldError{field: "name", message: "too long".to_owned()},
+            FieldError{field: "salary", message: "negative".to_owned()},
+        ]), error);
+        assert_eq!(StatusCode::BAD_REQUEST, error.status_code());
+        let body = error.to_response();
+        assert_eq!("validation_error", body.error.code);
+        assert_eq!(Some("name".to_owned()), body.error.field);
+        let errors = body.error.errors.unwrap();
+        assert_eq!(vec!["name", "salary"], errors.iter().map(|error| error.field.as_str()).collect::<Vec<&str>>());
+    }
+
+    #[test]
+    fn storage_error_response_test(){
+        let body = AppError::from(sqlx::Error::ColumnNotFound("secret_column".to_owned())).to_response();
+        assert_eq!(("storage_error", "storage error"), (body.error.code.as_str(), body.error.message.as_str()));
+        let body = AppError::from(sqlx::Error::PoolTimedOut).to_response();
+        assert_eq!(("storage_unavailable", "database unavailable"), (body.error.code.as_str(), body.error.message.as_str()));
+        // Подробности остаются для журнала
+        assert!(AppError::from(sqlx::Error::ColumnNotFound("secret_column".to_owned())).to_string().contains("secret_column"));
+    }
+
+    #[test]
+    fn for_employee_test(){
+        assert_eq!(AppError::Overflow("employee 7: too high".to_owned()), AppError::Overflow("too high".to_owned()).for_employee(7));
+        assert_eq!(AppError::validation("value", "employee 7: bad"), AppError::validation("value", "bad").for_employee(7));
+        assert_eq!(AppError::Storage("down".to_owned()), AppError::Storage("down".to_owned()).for_employee(7));
+    }
+
+    #[test]
+    fn sqlx_error_mapping_test(){
+        assert!(matches!(AppError::from(sqlx::Error::RowNotFound), AppError::NotFound(_)));
+        assert!(matches!(AppError::from(sqlx::Error::PoolTimedOut), AppError::StorageUnavailable(_)));
+        assert!(matches!(AppError::from(sqlx::Error::PoolClosed), AppError::StorageUnavailable(_)));
+        assert!(matches!(AppError::from(sqlx::Error::ColumnNotFound("x".to_owned())), AppError::Storage(_)));
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use actix_web::dev::ServerHandle;
+use actix_web::rt::{signal, time};
+use actix_web::web;
+use futures::future::{self, Either};
+use log::{info, error};
+use crate::errors::AppError;
+use crate::migrations::MigrationStatus;
+use crate::models::{DatabaseHealth, ReadinessReport};
+use crate::postgres_client::DBClient;
+
+
+/// Наибольшее время ожидания ответа базы при проверке готовности
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+
+/// Признак остановки сервера
+///
+/// Общий для всех рабочих потоков: после начала остановки проверка готовности отвечает 503
+#[derive(Debug, Default)]
+pub struct Readiness{
+    shutting_down: AtomicBool,
+}
+
+impl Readiness{
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+}
+
+
+/// Проверить готовность приложения принимать запросы
+///
+/// Состояние миграций запрашивается только у отвечающей базы
+pub async fn check_readiness(db_client: &dyn DBClient, readiness: &Readiness) -> ReadinessReport {
+    let started = Instant::now();
+    let ping = time::timeout(PING_TIMEOUT, db_client.ping()).await
+        .unwrap_or(Err(AppError::StorageUnavailable(format!("database did not respond in {PING_TIMEOUT:?}"))));
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let (database, migrations) = match ping {
+        Ok(()) => match db_client.migration_status().await {
+            Ok(status) => (DatabaseHealth{up: true, latency_ms, error: None}, Some(status)),
+            Err(e) => {
+                error!("Cannot read migration status: {e}");
+                (DatabaseHealth{up: true, latency_ms, error: Some(e.client_message())}, None)
+            },
+        },
+        Err(e) => {
+            error!("Database is not ready: {e}");
+            (DatabaseHealth{up: false, latency_ms, error: Some(e.client_message())}, None)
+        },
+    };
+    let shutting_down = readiness.is_shutting_down();
+    let ready = database.up
+        && migrations.as_ref().is_some_and(MigrationStatus::is_up_to_date)
+        && !shutting_down;
+    ReadinessReport{ready, shutting_down, database, migrations, pool: db_client.pool_status()}
+}
+
+
+/// Дождаться SIGTERM или Ctrl+C
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                let ctrl_c = Box::pin(signal::ctrl_c());
+                let terminate = Box::pin(terminate.recv());
+                match future::select(ctrl_c, terminate).await {
+                    Either::Left(_) => info!("Received Ctrl+C"),
+                    Either::Right(_) => info!("Received SIGTERM"),
+                }
+                return;
+            },
+            Err(e) => error!("Cannot listen for SIGTERM: {e}"),
+        }
+    }
+    if let Err(e) = signal::ctrl_c().await {
+        error!("Cannot listen for Ctrl+C: {e}");
+        future::pending::<()>().await;
+    }
+}
+
+/// Плавно остановить сервер по сигналу
+///
+/// Сначала проверка готовности переключается в 503, чтобы балансировщик перестал направлять запросы.
+/// Через delay сервер перестает принимать соединения и дожидается завершения начатых запросов
+pub async fn shutdown_on_signal(handle: ServerHandle, readiness: web::Data<Readiness>, delay: Duration) {
+    wait_for_signal().await;
+    readiness.begin_shutdown();
+    info!("Shutting down: reporting not ready for {delay:?} before closing connections");
+    time::sleep(delay).await;
+    handle.stop(true).await;
+}
+
+
+#[cfg(test)]
+mod tests{
+    use crate::errors::AppError;
+    use crate::migrations::MigrationStatus;
+    use crate::models::PoolStatus;
+    use crate::postgres_client::MockDBClient;
+    use super::{check_readiness, Readiness};
+
+    fn mock_client(ping: Result<(), AppError>, pending: Vec<i64>) -> MockDBClient {
+        let mut db_client = MockDBClient::new();
+        db_client.expect_ping().returning(move || ping.clone());
+        db_client.expect_migration_status().returning(move || Ok(MigrationStatus{applied: vec![1, 2], pending: pending.clone()}));
+        db_client.expect_pool_status().returning(|| PoolStatus::new(4, 1, 8));
+        db_client
+    }
+
+    #[actix_web::test]
+    async fn readiness_test(){
+        let readiness = Readiness::default();
+        let report = check_readiness(&mock_client(Ok(()), Vec::new()), &readiness).await;
+        assert!(report.ready);
+        assert!(report.database.up);
+        assert_eq!(3, report.pool.in_use);
+        assert_eq!(0.375, report.pool.saturation);
+
+        let report = check_readiness(&mock_client(Err(AppError::StorageUnavailable("connection refused".to_owned())), Vec::new()), &readiness).await;
+        assert!(!report.ready);
+        assert!(!report.database.up);
+        assert_eq!(Some("database unavailable".to_owned()), report.database.error);
+        assert_eq!(None, report.migrations);
+
+        let report = check_readiness(&mock_client(Ok(()), vec![3]), &readiness).await;
+        assert!(!report.ready);
+
+        readiness.begin_shutdown();
+        let report = check_readiness(&mock_client(Ok(()), Vec::new()), &readiness).await;
+        assert!(!report.ready);
+        assert!(report.shutting_down);
+    }
+}
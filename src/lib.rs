@@ -1,3 +1,17 @@
+pub mod cli;
+pub mod config;
 pub mod postgres_client;
 pub mod server;
 pub mod models;
+pub mod errors;
+pub mod access;
+pub mod auth;
+pub mod health;
+pub mod logging;
+pub mod metrics;
+pub mod migrations;
+pub mod money;
+pub mod names;
+pub mod openapi;
+pub mod params;
+pub mod telemetry;
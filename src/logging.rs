@@ -0,0 +1,482 @@
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock, RwLock};
+use actix_service::Service;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use chrono::{DateTime, Duration, DurationRound, SecondsFormat, Utc};
+use log::{LevelFilter, Log, Metadata, Record};
+use crate::config::LoggingConfig;
+use tokio::task::futures::TaskLocalFuture;
+use crate::errors::AppError;
+
+
+/// Заголовок ответа с идентификатором запроса
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+
+tokio::task_local! {
+    /// Идентификатор запроса, который обрабатывает текущая задача
+    static REQUEST_ID: String;
+    /// Пользователь или сервис, от имени которого выполняется запрос
+    static PRINCIPAL: String;
+}
+
+/// Идентификатор обрабатываемого запроса, если запись делается внутри запроса
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Пользователь обрабатываемого запроса, если запрос прошел проверку подлинности
+pub fn current_principal() -> Option<String> {
+    PRINCIPAL.try_with(|principal| principal.clone()).ok()
+}
+
+/// Выполнить обработку запроса от имени пользователя
+///
+/// Записи журнала, сделанные и при вызове call, и при опросе возвращенного им ответа, помечаются именем пользователя
+pub fn with_principal<F: Future>(principal: String, call: impl FnOnce() -> F) -> TaskLocalFuture<String, F> {
+    let response = PRINCIPAL.sync_scope(principal.clone(), call);
+    PRINCIPAL.scope(principal, response)
+}
+
+
+/// Формат строк журнала
+///
+/// Задается настройкой logging.format (LOG_FORMAT)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LogFormat{
+    /// Строка для чтения человеком
+    #[default]
+    Text,
+    /// Один JSON-объект на строку для сборщиков журналов
+    Json,
+}
+
+impl FromStr for LogFormat{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(AppError::validation("LOG_FORMAT", "log format must be either \"text\" or \"json\"")),
+        }
+    }
+}
+
+
+/// Период ротации файла журнала
+///
+/// Задается настройкой logging.rotation (LOG_ROTATION)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RotationPeriod{
+    /// Файл ротируется только по размеру
+    Never,
+    Hourly,
+    #[default]
+    Daily,
+}
+
+impl FromStr for RotationPeriod{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(RotationPeriod::Never),
+            "hourly" => Ok(RotationPeriod::Hourly),
+            "daily" => Ok(RotationPeriod::Daily),
+            _ => Err(AppError::validation("LOG_ROTATION", "rotation period must be one of \"never\", \"hourly\", \"daily\"")),
+        }
+    }
+}
+
+impl RotationPeriod{
+    /// Начало периода, в который попадает момент времени
+    fn start(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let period = match self {
+            RotationPeriod::Never => return None,
+            RotationPeriod::Hourly => Duration::hours(1),
+            RotationPeriod::Daily => Duration::days(1),
+        };
+        time.duration_trunc(period).ok()
+    }
+}
+
+
+/// Уровни логирования отдельных модулей
+///
+/// Записываются через запятую, например "sqlx=warn,wildberries_test::server=trace".
+/// Уровень модуля действует и на его вложенные модули
+pub fn parse_module_levels(value: &str) -> Result<Vec<(String, LevelFilter)>, String> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| {
+            let (module, level) = directive.split_once('=')
+                .ok_or(format!("\"{directive}\" must look like module=level"))?;
+            let level = level.trim().parse()
+                .map_err(|_| format!("level of \"{}\" must be one of off, error, warn, info, debug, trace", module.trim()))?;
+            Ok((module.trim().to_owned(), level))
+        })
+        .collect()
+}
+
+
+/// Файл журнала с ротацией по размеру и по времени
+///
+/// Записи дописываются в конец файла. При ротации текущий файл переименовывается в app.log.1,
+/// прежние архивы сдвигаются на один номер, архивы старше keep удаляются
+pub struct RotatingFile{
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: Option<u64>,
+    period: RotationPeriod,
+    period_start: Option<DateTime<Utc>>,
+    keep: usize,
+}
+
+impl RotatingFile{
+    pub fn open(path: &Path, max_size: Option<u64>, period: RotationPeriod, keep: usize) -> io::Result<RotatingFile> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        // Файл, оставшийся с прошлого запуска, относится к периоду своей последней записи
+        let modified = metadata.modified().map(DateTime::<Utc>::from).unwrap_or(Utc::now());
+        Ok(RotatingFile{
+            path: path.to_owned(),
+            file,
+            size: metadata.len(),
+            max_size,
+            period,
+            period_start: period.start(modified),
+            keep,
+        })
+    }
+
+    fn archive(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        PathBuf::from(path)
+    }
+
+    fn should_rotate(&self, len: usize, now: DateTime<Utc>) -> bool {
+        self.size > 0 && (
+            self.max_size.is_some_and(|max_size| self.size + len as u64 > max_size)
+            || self.period.start(now) != self.period_start
+        )
+    }
+
+    fn rotate(&mut self, now: DateTime<Utc>) -> io::Result<()> {
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = self.archive(self.keep);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+            for index in (1..self.keep).rev() {
+                let archive = self.archive(index);
+                if archive.exists() {
+                    fs::rename(&archive, self.archive(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.archive(1))?;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        self.period_start = self.period.start(now);
+        Ok(())
+    }
+
+    /// Записать строку, при необходимости сначала ротировав файл
+    pub fn write_line(&mut self, line: &str, now: DateTime<Utc>) -> io::Result<()> {
+        if self.should_rotate(line.len(), now) {
+            self.rotate(now)?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+
+/// Настроенный журнал
+struct Sink{
+    level: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+    format: LogFormat,
+    stdout: bool,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl Sink{
+    /// Уровень для цели записи: берется у самого длинного подходящего модуля
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules.iter()
+            .filter(|(module, _)| target == module || target.strip_prefix(module.as_str()).is_some_and(|rest| rest.starts_with("::")))
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|(_, level)| *level).fold(self.level, Ord::max)
+    }
+}
+
+/// Строка журнала с переводом строки в конце
+fn format_record(format: LogFormat, record: &Record, time: DateTime<Utc>, request_id: Option<&str>, principal: Option<&str>) -> String {
+    let timestamp = time.to_rfc3339_opts(SecondsFormat::Millis, true);
+    match format {
+        LogFormat::Text => {
+            let context = match (request_id, principal) {
+                (Some(request_id), Some(principal)) => format!(" [{request_id} {principal}]"),
+                (Some(context), None) | (None, Some(context)) => format!(" [{context}]"),
+                (None, None) => String::new(),
+            };
+            format!("{timestamp} [{:>5}]{context} {}: {}\n", record.level(), record.target(), record.args())
+        },
+        LogFormat::Json => {
+            let mut line = serde_json::json!({
+                "timestamp": timestamp,
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            if let Some(request_id) = request_id {
+                line["request_id"] = request_id.into();
+            }
+            if let Some(principal) = principal {
+                line["principal"] = principal.into();
+            }
+            format!("{line}\n")
+        },
+    }
+}
+
+
+/// Журнал приложения
+///
+/// Устанавливается в log один раз на процесс, повторная настройка только заменяет его параметры
+struct AppLogger{
+    sink: RwLock<Option<Sink>>,
+}
+
+static LOGGER: AppLogger = AppLogger{sink: RwLock::new(None)};
+
+/// Удалось ли установить журнал приложения в log
+static INSTALLED: OnceLock<bool> = OnceLock::new();
+
+impl Log for AppLogger{
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match &*self.sink.read().unwrap_or_else(|e| e.into_inner()) {
+            Some(sink) => metadata.level() <= sink.level_for(metadata.target()),
+            None => false,
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        let sink = self.sink.read().unwrap_or_else(|e| e.into_inner());
+        let Some(sink) = &*sink else {
+            return;
+        };
+        if record.level() > sink.level_for(record.target()) {
+            return;
+        }
+        let now = Utc::now();
+        let line = format_record(sink.format, record, now, current_request_id().as_deref(), current_principal().as_deref());
+        // Ошибки записи журнала некуда сообщить, поэтому они пропускаются
+        if sink.stdout {
+            let _ = io::stdout().lock().write_all(line.as_bytes());
+        }
+        if let Some(file) = &sink.file {
+            let _ = file.lock().unwrap_or_else(|e| e.into_inner()).write_line(&line, now);
+        }
+    }
+
+    fn flush(&self) {
+        let _ = io::stdout().flush();
+        if let Some(Sink{file: Some(file), ..}) = &*self.sink.read().unwrap_or_else(|e| e.into_inner()) {
+            let _ = file.lock().unwrap_or_else(|e| e.into_inner()).file.flush();
+        }
+    }
+}
+
+
+/// Настроить журнал приложения
+///
+/// Можно вызывать повторно, например при запуске нескольких серверов в одном процессе:
+/// журнал устанавливается один раз, а следующие вызовы меняют его настройки
+pub fn init(config: &LoggingConfig) -> Result<(), AppError> {
+    let file = match &config.file {
+        Some(path) => Some(RotatingFile::open(path, config.max_size, config.rotation, config.keep)
+            .map_err(|e| AppError::validation("logging.file", &format!("cannot open log file {}: {e}", path.display())))?),
+        None => None,
+    };
+    let sink = Sink{
+        level: config.level,
+        modules: config.modules.clone(),
+        format: config.format,
+        stdout: config.stdout,
+        file: file.map(Mutex::new),
+    };
+    let max_level = sink.max_level();
+    *LOGGER.sink.write().unwrap_or_else(|e| e.into_inner()) = Some(sink);
+    if !*INSTALLED.get_or_init(|| log::set_logger(&LOGGER).is_ok()) {
+        return Err(AppError::validation("logging", "another logger is already installed"));
+    }
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+
+/// Присвоить запросу идентификатор
+///
+/// Подключается через wrap_fn. Идентификатор попадает во все записи журнала, сделанные
+/// при обработке запроса, и возвращается в заголовке X-Request-Id
+pub fn assign_request_id<S, B>(request: ServiceRequest, service: &S) -> impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let response = REQUEST_ID.sync_scope(request_id.clone(), || service.call(request));
+    REQUEST_ID.scope(request_id.clone(), async move {
+        let mut response = response.await?;
+        if let Ok(value) = HeaderValue::from_str(&request_id) {
+            response.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+        }
+        Ok(response)
+    })
+}
+
+
+#[cfg(test)]
+mod tests{
+    use std::fs;
+    use std::path::PathBuf;
+    use chrono::{DateTime, TimeZone, Utc};
+    use log::{Level, LevelFilter, Record};
+    use serial_test::serial;
+    use crate::config::LoggingConfig;
+    use super::{format_record, init, parse_module_levels, LogFormat, RotatingFile, RotationPeriod, Sink, REQUEST_ID};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wildberries_test_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn time(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn module_levels_test(){
+        let modules = parse_module_levels("sqlx=warn, wildberries_test=info,wildberries_test::server=trace").unwrap();
+        let sink = Sink{level: LevelFilter::Debug, modules, format: LogFormat::Text, stdout: false, file: None};
+        assert_eq!(LevelFilter::Warn, sink.level_for("sqlx::query"));
+        assert_eq!(LevelFilter::Info, sink.level_for("wildberries_test::postgres_client"));
+        assert_eq!(LevelFilter::Trace, sink.level_for("wildberries_test::server"));
+        assert_eq!(LevelFilter::Debug, sink.level_for("sqlx_postgres"));
+        assert_eq!(LevelFilter::Trace, sink.max_level());
+        assert!(parse_module_levels("sqlx").is_err());
+        assert!(parse_module_levels("sqlx=loud").is_err());
+        assert_eq!(Vec::<(String, LevelFilter)>::new(), parse_module_levels("").unwrap());
+    }
+
+    #[test]
+    fn format_test(){
+        let line = format_record(LogFormat::Text, &Record::builder()
+            .level(Level::Info)
+            .target("wildberries_test::server")
+            .args(format_args!("Sent employee with id {}", 1))
+            .build(), time(10, 0), None, None);
+        assert_eq!("2024-05-01T10:00:00.000Z [ INFO] wildberries_test::server: Sent employee with id 1\n", line);
+        let line = format_record(LogFormat::Text, &Record::builder()
+            .level(Level::Info)
+            .target("wildberries_test::server")
+            .args(format_args!("Sent employee with id {}", 1))
+            .build(), time(10, 0), Some("42"), Some("payroll-bot"));
+        assert_eq!("2024-05-01T10:00:00.000Z [ INFO] [42 payroll-bot] wildberries_test::server: Sent employee with id 1\n", line);
+        let line = format_record(LogFormat::Json, &Record::builder()
+            .level(Level::Info)
+            .target("wildberries_test::server")
+            .args(format_args!("Sent employee with id {}", 1))
+            .build(), time(10, 0), Some("42"), Some("payroll-bot"));
+        let line: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!("INFO", line["level"]);
+        assert_eq!("Sent employee with id 1", line["message"]);
+        assert_eq!("42", line["request_id"]);
+        assert_eq!("payroll-bot", line["principal"]);
+        assert_eq!("2024-05-01T10:00:00.000Z", line["timestamp"]);
+    }
+
+    #[test]
+    fn size_rotation_test(){
+        let dir = temp_dir("size_rotation");
+        let path = dir.join("app.log");
+        fs::write(&path, "old\n").unwrap();
+        let mut file = RotatingFile::open(&path, Some(10), RotationPeriod::Never, 2).unwrap();
+        // Старые записи не затираются при открытии
+        file.write_line("first\n", time(10, 0)).unwrap();
+        assert_eq!("old\nfirst\n", fs::read_to_string(&path).unwrap());
+        for line in ["second\n", "third\n", "fourth\n"] {
+            file.write_line(line, time(10, 0)).unwrap();
+        }
+        assert_eq!("fourth\n", fs::read_to_string(&path).unwrap());
+        assert_eq!("third\n", fs::read_to_string(dir.join("app.log.1")).unwrap());
+        assert_eq!("second\n", fs::read_to_string(dir.join("app.log.2")).unwrap());
+        assert!(!dir.join("app.log.3").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn period_rotation_test(){
+        let dir = temp_dir("period_rotation");
+        let path = dir.join("app.log");
+        let mut file = RotatingFile::open(&path, None, RotationPeriod::Hourly, 3).unwrap();
+        file.period_start = RotationPeriod::Hourly.start(time(10, 0));
+        file.write_line("10:05\n", time(10, 5)).unwrap();
+        file.write_line("10:55\n", time(10, 55)).unwrap();
+        file.write_line("11:01\n", time(11, 1)).unwrap();
+        assert_eq!("11:01\n", fs::read_to_string(&path).unwrap());
+        assert_eq!("10:05\n10:55\n", fs::read_to_string(dir.join("app.log.1")).unwrap());
+        assert_eq!(None, RotationPeriod::Never.start(time(11, 1)));
+        assert_eq!(Some(time(0, 0)), RotationPeriod::Daily.start(time(11, 1)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn init_test(){
+        let dir = temp_dir("init");
+        let config = LoggingConfig{
+            level: LevelFilter::Info,
+            file: Some(dir.join("app.log")),
+            stdout: false,
+            format: LogFormat::Json,
+            ..LoggingConfig::default()
+        };
+        init(&config).unwrap();
+        // Повторная настройка не приводит к ошибке
+        init(&config).unwrap();
+        REQUEST_ID.scope("test-request".to_owned(), async {
+            log::info!(target: "wildberries_test::logging_init_test", "inside request");
+        }).await;
+        log::debug!(target: "wildberries_test::logging_init_test", "below level");
+        init(&LoggingConfig{file: None, stdout: false, ..config}).unwrap();
+        let content = fs::read_to_string(dir.join("app.log")).unwrap();
+        let lines: Vec<serde_json::Value> = content.lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .filter(|line: &serde_json::Value| line["target"] == "wildberries_test::logging_init_test")
+            .collect();
+        assert_eq!(1, lines.len());
+        assert_eq!("test-request", lines[0]["request_id"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
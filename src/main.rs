@@ -1,12 +1,16 @@
+use std::process::ExitCode;
+use clap::Parser;
 use dotenv::dotenv;
-use wildberries_test::server::Server; 
+use wildberries_test::cli::{self, Cli};
 
 #[actix_web::main]
-async fn main() {
+async fn main() -> ExitCode {
     dotenv().ok();
-    let app = Server::builder()
-        .host("0.0.0.0".to_owned())
-        .port(8080)
-        .build();
-    app.start().await.unwrap();
+    match cli::run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            cli::report(e.as_ref());
+            ExitCode::FAILURE
+        },
+    }
 }
@@ -0,0 +1,424 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use actix_service::Service;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::{get, web, HttpResponse};
+use async_trait::async_trait;
+use log::{error, warn};
+use prometheus::core::Collector;
+use prometheus::{Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use crate::access::{Permission, RaiseLimit};
+use crate::auth::Principal;
+use crate::errors::AppError;
+use crate::migrations::MigrationStatus;
+use crate::models::{Employee, EmployeeData, EmployeeUpdate, EmployeeTermination, EmployeeId, EmployeeName, SalaryMultiplier, SalaryChange, SalaryPercentage, SalaryAdjustment, SalaryBatch, SalaryBatchReport, EmployeeListQuery, EmployeePage, PoolStatus, EmployeeSearch, EmployeeMatch, SalaryHistoryPage, Page};
+use crate::postgres_client::DBClient;
+
+
+/// Границы корзин гистограммы процента повышения зарплаты
+const RAISE_PERCENT_BUCKETS: &[f64] = &[0.5, 1.0, 2.0, 3.0, 5.0, 7.5, 10.0, 15.0, 20.0, 30.0, 50.0, 100.0];
+
+
+/// Метрики приложения в формате Prometheus
+///
+/// Создаются одни на сервер и разделяются между рабочими потоками через web::Data
+pub struct Metrics{
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_call_duration: HistogramVec,
+    db_call_errors: IntCounterVec,
+    db_calls_in_flight: IntGauge,
+    db_pool_size: IntGauge,
+    db_pool_idle: IntGauge,
+    db_pool_in_use: IntGauge,
+    db_pool_max_connections: IntGauge,
+    db_pool_waiters: IntGauge,
+    employees_added: IntCounter,
+    salary_raises: IntCounter,
+    salary_raise_percent: Histogram,
+}
+
+/// Зарегистрировать метрику в реестре
+///
+/// Имена метрик заданы в коде и не повторяются, поэтому ошибка регистрации - ошибка программиста
+fn register<M: Collector + Clone + 'static>(registry: &Registry, metric: prometheus::Result<M>) -> M {
+    let metric = metric.expect("metric definition must be valid");
+    registry.register(Box::new(metric.clone())).expect("metric names must be unique");
+    metric
+}
+
+impl Default for Metrics{
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+impl Metrics{
+    pub fn new() -> Metrics {
+        let registry = Registry::new();
+        let http_labels = &["method", "route", "status"];
+        Metrics{
+            http_requests: register(&registry, IntCounterVec::new(
+                Opts::new("http_requests_total", "Number of /employee requests by route and status"),
+                http_labels)),
+            http_request_duration: register(&registry, HistogramVec::new(
+                HistogramOpts::new("http_request_duration_seconds", "Latency of /employee requests by route and status"),
+                http_labels)),
+            db_call_duration: register(&registry, HistogramVec::new(
+                HistogramOpts::new("db_call_duration_seconds", "Latency of DBClient calls by method"),
+                &["method"])),
+            db_call_errors: register(&registry, IntCounterVec::new(
+                Opts::new("db_call_errors_total", "Number of failed DBClient calls by method and error code"),
+                &["method", "code"])),
+            db_calls_in_flight: register(&registry, IntGauge::new(
+                "db_calls_in_flight", "Number of DBClient calls in progress")),
+            db_pool_size: register(&registry, IntGauge::new(
+                "db_pool_size", "Number of open connections in the pool")),
+            db_pool_idle: register(&registry, IntGauge::new(
+                "db_pool_idle", "Number of idle connections in the pool")),
+            db_pool_in_use: register(&registry, IntGauge::new(
+                "db_pool_in_use", "Number of connections in use")),
+            db_pool_max_connections: register(&registry, IntGauge::new(
+                "db_pool_max_connections", "Maximum number of connections in the pool")),
+            db_pool_waiters: register(&registry, IntGauge::new(
+                "db_pool_waiters", "Estimated number of DBClient calls waiting for a connection")),
+            employees_added: register(&registry, IntCounter::new(
+                "employees_added_total", "Number of employees added")),
+            salary_raises: register(&registry, IntCounter::new(
+                "salary_raises_total", "Number of applied salary raises")),
+            salary_raise_percent: register(&registry, Histogram::with_opts(
+                HistogramOpts::new("salary_raise_percent", "Distribution of applied salary raises in percent")
+                    .buckets(RAISE_PERCENT_BUCKETS.to_vec()))),
+            registry,
+        }
+    }
+
+    /// Учесть обработанный запрос
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_request_duration.with_label_values(&labels).observe(elapsed.as_secs_f64());
+    }
+
+    /// Учесть изменение зарплаты
+    ///
+    /// Повышением считается только рост зарплаты, понижения и изменения без эффекта не учитываются
+    pub fn observe_salary_change(&self, change: &SalaryChange) {
+        let old = change.old_salary.amount_minor;
+        let new = change.new_salary.amount_minor;
+        if new <= old {
+            return;
+        }
+        self.salary_raises.inc();
+        if old > 0 {
+            self.salary_raise_percent.observe((new - old) as f64 * 100.0 / old as f64);
+        }
+    }
+
+    /// Метрики в текстовом формате Prometheus
+    ///
+    /// Состояние пула снимается в момент запроса метрик
+    pub fn render(&self, pool: PoolStatus) -> Result<String, prometheus::Error> {
+        self.db_pool_size.set(pool.size.into());
+        self.db_pool_idle.set(pool.idle.into());
+        self.db_pool_in_use.set(pool.in_use.into());
+        self.db_pool_max_connections.set(pool.max_connections.into());
+        // Каждый вызов держит не больше одного соединения, остальные начатые вызовы ждут его в очереди пула
+        self.db_pool_waiters.set((self.db_calls_in_flight.get() - i64::from(pool.in_use)).max(0));
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+    }
+}
+
+
+/// Шаблон маршрута для метки route
+///
+/// Регулярные выражения параметров отбрасываются: /employee/{id:\d+} превращается в /employee/{id}
+pub(crate) fn route_label(pattern: &str) -> String {
+    let mut label = String::with_capacity(pattern.len());
+    // Глубина вложенности фигурных скобок: регулярное выражение само может их содержать
+    let mut depth = 0;
+    let mut in_regex = false;
+    for c in pattern.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ':' if depth == 1 => in_regex = true,
+            _ => {},
+        }
+        if in_regex && depth == 0 {
+            in_regex = false;
+        }
+        if !in_regex {
+            label.push(c);
+        }
+    }
+    label
+}
+
+/// Метод запроса для метки method
+///
+/// Клиент может прислать любой метод, поэтому нестандартные сводятся к other, чтобы не порождать новые ряды
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::PUT => "PUT",
+        Method::POST => "POST",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        _ => "other",
+    }
+}
+
+/// Учесть запрос в метриках
+///
+/// Подключается через wrap_fn. Метка route берется из шаблона сработавшего маршрута,
+/// чтобы идентификаторы сотрудников не порождали новые ряды
+pub fn track_request<S, B>(request: ServiceRequest, service: &S) -> impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let metrics = request.app_data::<web::Data<Metrics>>().cloned();
+    let method = method_label(request.method());
+    let started = Instant::now();
+    let response = service.call(request);
+    async move {
+        let response = response.await;
+        if let Some(metrics) = metrics {
+            let (route, status) = match &response {
+                Ok(response) => (response.request().match_pattern(), response.status()),
+                Err(e) => (None, e.as_response_error().status_code()),
+            };
+            let route = route.map(|route| route_label(&route)).unwrap_or("unmatched".to_owned());
+            metrics.observe_request(method, &route, status.as_u16(), started.elapsed());
+        }
+        response
+    }
+}
+
+
+/// Метрики в текстовом формате Prometheus
+///
+/// Требуют права ReadMetrics
+#[get("")]
+pub async fn prometheus_metrics(principal: Principal, metrics: web::Data<Metrics>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::ReadMetrics)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    match metrics.render(db_client.pool_status()) {
+        Ok(body) => Ok(HttpResponse::Ok()
+            .content_type(TextEncoder::new().format_type())
+            .body(body)),
+        Err(e) => {
+            error!("Failed to encode metrics: {e}");
+            Ok(HttpResponse::InternalServerError().finish())
+        },
+    }
+}
+
+
+/// Уменьшает число выполняющихся вызовов и при отмене запроса
+struct InFlight<'a>(&'a IntGauge);
+
+impl<'a> InFlight<'a>{
+    fn start(gauge: &'a IntGauge) -> Self {
+        gauge.inc();
+        InFlight(gauge)
+    }
+}
+
+impl Drop for InFlight<'_>{
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+
+/// Клиент базы данных, собирающий метрики
+///
+/// Оборачивает другой клиент и для каждого метода учитывает время выполнения и ошибки.
+/// Через него проходят все успешные изменения, поэтому здесь же считаются бизнес-показатели
+pub struct MeteredDBClient{
+    inner: Arc<dyn DBClient>,
+    metrics: web::Data<Metrics>,
+}
+
+impl MeteredDBClient{
+    pub fn new(inner: Arc<dyn DBClient>, metrics: web::Data<Metrics>) -> Self {
+        MeteredDBClient{inner, metrics}
+    }
+
+    async fn observe<T>(&self, method: &'static str, call: impl Future<Output = Result<T, AppError>>) -> Result<T, AppError> {
+        let in_flight = InFlight::start(&self.metrics.db_calls_in_flight);
+        let started = Instant::now();
+        let result = call.await;
+        drop(in_flight);
+        self.metrics.db_call_duration.with_label_values(&[method]).observe(started.elapsed().as_secs_f64());
+        if let Err(e) = &result {
+            self.metrics.db_call_errors.with_label_values(&[method, e.code()]).inc();
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl DBClient for MeteredDBClient{
+    async fn init_db(&self) -> Result<(), AppError> {
+        self.observe("init_db", self.inner.init_db()).await
+    }
+
+    async fn init_db_clear(&self) -> Result<(), AppError> {
+        self.observe("init_db_clear", self.inner.init_db_clear()).await
+    }
+
+    async fn get_employee_salary(&self, data: EmployeeName) -> Result<Employee, AppError> {
+        self.observe("get_employee_salary", self.inner.get_employee_salary(data)).await
+    }
+
+    async fn add_new_employee(&self, data: EmployeeData) -> Result<Employee, AppError> {
+        let employee = self.observe("add_new_employee", self.inner.add_new_employee(data)).await?;
+        self.metrics.employees_added.inc();
+        Ok(employee)
+    }
+
+    async fn increase_employee_salary(&self, data: SalaryMultiplier, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError> {
+        let change = self.observe("increase_employee_salary", self.inner.increase_employee_salary(data, limit, actor)).await?;
+        self.metrics.observe_salary_change(&change);
+        Ok(change)
+    }
+
+    async fn get_employee_by_id(&self, id: EmployeeId) -> Result<Employee, AppError> {
+        self.observe("get_employee_by_id", self.inner.get_employee_by_id(id)).await
+    }
+
+    async fn increase_employee_salary_by_id(&self, id: EmployeeId, percentage: SalaryPercentage, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError> {
+        let change = self.observe("increase_employee_salary_by_id", self.inner.increase_employee_salary_by_id(id, percentage, limit, actor)).await?;
+        self.metrics.observe_salary_change(&change);
+        Ok(change)
+    }
+
+    async fn adjust_employee_salary(&self, id: EmployeeId, adjustment: SalaryAdjustment, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError> {
+        let change = self.observe("adjust_employee_salary", self.inner.adjust_employee_salary(id, adjustment, limit, actor)).await?;
+        self.metrics.observe_salary_change(&change);
+        Ok(change)
+    }
+
+    async fn increase_salaries(&self, batch: SalaryBatch, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryBatchReport, AppError> {
+        let report = self.observe("increase_salaries", self.inner.increase_salaries(batch, limit, actor)).await?;
+        for change in report.items.iter().filter_map(|item| item.change.as_ref()) {
+            self.metrics.observe_salary_change(change);
+        }
+        Ok(report)
+    }
+
+    async fn update_employee(&self, id: EmployeeId, update: EmployeeUpdate) -> Result<Employee, AppError> {
+        self.observe("update_employee", self.inner.update_employee(id, update)).await
+    }
+
+    async fn terminate_employee(&self, id: EmployeeId) -> Result<EmployeeTermination, AppError> {
+        self.observe("terminate_employee", self.inner.terminate_employee(id)).await
+    }
+
+    async fn delete_employee(&self, id: EmployeeId) -> Result<Employee, AppError> {
+        self.observe("delete_employee", self.inner.delete_employee(id)).await
+    }
+
+    async fn list_employees(&self, query: EmployeeListQuery) -> Result<EmployeePage, AppError> {
+        self.observe("list_employees", self.inner.list_employees(query)).await
+    }
+
+    async fn search_employees(&self, search: EmployeeSearch) -> Result<Vec<EmployeeMatch>, AppError> {
+        self.observe("search_employees", self.inner.search_employees(search)).await
+    }
+
+    async fn get_salary_history(&self, id: EmployeeId, page: Page) -> Result<SalaryHistoryPage, AppError> {
+        self.observe("get_salary_history", self.inner.get_salary_history(id, page)).await
+    }
+
+    async fn ping(&self) -> Result<(), AppError> {
+        self.observe("ping", self.inner.ping()).await
+    }
+
+    async fn migration_status(&self) -> Result<MigrationStatus, AppError> {
+        self.observe("migration_status", self.inner.migration_status()).await
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        self.inner.pool_status()
+    }
+}
+
+
+#[cfg(test)]
+mod tests{
+    use std::sync::Arc;
+    use std::time::Duration;
+    use actix_web::http::Method;
+    use actix_web::web;
+    use crate::errors::AppError;
+    use crate::models::{Employee, EmployeeId, PersonName, SalaryChange};
+    use crate::money::{Currency, Money};
+    use crate::models::PoolStatus;
+    use crate::postgres_client::{DBClient, MockDBClient};
+    use super::{method_label, route_label, MeteredDBClient, Metrics};
+
+    fn change(old: i64, new: i64) -> SalaryChange {
+        let employee = Employee{id: 1, name_parts: PersonName::parse_legacy("Test Employee"), name: "Test Employee".to_owned(), salary: Money::new(new, Currency::Rub), personnel_number: None};
+        SalaryChange{employee, old_salary: Money::new(old, Currency::Rub), new_salary: Money::new(new, Currency::Rub)}
+    }
+
+    #[test]
+    fn route_label_test(){
+        assert_eq!("/employee/{id}/salary/history", route_label("/employee/{id:\\d+}/salary/history"));
+        assert_eq!("/employee/{id}", route_label("/employee/{id}"));
+        assert_eq!("/employee/salary", route_label("/employee/salary"));
+        assert_eq!("/employee/{code}", route_label("/employee/{code:[a-z]{2}}"));
+    }
+
+    #[test]
+    fn method_label_test(){
+        assert_eq!("PATCH", method_label(&Method::PATCH));
+        assert_eq!("other", method_label(&Method::TRACE));
+        assert_eq!("other", method_label(&Method::from_bytes(b"BREW").unwrap()));
+    }
+
+    #[test]
+    fn render_test(){
+        let metrics = Metrics::new();
+        metrics.observe_request("GET", "/employee/{id}", 200, Duration::from_millis(3));
+        metrics.observe_salary_change(&change(10000, 11000));
+        metrics.observe_salary_change(&change(10000, 9000));
+        let text = metrics.render(PoolStatus::new(3, 1, 7)).unwrap();
+        assert!(text.contains(r#"http_requests_total{method="GET",route="/employee/{id}",status="200"} 1"#));
+        assert!(text.contains("salary_raises_total 1"));
+        assert!(text.contains(r#"salary_raise_percent_bucket{le="10"} 1"#));
+        assert!(text.contains(r#"salary_raise_percent_bucket{le="7.5"} 0"#));
+        assert!(text.contains("db_pool_in_use 2"));
+        assert!(text.contains("db_pool_max_connections 7"));
+        assert!(text.contains("db_pool_waiters 0"));
+    }
+
+    #[actix_web::test]
+    async fn metered_client_test(){
+        let mut db_client = MockDBClient::new();
+        db_client.expect_get_employee_by_id()
+            .returning(|id| match id.id {
+                1 => Ok(change(0, 10000).employee),
+                _ => Err(AppError::NotFound("employee does not exist".to_owned())),
+            });
+        let metrics = web::Data::new(Metrics::new());
+        let client = MeteredDBClient::new(Arc::new(db_client), metrics.clone());
+        client.get_employee_by_id(EmployeeId{id: 1}).await.unwrap();
+        client.get_employee_by_id(EmployeeId{id: 2}).await.unwrap_err();
+        let text = metrics.render(PoolStatus::new(1, 1, 7)).unwrap();
+        assert!(text.contains(r#"db_call_duration_seconds_count{method="get_employee_by_id"} 2"#));
+        assert!(text.contains(r#"db_call_errors_total{code="not_found",method="get_employee_by_id"} 1"#));
+        assert!(text.contains("db_calls_in_flight 0"));
+    }
+}
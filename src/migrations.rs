@@ -0,0 +1,172 @@
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgConnection, Postgres};
+use sqlx::Pool;
+use std::str::FromStr;
+use crate::errors::AppError;
+use crate::models::{normalize_name, PersonName};
+
+
+/// Миграции схемы БД
+///
+/// Файлы из каталога migrations встраиваются в бинарник на этапе сборки
+/// и применяются по возрастанию версии. Учет примененных миграций ведется в таблице _sqlx_migrations
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+
+/// Режим работы с миграциями при старте
+///
+/// Задается настройкой database.migrations (DB_MIGRATIONS)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MigrationMode{
+    /// Применить недостающие миграции
+    #[default]
+    Apply,
+    /// Только проверить схему и отказаться от запуска, если она отстает
+    Check,
+}
+
+impl FromStr for MigrationMode{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "apply" => Ok(MigrationMode::Apply),
+            "check" => Ok(MigrationMode::Check),
+            _ => Err(AppError::validation("DB_MIGRATIONS", "migration mode must be either \"apply\" or \"check\"")),
+        }
+    }
+}
+
+
+/// Состояние миграций
+///
+/// Версии примененных миграций и миграций, ожидающих применения
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct MigrationStatus{
+    pub applied: Vec<i64>,
+    pub pending: Vec<i64>,
+}
+
+impl MigrationStatus{
+    pub fn is_up_to_date(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+
+/// Применить недостающие миграции
+///
+/// Одновременный запуск нескольких экземпляров безопасен: мигратор берет advisory lock.
+/// После схемных миграций в одной транзакции заполняются данные, которые вычисляет приложение
+pub async fn apply(pool: &Pool<Postgres>) -> Result<(), AppError> {
+    MIGRATOR.run(pool).await
+        .map_err(|e| AppError::Storage(format!("failed to apply migrations: {e}")))?;
+    let mut tx = pool.begin().await?;
+    fill_normalized_names(&mut tx).await?;
+    fill_name_parts(&mut tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Заполнить нормализованные имена у записей, добавленных до их появления
+///
+/// Нормализация выполняется на стороне приложения, чтобы не зависеть от локали базы
+async fn fill_normalized_names(conn: &mut PgConnection) -> Result<(), AppError> {
+    let unnormalized: Vec<(i32, String)> = sqlx::query_as(r#"SELECT id, name FROM employees WHERE normalized_name IS NULL FOR UPDATE"#)
+        .fetch_all(&mut *conn)
+        .await?;
+    for (id, name) in unnormalized {
+        sqlx::query(r#"UPDATE employees SET normalized_name = $1 WHERE id = $2"#)
+            .bind(normalize_name(&name))
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Заполнить части имени у записей, добавленных до их появления
+///
+/// Имя разбирается на стороне приложения теми же правилами, что и при добавлении одной строкой
+async fn fill_name_parts(conn: &mut PgConnection) -> Result<(), AppError> {
+    let unparsed: Vec<(i32, String)> = sqlx::query_as(r#"SELECT id, name FROM employees WHERE surname IS NULL FOR UPDATE"#)
+        .fetch_all(&mut *conn)
+        .await?;
+    for (id, name) in unparsed {
+        let parts = PersonName::parse_legacy(&name);
+        sqlx::query(r#"UPDATE employees SET surname = $1, given_name = $2, patronymic = $3, display_name = $4, normalized_surname = $5 WHERE id = $6"#)
+            .bind(&parts.surname)
+            .bind(&parts.given_name)
+            .bind(&parts.patronymic)
+            .bind(&parts.display_name)
+            .bind(normalize_name(&parts.surname))
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Получить состояние миграций
+///
+/// Не изменяет схему: если таблицы учета еще нет, все миграции считаются ожидающими
+pub async fn status(conn: &mut PgConnection) -> Result<MigrationStatus, AppError> {
+    let (exists,): (bool,) = sqlx::query_as(r#"SELECT to_regclass('_sqlx_migrations') IS NOT NULL"#)
+        .fetch_one(&mut *conn)
+        .await?;
+    let applied: Vec<i64> = if exists {
+        sqlx::query_as(r#"SELECT version FROM _sqlx_migrations WHERE success ORDER BY version"#)
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .map(|(version,)| version)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let pending = MIGRATOR.iter()
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect();
+    Ok(MigrationStatus{applied, pending})
+}
+
+/// Проверить, что схема не отстает от встроенных миграций
+///
+/// Только читает: записи без нормализованного имени или частей имени
+/// означают, что заполнение данных после миграций еще не выполнялось
+pub async fn check(conn: &mut PgConnection) -> Result<MigrationStatus, AppError> {
+    let status = status(conn).await?;
+    if !status.is_up_to_date() {
+        Err(AppError::Storage(format!("database schema is behind, pending migrations: {:?}", status.pending)))?;
+    }
+    let (unfilled,): (i64,) = sqlx::query_as(r#"SELECT COUNT(*) FROM employees WHERE normalized_name IS NULL OR surname IS NULL"#)
+        .fetch_one(&mut *conn)
+        .await?;
+    if unfilled > 0 {
+        Err(AppError::Storage(format!("database data is behind, {unfilled} employees are waiting for backfill")))?;
+    }
+    Ok(status)
+}
+
+
+#[cfg(test)]
+mod tests{
+    use super::{MigrationMode, MIGRATOR};
+
+    #[test]
+    fn migration_mode_parse_test(){
+        assert_eq!(MigrationMode::Apply, "apply".parse().unwrap());
+        assert_eq!(MigrationMode::Check, "check".parse().unwrap());
+        assert!("skip".parse::<MigrationMode>().is_err());
+    }
+
+    #[test]
+    fn migrations_are_ordered_test(){
+        let versions: Vec<i64> = MIGRATOR.iter().map(|migration| migration.version).collect();
+        let mut sorted = versions.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted, versions);
+    }
+}
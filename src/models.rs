@@ -1,43 +1,180 @@
-use std::error::Error;
-use std::fmt::Display;
 use sqlx::FromRow;
+use std::collections::HashSet;
+use std::str::FromStr;
+use chrono::{DateTime, Utc};
+use crate::errors::AppError;
+use crate::migrations::MigrationStatus;
+use crate::names::{NamePolicy, MAX_NAME_LENGTH};
+use crate::money::{deserialize_decimal, deserialize_optional_decimal, Currency, Money, Percentage, RoundingMode, MAX_AMOUNT_MINOR};
 
 
 // Полезные инструменты
 
-#[derive(Debug)]
-struct CustomError<'a>{
-    msg: &'a str
+/// Проверка имени, по которому ищут сотрудника
+///
+/// Мягче правил NamePolicy, чтобы находить сотрудников, добавленных до их ужесточения
+fn check_name(name: &str) -> Result<String, AppError> {
+    let name = NamePolicy::clean(name);
+    if name.is_empty(){
+        Err(AppError::validation("name", "employee name cannot consist of whitespaces or have zero length"))?
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        Err(AppError::validation("name", &format!("employee name cannot be longer than {MAX_NAME_LENGTH} characters")))?
+    }
+    Ok(name)
 }
 
-impl<'a> Display for CustomError<'a>{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.msg) 
+fn check_salary(salary: &Money) -> Result<(), AppError> {
+    if salary.amount_minor <= 0 {
+        Err(AppError::validation("salary", "employee's salary cannot be less than or equal to zero"))?;
+    }
+    if salary.amount_minor > MAX_AMOUNT_MINOR {
+        Err(AppError::validation("salary", "employee's salary is too large"))?;
     }
+    Ok(())
 }
 
-impl<'a> Error for CustomError<'a>{
+fn check_currency(currency: &Option<String>) -> Result<Currency, AppError> {
+    match currency {
+        Some(code) => code.parse(),
+        None => Ok(Currency::default()),
+    }
 }
 
-fn check_name(name: &str) -> Result<(), Box<dyn Error>> {
-    if name.trim().is_empty(){
-        Err(CustomError{msg: "employee name cannot consist of whitespaces or have zero length"})?
+fn check_personnel_number(personnel_number: &Option<String>) -> Result<(), AppError> {
+    if let Some(number) = personnel_number {
+        if number.trim().is_empty() || number.len() > 64 {
+            Err(AppError::validation("personnel_number", "personnel number must be non-empty and at most 64 bytes long"))?;
+        }
     }
     Ok(())
 }
 
-fn check_salary(salary: i32) -> Result<(), Box<dyn Error>> {
-    if salary <= 0 {
-        Err(CustomError{msg: "employee's salary cannot be less than or equal to zero"})?;
+fn check_percentage(percentage: &str) -> Result<Percentage, AppError> {
+    let percentage = Percentage::parse(percentage, "percentage")?;
+    if percentage.hundredths <= 0 {
+        Err(AppError::validation("percentage", "salary percentage increase cannot be less than or equal to zero"))?;
     }
-    Ok(())
+    Ok(percentage)
 }
 
-fn check_percentage(percentage: i32) -> Result<(), Box<dyn Error>> {
-    if percentage <= 0 {
-        Err(CustomError{msg: "salary percentage increase cannot be less than or equal to zero"})?;
+fn check_adjustment_currency(currency: &Option<Currency>, salary_currency: Currency) -> Result<(), AppError> {
+    match currency {
+        Some(currency) if *currency != salary_currency => Err(AppError::validation(
+            "currency", &format!("employee is paid in {salary_currency}, not in {currency}")
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_rounding(rounding: &Option<String>) -> Result<RoundingMode, AppError> {
+    match rounding {
+        Some(mode) => mode.parse(),
+        None => Ok(RoundingMode::default()),
+    }
+}
+
+
+/// Нормализованная форма имени
+///
+/// Используется для проверки уникальности: регистр, лишние пробелы и форма записи Unicode не различаются
+pub fn normalize_name(name: &str) -> String {
+    NamePolicy::clean(name).to_lowercase()
+}
+
+
+/// Тюркские отчества записываются отдельным словом после имени отца
+const TURKIC_PATRONYMICS: [&str; 3] = ["оглы", "кызы", "улы"];
+
+/// Похоже ли слово на отчество
+///
+/// Отчества образуются суффиксами -ович/-евич/-ич и -овна/-евна/-ична
+fn is_patronymic(word: &str) -> bool {
+    let word = word.to_lowercase();
+    ["ич", "овна", "евна", "ична"].iter().any(|suffix| word.ends_with(suffix))
+        || TURKIC_PATRONYMICS.contains(&word.as_str())
+}
+
+
+/// Модель структурированного имени сотрудника
+///
+/// Фамилия, имя, необязательное отчество и необязательное отображаемое имя.
+/// Если отображаемое имя не задано, сотрудник отображается как "Фамилия Имя Отчество"
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct PersonName{
+    pub surname: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub given_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patronymic: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+}
+
+impl PersonName{
+    /// Разобрать имя, записанное одной строкой
+    ///
+    /// Слова читаются в порядке "Фамилия Имя Отчество". Если отчеством похоже второе слово,
+    /// а третье нет, имя читается как "Имя Отчество Фамилия". Из одного слова получается только фамилия.
+    /// Если исходная запись отличается от составленной из частей, она сохраняется как отображаемое имя
+    pub fn parse_legacy(name: &str) -> PersonName {
+        let name = NamePolicy::clean(name);
+        let words: Vec<&str> = name.split(' ').collect();
+        let (surname, given_name, patronymic) = match words.as_slice() {
+            [given_name, patronymic, surname] if is_patronymic(patronymic) && !is_patronymic(surname) => {
+                (surname.to_string(), Some(given_name.to_string()), Some(patronymic.to_string()))
+            },
+            [surname, given_name, patronymic] => (surname.to_string(), Some(given_name.to_string()), Some(patronymic.to_string())),
+            [surname, given_name, father @ .., last] if TURKIC_PATRONYMICS.contains(&last.to_lowercase().as_str()) => {
+                (surname.to_string(), Some(given_name.to_string()), Some(format!("{} {last}", father.join(" "))))
+            },
+            [surname, given_name @ .., last] if is_patronymic(last) && !given_name.is_empty() => (surname.to_string(), Some(given_name.join(" ")), Some(last.to_string())),
+            [surname, given_name @ ..] if !given_name.is_empty() => (surname.to_string(), Some(given_name.join(" ")), None),
+            _ => (name.clone(), None, None),
+        };
+        let mut parts = PersonName{surname, given_name, patronymic, display_name: None};
+        if parts.full_name() != name {
+            parts.display_name = Some(name);
+        }
+        parts
+    }
+
+    /// Полное имя в порядке "Фамилия Имя Отчество"
+    pub fn full_name(&self) -> String {
+        [Some(&self.surname), self.given_name.as_ref(), self.patronymic.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// Имя, под которым сотрудник отображается и ищется
+    pub fn display(&self) -> String {
+        self.display_name.clone().unwrap_or_else(|| self.full_name())
+    }
+}
+
+
+/// Накопитель нарушений
+///
+/// Проверки всех полей выполняются до конца, а нарушения возвращаются разом
+#[derive(Default)]
+struct Violations(Vec<AppError>);
+
+impl Violations{
+    fn check<T>(&mut self, result: Result<T, AppError>) -> Option<T> {
+        result.map_err(|error| self.0.push(error)).ok()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn into_error(self) -> AppError {
+        AppError::from_violations(self.0)
+            .unwrap_or(AppError::validation("query", "request is invalid"))
     }
-    Ok(())
 }
 
 
@@ -47,8 +184,10 @@ fn check_percentage(percentage: i32) -> Result<(), Box<dyn Error>> {
 /// Модель непроверенного имени сотрудника
 ///
 /// Имя сотрудника, приходящее с эндпоинта и подлежащее проверке
-#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone)]
+#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UncheckedEmployeeName{
+    #[param(min_length = 1, max_length = 255)]
     name: String,
 }
 
@@ -56,10 +195,11 @@ impl UncheckedEmployeeName{
     /// Sanity-check для входящего имени
     ///
     /// Преобразует непроверенные данные в проверенные, поглощая объект.
-    /// Проверка - имя не должно состоять из пробелов или иметь нулевую длину
-    pub fn check(self) -> Result<EmployeeName, Box<dyn Error>> {
-        check_name(&self.name)?;
-        Ok(EmployeeName{name:self.name})
+    /// Проверка - имя не должно состоять из пробелов, иметь нулевую длину или не помещаться в колонку.
+    /// Имя приводится к NFC, пробелы по краям отбрасываются, а внутри схлопываются
+    pub fn check(self) -> Result<EmployeeName, AppError> {
+        let name = check_name(&self.name)?;
+        Ok(EmployeeName{name})
     }
 }
 
@@ -76,20 +216,23 @@ pub struct EmployeeName{
 
 /// Модель Непроверенной зарплаты сотрудника
 ///
-/// Значение зарплаты сотрудника, приходящее с эндпоинта и подлежащее проверке
+/// Значение зарплаты сотрудника в минорных единицах и код валюты,
+/// приходящие из базы и подлежащие проверке
 #[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone)]
 pub struct UncheckedEmployeeSalary{
-    amount: i32,
+    amount_minor: i64,
+    currency: String,
 }
 
 impl UncheckedEmployeeSalary{
     /// Sanity-check для значения зарплаты сотрудника
     ///
     /// Преобразует непроверенные данные в проверенные, поглощая объект
-    /// Проверка - зарплата не может быть меньше либо равной нулю
-    pub fn check(self) -> Result<EmployeeSalary, Box<dyn Error>> {
-        check_salary(self.amount)?;
-        Ok(EmployeeSalary{amount:self.amount})
+    /// Проверка - валюта должна поддерживаться, зарплата не может быть меньше либо равной нулю
+    pub fn check(self) -> Result<EmployeeSalary, AppError> {
+        let amount = Money::new(self.amount_minor, self.currency.parse()?);
+        check_salary(&amount)?;
+        Ok(EmployeeSalary{amount})
     }
 }
 
@@ -97,31 +240,49 @@ impl UncheckedEmployeeSalary{
 /// Модель Зарплаты сотрудника
 ///
 /// Проверенное значение зарплаты сотрудника
-#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, FromRow)]
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct EmployeeSalary{
-    pub amount: i32,
+    pub amount: Money,
 }
 
 impl EmployeeSalary{
     /// Увеличить зарплату на процент
     ///
     /// Увеличивает зарплату на определенный процент с необходимыми проверками и делает возвращает
-    /// старое значение
-    pub fn increase_by_percentage(&mut self, percent: &SalaryMultiplier) -> Result<EmployeeSalary, Box<dyn Error>> {
+    /// старое значение. Результат округляется до минорной единицы выбранным способом
+    pub fn increase_by_percentage(&mut self, percent: &SalaryPercentage) -> Result<EmployeeSalary, AppError> {
+        self.adjust(&percent.to_adjustment())
+    }
+
+    /// Изменить зарплату
+    ///
+    /// Применяет изменение с необходимыми проверками и возвращает старое значение.
+    /// Снижение на процент не опускает зарплату ниже одной минорной единицы,
+    /// а изменение на сумму или установка значения, после которых зарплата не положительна, отклоняются
+    pub fn adjust(&mut self, adjustment: &SalaryAdjustment) -> Result<EmployeeSalary, AppError> {
         let old_salary = self.clone();
-        let mut addition = old_salary.amount;
-        addition = addition.checked_mul(percent.percentage)
-            .ok_or(CustomError{msg:"employee's salary is too high to perform math operations"})?;
-        addition = addition.checked_add(100)
-            .ok_or(CustomError{msg:"employee's salary is too high to perform math operations"})?;
-        addition = addition.checked_sub(1)
-            .ok_or(CustomError{msg:"employee's salary is too low to perform math operations"})?;
-        addition = addition.checked_div(100)
-            .ok_or(CustomError{msg:"employee's salary is too low to perform math operations"})?;
-
-        self.amount = self.amount.checked_add(addition)
-            .ok_or(CustomError{msg:"employee's salary is too high to perform math operations"})?;
-        check_salary(self.amount)?;
+        let amount_minor = match adjustment {
+            SalaryAdjustment::Percentage{percentage, rounding} => {
+                let amount = self.amount.add_percentage(*percentage, *rounding)?;
+                amount.amount_minor.max(1)
+            },
+            SalaryAdjustment::Delta{amount_minor, currency} => {
+                check_adjustment_currency(currency, self.amount.currency)?;
+                self.amount.amount_minor.checked_add(*amount_minor)
+                    .ok_or(AppError::Overflow("employee's salary is too high to perform math operations".to_owned()))?
+            },
+            SalaryAdjustment::Set{amount_minor, currency} => {
+                check_adjustment_currency(currency, self.amount.currency)?;
+                *amount_minor
+            },
+        };
+        if amount_minor > MAX_AMOUNT_MINOR {
+            Err(AppError::Overflow("employee's salary is too high to be stored".to_owned()))?;
+        }
+        if amount_minor <= 0 {
+            Err(AppError::validation("value", "salary adjustment cannot make the salary less than or equal to zero"))?;
+        }
+        self.amount = Money::new(amount_minor, self.amount.currency);
         Ok(old_salary)
     }
 }
@@ -131,17 +292,90 @@ impl EmployeeSalary{
 /// Модель Непроверенных данных о работнике
 ///
 /// Данные, приходящие с эндпоинта и посдлежащие проверке
-#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone)]
+#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone, Default, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UncheckedEmployeeData{
+    #[param(max_length = 255)]
+    #[schema(max_length = 255)]
+    #[serde(default)]
     name: String,
-    salary: i32,
+    #[param(max_length = 255)]
+    #[schema(max_length = 255)]
+    #[serde(default)]
+    surname: Option<String>,
+    #[param(max_length = 255)]
+    #[schema(max_length = 255)]
+    #[serde(default)]
+    given_name: Option<String>,
+    #[param(max_length = 255)]
+    #[schema(max_length = 255)]
+    #[serde(default)]
+    patronymic: Option<String>,
+    #[param(max_length = 255)]
+    #[schema(max_length = 255)]
+    #[serde(default)]
+    display_name: Option<String>,
+    /// Зарплата больше нуля, не более двух знаков после точки
+    #[param(pattern = r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$", example = "8000.50")]
+    #[schema(pattern = r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$", example = "8000.50")]
+    #[serde(deserialize_with = "deserialize_decimal")]
+    salary: String,
+    #[param(value_type = Option<Currency>)]
+    #[schema(value_type = Option<Currency>)]
+    #[serde(default)]
+    currency: Option<String>,
+    #[param(min_length = 1, max_length = 64)]
+    #[schema(min_length = 1, max_length = 64)]
+    #[serde(default)]
+    personnel_number: Option<String>,
 }
 
 impl UncheckedEmployeeData{
-    pub fn check(self) -> Result<EmployeeData, Box<dyn Error>> {
-        check_name(&self.name)?;
-        check_salary(self.salary)?;
-        Ok(EmployeeData { name: self.name, salary: self.salary })
+    /// Sanity-check для данных о сотруднике по правилам по умолчанию
+    pub fn check(self) -> Result<EmployeeData, AppError> {
+        self.check_with(&NamePolicy::default())
+    }
+
+    /// Sanity-check для данных о сотруднике
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Имя задается либо одной строкой name, либо частями surname, given_name, patronymic
+    /// и необязательным display_name. Части проверяются и приводятся к каноническому виду по правилам policy,
+    /// зарплата задается десятичной записью, валюта по умолчанию - рубли.
+    /// Нарушения во всех полях возвращаются разом
+    pub fn check_with(self, policy: &NamePolicy) -> Result<EmployeeData, AppError> {
+        let mut violations = Violations::default();
+        let name_parts = if self.surname.is_some() || self.given_name.is_some() || self.patronymic.is_some() || self.display_name.is_some() {
+            if !self.name.is_empty() {
+                violations.check::<()>(Err(AppError::validation("name", "name cannot be combined with surname, given_name, patronymic and display_name")));
+            }
+            let mut part = |field: &'static str, value: Option<String>, required: bool| match value {
+                Some(value) => violations.check(policy.check(field, &value)).map(Some),
+                None if required => violations.check(Err(AppError::validation(field, &format!("{field} is required when the name is given by parts")))),
+                None => Some(None),
+            };
+            let surname = part("surname", self.surname, true);
+            let given_name = part("given_name", self.given_name, true);
+            let patronymic = part("patronymic", self.patronymic, false);
+            let display_name = part("display_name", self.display_name, false);
+            match (surname, given_name, patronymic, display_name) {
+                (Some(Some(surname)), Some(given_name), Some(patronymic), Some(display_name)) => {
+                    let parts = PersonName{surname, given_name, patronymic, display_name};
+                    violations.check(policy.check("name", &parts.display())).map(|_| parts)
+                },
+                _ => None,
+            }
+        } else {
+            violations.check(policy.check("name", &self.name)).map(|name| PersonName::parse_legacy(&name))
+        };
+        let currency = violations.check(check_currency(&self.currency)).unwrap_or_default();
+        let salary = violations.check(Money::parse(&self.salary, currency, "salary")
+            .and_then(|salary| check_salary(&salary).map(|_| salary)));
+        violations.check(check_personnel_number(&self.personnel_number));
+        match (name_parts, salary) {
+            (Some(name_parts), Some(salary)) if violations.is_empty() => Ok(EmployeeData{name: name_parts.display(), name_parts, salary, personnel_number: self.personnel_number}),
+            _ => Err(violations.into_error()),
+        }
     }
 }
 
@@ -149,31 +383,50 @@ impl UncheckedEmployeeData{
 /// Модель данных о работнике
 ///
 /// Проверенное значение данных о работнике
-#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone)]
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct EmployeeData{
     pub name: String,
-    pub salary: i32,
+    pub name_parts: PersonName,
+    pub salary: Money,
+    pub personnel_number: Option<String>,
 }
 
 
 /// Модель Непроверенного процента повышения зарплаты
 ///
 /// Процент повышения зарплаты, приходящий с эндпоинта и посдлежащий проверке
-#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone)]
+#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UncheckedSalaryMultiplier{
+    #[param(min_length = 1, max_length = 255)]
+    #[schema(min_length = 1, max_length = 255)]
     name: String,
-    percentage: i32,
+    /// Процент повышения больше нуля, не более двух знаков после точки
+    #[param(pattern = r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$", example = "3.5")]
+    #[schema(pattern = r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$", example = "3.5")]
+    #[serde(deserialize_with = "deserialize_decimal")]
+    percentage: String,
+    #[param(value_type = Option<RoundingMode>)]
+    #[schema(value_type = Option<RoundingMode>)]
+    #[serde(default)]
+    rounding: Option<String>,
 }
 
 impl UncheckedSalaryMultiplier{
     /// Sanity-check для значения процента от зарплаты
     ///
     /// Преобразует непроверенные данные в проверенные, поглощая объект
-    /// Проверка - процент не может быть равен нулю
-    pub fn check(self) -> Result<SalaryMultiplier, Box<dyn Error>> {
-        check_name(&self.name)?;
-        check_percentage(self.percentage)?;
-        Ok(SalaryMultiplier{percentage: self.percentage, name: self.name})
+    /// Проверка - процент не может быть равен нулю и задается не точнее сотых,
+    /// способ округления по умолчанию - вверх
+    pub fn check(self) -> Result<SalaryMultiplier, AppError> {
+        let mut violations = Violations::default();
+        let name = violations.check(check_name(&self.name));
+        let percentage = violations.check(check_percentage(&self.percentage));
+        let rounding = violations.check(check_rounding(&self.rounding));
+        match (name, percentage, rounding) {
+            (Some(name), Some(percentage), Some(rounding)) => Ok(SalaryMultiplier{percentage, rounding, name}),
+            _ => Err(violations.into_error()),
+        }
     }
 }
 
@@ -182,21 +435,1069 @@ impl UncheckedSalaryMultiplier{
 /// Модель процента повышения зарплаты сотрудника
 ///
 /// Проверенное значение зарплаты сотрудника
-#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone)]
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct SalaryMultiplier{
     pub name: String,
-    pub percentage: i32,
+    pub percentage: Percentage,
+    #[serde(default)]
+    pub rounding: RoundingMode,
 }
 
 impl SalaryMultiplier{
     pub fn get_name(&self) -> EmployeeName {
         EmployeeName{name: self.name.to_owned()}
     } 
+
+    pub fn get_percentage(&self) -> SalaryPercentage {
+        SalaryPercentage{percentage: self.percentage, rounding: self.rounding}
+    }
+}
+
+
+/// Модель Непроверенного процента повышения зарплаты без имени сотрудника
+///
+/// Процент повышения зарплаты для сотрудника, адресованного идентификатором
+#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UncheckedSalaryPercentage{
+    /// Процент повышения больше нуля, не более двух знаков после точки
+    #[param(pattern = r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$", example = "3.5")]
+    #[schema(pattern = r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$", example = "3.5")]
+    #[serde(deserialize_with = "deserialize_decimal")]
+    percentage: String,
+    #[param(value_type = Option<RoundingMode>)]
+    #[schema(value_type = Option<RoundingMode>)]
+    #[serde(default)]
+    rounding: Option<String>,
+}
+
+impl UncheckedSalaryPercentage{
+    /// Sanity-check для значения процента от зарплаты
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Проверка - процент не может быть меньше либо равным нулю и задается не точнее сотых,
+    /// способ округления по умолчанию - вверх
+    pub fn check(self) -> Result<SalaryPercentage, AppError> {
+        let percentage = check_percentage(&self.percentage)?;
+        let rounding = check_rounding(&self.rounding)?;
+        Ok(SalaryPercentage{percentage, rounding})
+    }
+}
+
+
+/// Модель процента повышения зарплаты
+///
+/// Проверенное значение процента повышения зарплаты и способ округления результата
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct SalaryPercentage{
+    pub percentage: Percentage,
+    #[serde(default)]
+    pub rounding: RoundingMode,
+}
+
+impl SalaryPercentage{
+    pub fn to_adjustment(&self) -> SalaryAdjustment {
+        SalaryAdjustment::Percentage{percentage: self.percentage, rounding: self.rounding}
+    }
+}
+
+
+/// Вид изменения зарплаты
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SalaryOperation{
+    /// Изменение на процент
+    Percentage,
+    /// Изменение на сумму
+    Delta,
+    /// Установка нового значения
+    Set,
+}
+
+impl SalaryOperation{
+    pub fn name(&self) -> &'static str {
+        match self {
+            SalaryOperation::Percentage => "percentage",
+            SalaryOperation::Delta => "delta",
+            SalaryOperation::Set => "set",
+        }
+    }
+}
+
+impl FromStr for SalaryOperation{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "percentage" => Ok(SalaryOperation::Percentage),
+            "delta" => Ok(SalaryOperation::Delta),
+            "set" => Ok(SalaryOperation::Set),
+            _ => Err(AppError::validation("operation", "operation must be one of percentage, delta, set")),
+        }
+    }
+}
+
+
+/// Модель Непроверенного изменения зарплаты
+///
+/// Изменение зарплаты, приходящее с эндпоинта и подлежащее проверке.
+/// Значение value трактуется в зависимости от вида операции: процент, сумма изменения или новая зарплата
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UncheckedSalaryAdjustment{
+    #[param(value_type = SalaryOperation)]
+    #[schema(value_type = SalaryOperation)]
+    operation: String,
+    /// Процент больше -100, сумма изменения или новая зарплата, не более двух знаков после точки
+    #[param(pattern = r"^-?\d+(\.\d{1,2})?$", example = "-5")]
+    #[schema(pattern = r"^-?\d+(\.\d{1,2})?$", example = "-5")]
+    #[serde(deserialize_with = "deserialize_decimal")]
+    value: String,
+    #[param(value_type = Option<Currency>)]
+    #[schema(value_type = Option<Currency>)]
+    #[serde(default)]
+    currency: Option<String>,
+    #[param(value_type = Option<RoundingMode>)]
+    #[schema(value_type = Option<RoundingMode>)]
+    #[serde(default)]
+    rounding: Option<String>,
+}
+
+impl UncheckedSalaryAdjustment{
+    /// Sanity-check для изменения зарплаты
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Проверка - процент не равен нулю и больше -100, сумма изменения не равна нулю,
+    /// новая зарплата положительна. Округление задается только для процента, валюта - только для сумм
+    pub fn check(self) -> Result<SalaryAdjustment, AppError> {
+        let operation: SalaryOperation = self.operation.parse()?;
+        match operation {
+            SalaryOperation::Percentage => {
+                if self.currency.is_some() {
+                    Err(AppError::validation("currency", "currency is only allowed for delta and set operations"))?;
+                }
+                let percentage = Percentage::parse(&self.value, "value")?;
+                if percentage.hundredths == 0 {
+                    Err(AppError::validation("value", "salary percentage change cannot be zero"))?;
+                }
+                if percentage <= Percentage::whole(-100) {
+                    Err(AppError::validation("value", "salary cannot be cut by 100 percent or more"))?;
+                }
+                let rounding = check_rounding(&self.rounding)?;
+                Ok(SalaryAdjustment::Percentage{percentage, rounding})
+            },
+            SalaryOperation::Delta | SalaryOperation::Set => {
+                if self.rounding.is_some() {
+                    Err(AppError::validation("rounding", "rounding is only allowed for percentage operations"))?;
+                }
+                let currency = match self.currency {
+                    Some(code) => Some(code.parse::<Currency>()?),
+                    None => None,
+                };
+                let amount = Money::parse(&self.value, currency.unwrap_or_default(), "value")?;
+                if operation == SalaryOperation::Delta {
+                    if amount.amount_minor == 0 {
+                        Err(AppError::validation("value", "salary change cannot be zero"))?;
+                    }
+                    Ok(SalaryAdjustment::Delta{amount_minor: amount.amount_minor, currency})
+                } else {
+                    check_salary(&amount).map_err(|_| AppError::validation("value", "new salary must be positive and fit into the salary column"))?;
+                    Ok(SalaryAdjustment::Set{amount_minor: amount.amount_minor, currency})
+                }
+            },
+        }
+    }
+}
+
+
+/// Модель изменения зарплаты
+///
+/// Проверенное изменение зарплаты. Если валюта суммы указана, она должна совпадать с валютой зарплаты
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub enum SalaryAdjustment{
+    /// Изменение на процент, положительный или отрицательный
+    Percentage{
+        percentage: Percentage,
+        rounding: RoundingMode,
+    },
+    /// Изменение на сумму в минорных единицах, положительную или отрицательную
+    Delta{
+        amount_minor: i64,
+        currency: Option<Currency>,
+    },
+    /// Установка нового значения в минорных единицах
+    Set{
+        amount_minor: i64,
+        currency: Option<Currency>,
+    },
+}
+
+impl SalaryAdjustment{
+    pub fn operation(&self) -> SalaryOperation {
+        match self {
+            SalaryAdjustment::Percentage{..} => SalaryOperation::Percentage,
+            SalaryAdjustment::Delta{..} => SalaryOperation::Delta,
+            SalaryAdjustment::Set{..} => SalaryOperation::Set,
+        }
+    }
+}
+
+
+/// Модель Непроверенного идентификатора сотрудника
+///
+/// Идентификатор сотрудника, приходящий из пути запроса и подлежащий проверке
+#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone, utoipa::IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct UncheckedEmployeeId{
+    #[param(minimum = 1)]
+    id: i32,
+}
+
+impl UncheckedEmployeeId{
+    /// Sanity-check для идентификатора сотрудника
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Проверка - идентификатор должен быть положительным
+    pub fn check(self) -> Result<EmployeeId, AppError> {
+        if self.id <= 0 {
+            Err(AppError::validation("id", "employee id must be a positive number"))?;
+        }
+        Ok(EmployeeId{id: self.id})
+    }
+}
+
+
+/// Модель идентификатора сотрудника
+///
+/// Проверенный идентификатор сотрудника
+#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone, Copy, PartialEq)]
+pub struct EmployeeId{
+    pub id: i32,
+}
+
+/// Модель Непроверенной записи о сотруднике
+///
+/// Запись о сотруднике в том виде, в котором она хранится в базе, подлежащая проверке
+#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone, Default)]
+pub struct UncheckedEmployee{
+    id: i32,
+    name: String,
+    surname: Option<String>,
+    given_name: Option<String>,
+    patronymic: Option<String>,
+    display_name: Option<String>,
+    salary_minor: i64,
+    currency: String,
+    personnel_number: Option<String>,
+}
+
+impl UncheckedEmployee{
+    /// Sanity-check для записи о сотруднике
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Если части имени еще не заполнены, они разбираются из имени
+    pub fn check(self) -> Result<Employee, AppError> {
+        let salary = UncheckedEmployeeSalary{amount_minor: self.salary_minor, currency: self.currency}.check()?;
+        let name_parts = match self.surname {
+            Some(surname) => PersonName{surname, given_name: self.given_name, patronymic: self.patronymic, display_name: self.display_name},
+            None => PersonName::parse_legacy(&self.name),
+        };
+        Ok(Employee{id: self.id, name_parts, name: self.name, salary: salary.amount, personnel_number: self.personnel_number})
+    }
+}
+
+
+/// Модель записи о сотруднике
+///
+/// Проверенная запись о сотруднике
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct Employee{
+    pub id: i32,
+    pub name: String,
+    pub name_parts: PersonName,
+    pub salary: Money,
+    pub personnel_number: Option<String>,
+}
+
+
+/// Модель Непроверенного поискового запроса
+///
+/// Строка поиска и количество кандидатов, приходящие с эндпоинта и подлежащие проверке
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UncheckedEmployeeSearch{
+    #[param(min_length = 1, max_length = 255)]
+    q: String,
+    #[param(minimum = 1, maximum = 50, default = 10)]
+    #[serde(default)]
+    limit: Option<i64>,
+}
+
+impl UncheckedEmployeeSearch{
+    /// Sanity-check для поискового запроса
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Проверка - строка поиска проходит ту же проверку, что и имя, и приводится к нормализованной форме,
+    /// кандидатов от 1 до 50, по умолчанию 10
+    pub fn check(self) -> Result<EmployeeSearch, AppError> {
+        check_name(&self.q).map_err(|_| AppError::validation("q", "search query must be non-blank and at most 255 characters long"))?;
+        let limit = self.limit.unwrap_or(10);
+        if !(1..=50).contains(&limit) {
+            Err(AppError::validation("limit", "limit must be between 1 and 50"))?;
+        }
+        Ok(EmployeeSearch{query: normalize_name(&self.q), limit})
+    }
+}
+
+
+/// Модель поискового запроса
+///
+/// Нормализованная строка поиска и наибольшее количество кандидатов
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct EmployeeSearch{
+    pub query: String,
+    pub limit: i64,
+}
+
+
+/// Модель Непроверенного кандидата поиска
+///
+/// Запись о сотруднике и степень сходства имени со строкой поиска в том виде, в котором их возвращает база
+#[derive(Debug, FromRow, Clone)]
+pub struct UncheckedEmployeeMatch{
+    #[sqlx(flatten)]
+    employee: UncheckedEmployee,
+    score: f32,
+}
+
+impl UncheckedEmployeeMatch{
+    /// Sanity-check для кандидата поиска
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    pub fn check(self) -> Result<EmployeeMatch, AppError> {
+        Ok(EmployeeMatch{employee: self.employee.check()?, score: self.score})
+    }
+}
+
+
+/// Модель кандидата поиска
+///
+/// Запись о сотруднике и степень сходства имени от 0 до 1, где 1 - совпадение с точностью до регистра и пробелов
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct EmployeeMatch{
+    pub employee: Employee,
+    pub score: f32,
+}
+
+
+/// Модель Непроверенного изменения записи о сотруднике
+///
+/// Новые значения полей, приходящие с эндпоинта и подлежащие проверке.
+/// Незаданные поля не изменяются
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Default, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UncheckedEmployeeUpdate{
+    #[param(max_length = 255)]
+    #[schema(max_length = 255)]
+    #[serde(default)]
+    name: Option<String>,
+    #[param(min_length = 1, max_length = 64)]
+    #[schema(min_length = 1, max_length = 64)]
+    #[serde(default)]
+    personnel_number: Option<String>,
+}
+
+impl UncheckedEmployeeUpdate{
+    /// Sanity-check для изменения записи о сотруднике по правилам по умолчанию
+    pub fn check(self) -> Result<EmployeeUpdate, AppError> {
+        self.check_with(&NamePolicy::default())
+    }
+
+    /// Sanity-check для изменения записи о сотруднике
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Проверка - задано хотя бы одно поле, поля проходят те же проверки, что и при добавлении
+    pub fn check_with(self, policy: &NamePolicy) -> Result<EmployeeUpdate, AppError> {
+        if self.name.is_none() && self.personnel_number.is_none() {
+            Err(AppError::validation("query", "at least one of name, personnel_number must be given"))?;
+        }
+        let mut violations = Violations::default();
+        let name = self.name.and_then(|name| violations.check(policy.check("name", &name)));
+        violations.check(check_personnel_number(&self.personnel_number));
+        if !violations.is_empty() {
+            Err(violations.into_error())?;
+        }
+        let name_parts = name.as_deref().map(PersonName::parse_legacy);
+        Ok(EmployeeUpdate{name, name_parts, personnel_number: self.personnel_number})
+    }
+}
+
+
+/// Модель изменения записи о сотруднике
+///
+/// Проверенные новые значения полей
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct EmployeeUpdate{
+    pub name: Option<String>,
+    /// Части нового имени, разобранные из него
+    pub name_parts: Option<PersonName>,
+    pub personnel_number: Option<String>,
+}
+
+
+/// Модель результата увольнения
+///
+/// Запись об уволенном сотруднике и дата увольнения
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct EmployeeTermination{
+    pub employee: Employee,
+    pub terminated_at: DateTime<Utc>,
+}
+
+
+/// Модель результата изменения зарплаты
+///
+/// Запись о сотруднике после изменения, предыдущее и новое значение зарплаты
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct SalaryChange{
+    pub employee: Employee,
+    pub old_salary: Money,
+    pub new_salary: Money,
+}
+
+
+/// Модель Непроверенной записи журнала изменений зарплаты
+///
+/// Запись журнала в том виде, в котором она хранится в базе, подлежащая проверке
+#[derive(Debug, serde::Serialize, serde::Deserialize, FromRow, Clone)]
+pub struct UncheckedSalaryHistoryEntry{
+    id: i64,
+    employee_id: i32,
+    old_salary_minor: i64,
+    new_salary_minor: i64,
+    currency: String,
+    operation: String,
+    percentage_hundredths: Option<i32>,
+    rounding: Option<String>,
+    changed_at: DateTime<Utc>,
+    actor: String,
+}
+
+impl UncheckedSalaryHistoryEntry{
+    /// Sanity-check для записи журнала
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Проверка - валюта, вид изменения и способ округления записи должны поддерживаться
+    pub fn check(self) -> Result<SalaryHistoryEntry, AppError> {
+        let currency: Currency = self.currency.parse()?;
+        Ok(SalaryHistoryEntry{
+            id: self.id,
+            employee_id: self.employee_id,
+            old_salary: Money::new(self.old_salary_minor, currency),
+            new_salary: Money::new(self.new_salary_minor, currency),
+            operation: self.operation.parse()?,
+            percentage: self.percentage_hundredths.map(Percentage::new),
+            rounding: match self.rounding {
+                Some(rounding) => Some(rounding.parse()?),
+                None => None,
+            },
+            changed_at: self.changed_at,
+            actor: self.actor,
+        })
+    }
+}
+
+
+/// Модель записи журнала изменений зарплаты
+///
+/// Неизменяемая запись о том, кто, когда и насколько изменил зарплату сотрудника
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct SalaryHistoryEntry{
+    pub id: i64,
+    pub employee_id: i32,
+    pub old_salary: Money,
+    pub new_salary: Money,
+    pub operation: SalaryOperation,
+    pub percentage: Option<Percentage>,
+    pub rounding: Option<RoundingMode>,
+    pub changed_at: DateTime<Utc>,
+    pub actor: String,
+}
+
+
+/// Наибольший номер страницы
+///
+/// Ограничивает смещение первой записи, чтобы оно помещалось в BIGINT при любом размере страницы
+pub const MAX_PAGE: i64 = 1_000_000;
+
+
+/// Модель Непроверенных параметров страницы
+///
+/// Номер страницы и ее размер, приходящие с эндпоинта и подлежащие проверке
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Default, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UncheckedPage{
+    #[param(minimum = 1, maximum = 1000000, default = 1)]
+    #[serde(default)]
+    page: Option<i64>,
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    #[serde(default)]
+    per_page: Option<i64>,
+}
+
+impl UncheckedPage{
+    /// Sanity-check для параметров страницы
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Проверка - страницы нумеруются с единицы до MAX_PAGE, размер страницы от 1 до 100
+    /// По умолчанию возвращается первая страница из 20 записей
+    pub fn check(self) -> Result<Page, AppError> {
+        let page = self.page.unwrap_or(1);
+        let per_page = self.per_page.unwrap_or(20);
+        if page < 1 {
+            Err(AppError::validation("page", "page number must be greater than or equal to one"))?;
+        }
+        if page > MAX_PAGE {
+            Err(AppError::validation("page", &format!("page number must be at most {MAX_PAGE}")))?;
+        }
+        if !(1..=100).contains(&per_page) {
+            Err(AppError::validation("per_page", "page size must be between 1 and 100"))?;
+        }
+        Ok(Page{page, per_page})
+    }
+}
+
+
+/// Модель параметров страницы
+///
+/// Проверенные номер страницы и ее размер
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq)]
+pub struct Page{
+    pub page: i64,
+    pub per_page: i64,
+}
+
+impl Page{
+    pub fn offset(&self) -> i64 {
+        (self.page - 1) * self.per_page
+    }
+}
+
+
+/// Модель страницы журнала изменений зарплаты
+///
+/// Записи журнала от новых к старым и общее количество записей
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct SalaryHistoryPage{
+    pub entries: Vec<SalaryHistoryEntry>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+
+/// Наибольшее количество сотрудников, перечисляемых в одном пакете
+pub const MAX_BATCH_ITEMS: usize = 1000;
+
+
+/// Режим применения пакета изменений
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode{
+    /// Все изменения применяются вместе, первая же ошибка отменяет пакет
+    #[default]
+    Atomic,
+    /// Изменения применяются по отдельности, ошибки перечисляются в отчете
+    BestEffort,
+}
+
+impl FromStr for BatchMode{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "atomic" => Ok(BatchMode::Atomic),
+            "best_effort" => Ok(BatchMode::BestEffort),
+            _ => Err(AppError::validation("mode", "batch mode must be either \"atomic\" or \"best_effort\"")),
+        }
+    }
+}
+
+
+/// Модель Непроверенного элемента пакета
+///
+/// Сотрудник и процент повышения его зарплаты
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, utoipa::ToSchema)]
+pub struct UncheckedSalaryBatchItem{
+    #[schema(minimum = 1)]
+    id: i32,
+    #[schema(pattern = r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$", example = "5")]
+    #[serde(deserialize_with = "deserialize_decimal")]
+    percentage: String,
+}
+
+
+/// Модель Непроверенного фильтра сотрудников
+///
+/// Все условия необязательны, пустой фильтр выбирает всех сотрудников.
+/// Границы зарплаты включительные и требуют указать валюту,
+/// начало имени и фамилии сравнивается без учета регистра и лишних пробелов
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Default, utoipa::ToSchema)]
+pub struct UncheckedEmployeeFilter{
+    #[schema(max_length = 255)]
+    #[serde(default)]
+    name_prefix: Option<String>,
+    #[schema(max_length = 255)]
+    #[serde(default)]
+    surname_prefix: Option<String>,
+    #[schema(value_type = Option<Currency>)]
+    #[serde(default)]
+    currency: Option<String>,
+    #[schema(pattern = r"^\d+(\.\d{1,2})?$")]
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    min_salary: Option<String>,
+    #[schema(pattern = r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$")]
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    max_salary: Option<String>,
+}
+
+impl UncheckedEmployeeFilter{
+    /// Sanity-check для фильтра сотрудников
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    pub fn check(self) -> Result<EmployeeFilter, AppError> {
+        let currency = match self.currency {
+            Some(code) => Some(code.parse::<Currency>()?),
+            None => None,
+        };
+        let bound = |value: Option<String>, field: &'static str| -> Result<Option<Money>, AppError> {
+            match value {
+                Some(value) => {
+                    let currency = currency
+                        .ok_or(AppError::validation("currency", "salary bounds require the filter currency"))?;
+                    let bound = Money::parse(&value, currency, field)?;
+                    if bound.amount_minor < 0 || bound.amount_minor > MAX_AMOUNT_MINOR {
+                        Err(AppError::validation(field, "salary bound cannot be negative or too large"))?;
+                    }
+                    Ok(Some(bound))
+                },
+                None => Ok(None),
+            }
+        };
+        let min_salary = bound(self.min_salary, "min_salary")?;
+        let max_salary = bound(self.max_salary, "max_salary")?;
+        // Нижняя граница 0 выбирает всех, а верхняя граница 0 - никого, ведь зарплаты положительны
+        if let Some(max) = max_salary {
+            check_salary(&max).map_err(|_| AppError::validation("max_salary", "maximal salary must be greater than zero"))?;
+        }
+        if let (Some(min), Some(max)) = (min_salary, max_salary) {
+            if min.amount_minor > max.amount_minor {
+                Err(AppError::validation("min_salary", "minimal salary cannot be greater than maximal salary"))?;
+            }
+        }
+        let prefix = |value: Option<String>, field: &'static str| -> Result<Option<String>, AppError> {
+            match value {
+                Some(prefix) => {
+                    check_name(&prefix).map_err(|_| AppError::validation(field, "prefix must be non-blank and at most 255 characters long"))?;
+                    Ok(Some(normalize_name(&prefix)))
+                },
+                None => Ok(None),
+            }
+        };
+        let name_prefix = prefix(self.name_prefix, "name_prefix")?;
+        let surname_prefix = prefix(self.surname_prefix, "surname_prefix")?;
+        Ok(EmployeeFilter{name_prefix, surname_prefix, currency, min_salary, max_salary, after_id: None})
+    }
+}
+
+
+/// Модель фильтра сотрудников
+///
+/// Проверенные условия выбора сотрудников. Начало имени хранится в нормализованной форме
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, Default)]
+pub struct EmployeeFilter{
+    pub name_prefix: Option<String>,
+    pub surname_prefix: Option<String>,
+    pub currency: Option<Currency>,
+    pub min_salary: Option<Money>,
+    pub max_salary: Option<Money>,
+    /// Только сотрудники с идентификатором больше заданного, для обхода списка по ключу
+    ///
+    /// Задается только изнутри приложения, в запросах API такого условия нет
+    pub after_id: Option<i32>,
+}
+
+
+/// Модель Непроверенного пакета повышений зарплаты
+///
+/// Пакет задается либо списком сотрудников с процентами в items,
+/// либо одним процентом percentage для всех сотрудников, подходящих под filter
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, utoipa::ToSchema)]
+pub struct UncheckedSalaryBatch{
+    #[schema(value_type = Option<BatchMode>)]
+    #[serde(default)]
+    mode: Option<String>,
+    #[schema(value_type = Option<RoundingMode>)]
+    #[serde(default)]
+    rounding: Option<String>,
+    /// От 1 до 1000 сотрудников без повторов
+    #[schema(max_items = 1000)]
+    #[serde(default)]
+    items: Option<Vec<UncheckedSalaryBatchItem>>,
+    #[schema(pattern = r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$")]
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    percentage: Option<String>,
+    #[serde(default)]
+    filter: Option<UncheckedEmployeeFilter>,
+}
+
+impl UncheckedSalaryBatch{
+    /// Sanity-check для пакета повышений
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Проверка - задан ровно один способ выбора сотрудников, список не пуст, не длиннее
+    /// MAX_BATCH_ITEMS и не повторяет сотрудников, проценты положительны
+    pub fn check(self) -> Result<SalaryBatch, AppError> {
+        let mode = match self.mode {
+            Some(mode) => mode.parse()?,
+            None => BatchMode::default(),
+        };
+        let rounding = check_rounding(&self.rounding)?;
+        let target = match (self.items, self.percentage) {
+            (Some(items), None) => {
+                if self.filter.is_some() {
+                    Err(AppError::validation("filter", "filter can only be combined with a single percentage"))?;
+                }
+                if items.is_empty() || items.len() > MAX_BATCH_ITEMS {
+                    Err(AppError::validation("items", &format!("batch must contain from 1 to {MAX_BATCH_ITEMS} employees")))?;
+                }
+                let mut checked: Vec<SalaryBatchItem> = Vec::with_capacity(items.len());
+                let mut seen: HashSet<i32> = HashSet::with_capacity(items.len());
+                for item in items {
+                    let id = UncheckedEmployeeId{id: item.id}.check()?;
+                    if !seen.insert(id.id) {
+                        Err(AppError::validation("items", &format!("employee {} is listed more than once", id.id)))?;
+                    }
+                    let percentage = check_percentage(&item.percentage)?;
+                    checked.push(SalaryBatchItem{id, percentage: SalaryPercentage{percentage, rounding}});
+                }
+                SalaryBatchTarget::Items(checked)
+            },
+            (None, Some(percentage)) => {
+                let percentage = check_percentage(&percentage)?;
+                let filter = self.filter.unwrap_or_default().check()?;
+                SalaryBatchTarget::Filter{percentage: SalaryPercentage{percentage, rounding}, filter}
+            },
+            _ => Err(AppError::validation("items", "batch must contain either items or a percentage with an optional filter"))?,
+        };
+        Ok(SalaryBatch{mode, target})
+    }
+}
+
+
+/// Модель элемента пакета
+///
+/// Проверенные сотрудник и процент повышения
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct SalaryBatchItem{
+    pub id: EmployeeId,
+    pub percentage: SalaryPercentage,
+}
+
+
+/// Способ выбора сотрудников для пакета
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub enum SalaryBatchTarget{
+    /// Перечисленные сотрудники, у каждого свой процент
+    Items(Vec<SalaryBatchItem>),
+    /// Все сотрудники, подходящие под фильтр, с одним процентом
+    Filter{
+        percentage: SalaryPercentage,
+        filter: EmployeeFilter,
+    },
+}
+
+
+/// Модель пакета повышений зарплаты
+///
+/// Проверенный пакет
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct SalaryBatch{
+    pub mode: BatchMode,
+    pub target: SalaryBatchTarget,
+}
+
+
+/// Модель результата для одного сотрудника из пакета
+///
+/// Заполняется либо изменение, либо ошибка
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct SalaryBatchItemReport{
+    pub id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<SalaryChange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorDetails>,
+}
+
+
+/// Модель отчета о применении пакета
+///
+/// Результаты перечислены в порядке обработки сотрудников
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct SalaryBatchReport{
+    pub mode: BatchMode,
+    pub applied: usize,
+    pub failed: usize,
+    pub items: Vec<SalaryBatchItemReport>,
+}
+
+
+/// Поле сортировки списка сотрудников
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EmployeeSortField{
+    #[default]
+    Id,
+    /// По нормализованному имени
+    Name,
+    /// По нормализованной фамилии, затем по имени
+    Surname,
+    /// По зарплате внутри каждой валюты, валюты по коду
+    Salary,
+}
+
+
+/// Порядок сортировки списка сотрудников
+///
+/// Записывается именем поля, перед которым минус означает сортировку по убыванию: -salary.
+/// При равенстве значений сотрудники упорядочиваются по идентификатору
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EmployeeSort{
+    pub field: EmployeeSortField,
+    pub descending: bool,
+}
+
+impl FromStr for EmployeeSort{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (descending, field) = match s.strip_prefix('-') {
+            Some(field) => (true, field),
+            None => (false, s),
+        };
+        let field = match field {
+            "id" => EmployeeSortField::Id,
+            "name" => EmployeeSortField::Name,
+            "surname" => EmployeeSortField::Surname,
+            "salary" => EmployeeSortField::Salary,
+            _ => Err(AppError::validation("sort", "sort must be one of id, name, surname, salary, optionally prefixed with a minus"))?,
+        };
+        Ok(EmployeeSort{field, descending})
+    }
+}
+
+
+/// Модель Непроверенного запроса списка сотрудников
+///
+/// Параметры страницы, сортировки и фильтра, приходящие с эндпоинта и подлежащие проверке
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Default, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UncheckedEmployeeListQuery{
+    #[param(minimum = 1, maximum = 1000000, default = 1)]
+    #[serde(default)]
+    page: Option<i64>,
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    #[serde(default)]
+    per_page: Option<i64>,
+    #[param(pattern = r"^-?(id|name|surname|salary)$", example = "-salary")]
+    #[serde(default)]
+    sort: Option<String>,
+    #[param(max_length = 255)]
+    #[serde(default)]
+    name_prefix: Option<String>,
+    #[param(max_length = 255)]
+    #[serde(default)]
+    surname_prefix: Option<String>,
+    #[param(value_type = Option<Currency>)]
+    #[serde(default)]
+    currency: Option<String>,
+    #[param(pattern = r"^\d+(\.\d{1,2})?$")]
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    min_salary: Option<String>,
+    #[param(pattern = r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$")]
+    #[serde(default, deserialize_with = "deserialize_optional_decimal")]
+    max_salary: Option<String>,
+}
+
+impl UncheckedEmployeeListQuery{
+    /// Sanity-check для запроса списка сотрудников
+    ///
+    /// Преобразует непроверенные данные в проверенные, поглощая объект
+    /// Страница и фильтр проверяются так же, как в журнале изменений и пакетных повышениях,
+    /// по умолчанию сотрудники упорядочены по идентификатору
+    pub fn check(self) -> Result<EmployeeListQuery, AppError> {
+        let page = UncheckedPage{page: self.page, per_page: self.per_page}.check()?;
+        let sort = match self.sort {
+            Some(sort) => sort.parse()?,
+            None => EmployeeSort::default(),
+        };
+        let filter = UncheckedEmployeeFilter{
+            name_prefix: self.name_prefix,
+            surname_prefix: self.surname_prefix,
+            currency: self.currency,
+            min_salary: self.min_salary,
+            max_salary: self.max_salary,
+        }.check()?;
+        Ok(EmployeeListQuery{filter, sort, page})
+    }
+}
+
+
+/// Модель запроса списка сотрудников
+///
+/// Проверенные параметры страницы, сортировки и фильтра
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct EmployeeListQuery{
+    pub filter: EmployeeFilter,
+    pub sort: EmployeeSort,
+    pub page: Page,
+}
+
+
+/// Модель страницы списка сотрудников
+///
+/// Работающие сотрудники и общее количество сотрудников, подходящих под фильтр
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct EmployeePage{
+    pub employees: Vec<Employee>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+
+// Модели состояния приложения
+
+
+/// Загруженность пула соединений с базой данных
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct PoolStatus{
+    /// Открытые соединения
+    pub size: u32,
+    /// Открытые соединения, не занятые запросами
+    pub idle: u32,
+    /// Соединения, занятые запросами
+    pub in_use: u32,
+    pub max_connections: u32,
+    /// Доля занятых соединений от наибольшего размера пула, от 0 до 1
+    pub saturation: f64,
+}
+
+impl PoolStatus{
+    pub fn new(size: u32, idle: u32, max_connections: u32) -> PoolStatus {
+        let in_use = size.saturating_sub(idle);
+        let saturation = match max_connections {
+            0 => 0.0,
+            max => in_use as f64 / max as f64,
+        };
+        PoolStatus{size, idle, in_use, max_connections, saturation}
+    }
+}
+
+
+/// Доступность базы данных
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct DatabaseHealth{
+    pub up: bool,
+    /// Время ответа на проверку в миллисекундах
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+
+/// Модель ответа проверки готовности
+///
+/// Приложение готово принимать запросы, если база отвечает, схема не отстает от миграций
+/// и не начата остановка сервера
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct ReadinessReport{
+    pub ready: bool,
+    pub shutting_down: bool,
+    pub database: DatabaseHealth,
+    /// Состояние миграций, если его удалось получить
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migrations: Option<MigrationStatus>,
+    pub pool: PoolStatus,
+}
+
+
+/// Модель ответа проверки жизнеспособности
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct LivenessReport{
+    pub alive: bool,
+}
+
+
+// Модели ответов
+
+
+/// Модель успешного ответа
+///
+/// Конверт, в который заворачиваются все успешные ответы эндпоинтов
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, utoipa::ToSchema)]
+pub struct ApiResponse<T>{
+    pub data: T,
+}
+
+impl<T> ApiResponse<T>{
+    pub fn new(data: T) -> ApiResponse<T> {
+        ApiResponse{data}
+    }
+}
+
+
+/// Модель описания ошибки
+///
+/// Машиночитаемый код ошибки, сообщение и поле запроса, вызвавшее ошибку
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct ErrorDetails{
+    pub code: String,
+    pub message: String,
+    pub field: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<Vec<i32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Vec<FieldErrorDetails>>,
+}
+
+
+/// Модель ошибки проверки поля
+///
+/// Для ошибок проверки ответ перечисляет все нарушения по полям
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct FieldErrorDetails{
+    pub field: String,
+    pub message: String,
+}
+
+
+/// Модель ответа с ошибкой
+///
+/// Конверт, в который заворачиваются все ошибки эндпоинтов
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, utoipa::ToSchema)]
+pub struct ErrorResponse{
+    pub error: ErrorDetails,
 }
 
 #[cfg(test)]
 mod tests{
-    use super::{UncheckedEmployeeName, UncheckedEmployeeData, UncheckedEmployeeSalary, SalaryMultiplier, UncheckedSalaryMultiplier};
+    use super::{normalize_name, PersonName, UncheckedEmployeeName, UncheckedEmployeeData, UncheckedEmployeeSalary, SalaryPercentage, UncheckedSalaryMultiplier, UncheckedSalaryPercentage, UncheckedSalaryAdjustment, SalaryAdjustment, UncheckedEmployeeUpdate, EmployeeUpdate, UncheckedEmployeeListQuery, UncheckedEmployeeSearch, EmployeeSearch, EmployeeSort, EmployeeSortField, EmployeeFilter, UncheckedEmployeeId, UncheckedPage, MAX_PAGE, UncheckedEmployee};
+    use crate::errors::AppError;
+    use crate::names::NamePolicy;
+    use crate::money::{Currency, Money, Percentage, RoundingMode, MAX_AMOUNT_MINOR};
 
     #[test]
     fn employee_name_test(){
@@ -216,102 +1517,376 @@ mod tests{
         if let Ok(val) = (UncheckedEmployeeName{name: "       ".to_owned()}).check(){
             panic!("Bad name somehow passed the check: {}", val.name)
         }
+        if let Ok(val) = (UncheckedEmployeeName{name: "я".repeat(256)}).check(){
+            panic!("Bad name somehow passed the check: {}", val.name)
+        }
+    }
+
+    #[test]
+    fn employee_name_cleanup_test(){
+        let employee = UncheckedEmployeeName{name: "  Владимир   Масленников ".to_owned()}.check().unwrap();
+        assert_eq!("Владимир Масленников", employee.name);
+        let employee = UncheckedEmployeeData{name: " Андреи\u{0306}  Белый ".to_owned(), salary: "5000".to_owned(), currency: None, personnel_number: None, ..Default::default()}.check().unwrap();
+        assert_eq!("Андрей Белый", employee.name);
+        assert_eq!(normalize_name("Андрей Белый"), normalize_name("АНДРЕИ\u{0306} белый"));
     }
 
     #[test]
     fn employee_data_test(){
-        let employee = UncheckedEmployeeData{name:"Владимир Евгеньевич Масленников".to_owned(), salary: 5000};
+        let employee = UncheckedEmployeeData{name:"Владимир Евгеньевич Масленников".to_owned(), salary: "5000".to_owned(), currency: None, personnel_number: None, ..Default::default()};
         let employee = employee.check().unwrap();
         assert_eq!("Владимир Евгеньевич Масленников".to_owned(), employee.name);
-        assert_eq!(5000, employee.salary);
+        assert_eq!(Money::new(500000, Currency::Rub), employee.salary);
     }
 
     #[test]
     fn employee_data_test_failing(){
-        if let Ok(data) = (UncheckedEmployeeData{name:"Владимир Евгеньевич Масленников".to_owned(), salary: 0}).check() {
+        if let Ok(data) = (UncheckedEmployeeData{name:"Владимир Евгеньевич Масленников".to_owned(), salary: "0".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check() {
+            panic!("Bad data somehow passed the check: {:?}", data);
+        }
+        if let Ok(data) = (UncheckedEmployeeData{name:"Владимир Евгеньевич Масленников".to_owned(), salary: "-320".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check() {
+            panic!("Bad data somehow passed the check: {:?}", data);
+        }
+        if let Ok(data) = (UncheckedEmployeeData{name:"".to_owned(), salary: "5000".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check() {
             panic!("Bad data somehow passed the check: {:?}", data);
         }
-        if let Ok(data) = (UncheckedEmployeeData{name:"Владимир Евгеньевич Масленников".to_owned(), salary: -320}).check() {
+        if let Ok(data) = (UncheckedEmployeeData{name:"  ".to_owned(), salary: "5000".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check() {
             panic!("Bad data somehow passed the check: {:?}", data);
         }
-        if let Ok(data) = (UncheckedEmployeeData{name:"".to_owned(), salary: 5000}).check() {
+        if let Ok(data) = (UncheckedEmployeeData{name:"         ".to_owned(), salary: "5000".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check() {
             panic!("Bad data somehow passed the check: {:?}", data);
         }
-        if let Ok(data) = (UncheckedEmployeeData{name:"  ".to_owned(), salary: 5000}).check() {
+        if let Ok(data) = (UncheckedEmployeeData{name:"".to_owned(), salary: "0".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check() {
             panic!("Bad data somehow passed the check: {:?}", data);
         }
-        if let Ok(data) = (UncheckedEmployeeData{name:"         ".to_owned(), salary: 5000}).check() {
+        if let Ok(data) = (UncheckedEmployeeData{name:"".to_owned(), salary: "-250".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check() {
             panic!("Bad data somehow passed the check: {:?}", data);
         }
-        if let Ok(data) = (UncheckedEmployeeData{name:"".to_owned(), salary: 0}).check() {
+    }
+
+    #[test]
+    fn employee_data_money_test(){
+        let employee = UncheckedEmployeeData{name: "Test Employee".to_owned(), salary: "150000.75".to_owned(), currency: Some("kzt".to_owned()), personnel_number: None, ..Default::default()}.check().unwrap();
+        assert_eq!(Money::new(15000075, Currency::Kzt), employee.salary);
+        if let Ok(data) = (UncheckedEmployeeData{name: "Test Employee".to_owned(), salary: "5000".to_owned(), currency: Some("USD".to_owned()), personnel_number: None, ..Default::default()}).check() {
+            panic!("Bad data somehow passed the check: {:?}", data);
+        }
+        if let Ok(data) = (UncheckedEmployeeData{name: "Test Employee".to_owned(), salary: "50.001".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check() {
+            panic!("Bad data somehow passed the check: {:?}", data);
+        }
+        if let Ok(data) = (UncheckedEmployeeData{name: "Test Employee".to_owned(), salary: "99999999999999999".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check() {
+            panic!("Bad data somehow passed the check: {:?}", data);
+        }
+        if let Ok(data) = (UncheckedEmployeeData{name: "Test Employee".to_owned(), salary: "0.00".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check() {
+            panic!("Bad data somehow passed the check: {:?}", data);
+        }
+    }
+
+    #[test]
+    fn employee_record_check_test(){
+        let employee = UncheckedEmployee{id: 1, name: "Test Employee".to_owned(), salary_minor: 10050, currency: "BYN".to_owned(), personnel_number: None, ..Default::default()}.check().unwrap();
+        assert_eq!(Money::new(10050, Currency::Byn), employee.salary);
+        assert!(UncheckedEmployee{id: 1, name: "Test Employee".to_owned(), salary_minor: 10050, currency: "XXX".to_owned(), personnel_number: None, ..Default::default()}.check().is_err());
+        assert!(UncheckedEmployee{id: 1, name: "Test Employee".to_owned(), salary_minor: 0, currency: "RUB".to_owned(), personnel_number: None, ..Default::default()}.check().is_err());
+    }
+
+    #[test]
+    fn employee_personnel_number_test(){
+        let employee = UncheckedEmployeeData{name: "Test Employee".to_owned(), salary: "5000".to_owned(), currency: None, personnel_number: Some("A-0042".to_owned()), ..Default::default()}.check().unwrap();
+        assert_eq!(Some("A-0042".to_owned()), employee.personnel_number);
+        if let Ok(data) = (UncheckedEmployeeData{name: "Test Employee".to_owned(), salary: "5000".to_owned(), currency: None, personnel_number: Some("  ".to_owned()), ..Default::default()}).check() {
             panic!("Bad data somehow passed the check: {:?}", data);
         }
-        if let Ok(data) = (UncheckedEmployeeData{name:"".to_owned(), salary: -250}).check() {
+        if let Ok(data) = (UncheckedEmployeeData{name: "Test Employee".to_owned(), salary: "5000".to_owned(), currency: None, personnel_number: Some("7".repeat(65)), ..Default::default()}).check() {
             panic!("Bad data somehow passed the check: {:?}", data);
         }
     }
 
+    #[test]
+    fn normalize_name_test(){
+        assert_eq!("владимир масленников", normalize_name("  Владимир   МАСЛЕННИКОВ "));
+        assert_eq!("abdula ibn nurahmat", normalize_name("Abdula\tIbn  Nurahmat"));
+    }
+
     #[test]
     fn employee_salary_check_test(){
-        let salary = UncheckedEmployeeSalary{amount: 500}.check().unwrap();
-        assert_eq!(500, salary.amount);
+        let salary = UncheckedEmployeeSalary{amount_minor: 500, currency: "RUB".to_owned()}.check().unwrap();
+        assert_eq!(500, salary.amount.amount_minor);
     }
 
     #[test]
     fn employee_salary_increase_test(){
-        let mut salary = UncheckedEmployeeSalary{amount: 100}.check().unwrap();
-        let old_salary = salary.increase_by_percentage(&SalaryMultiplier{name: "Test Employee".to_owned(), percentage: 25}).unwrap();
-        assert_eq!(100, old_salary.amount);
-        assert_eq!(125, salary.amount);
+        let mut salary = UncheckedEmployeeSalary{amount_minor: 100, currency: "RUB".to_owned()}.check().unwrap();
+        let old_salary = salary.increase_by_percentage(&SalaryPercentage{percentage: Percentage::whole(25), rounding: RoundingMode::Ceil}).unwrap();
+        assert_eq!(100, old_salary.amount.amount_minor);
+        assert_eq!(125, salary.amount.amount_minor);
+
+        let mut salary = UncheckedEmployeeSalary{amount_minor: 1000, currency: "RUB".to_owned()}.check().unwrap();
+        let old_salary = salary.increase_by_percentage(&SalaryPercentage{percentage: Percentage::whole(25), rounding: RoundingMode::Ceil}).unwrap();
+        assert_eq!(1000, old_salary.amount.amount_minor);
+        assert_eq!(1250, salary.amount.amount_minor);
 
-        let mut salary = UncheckedEmployeeSalary{amount: 1000}.check().unwrap();
-        let old_salary = salary.increase_by_percentage(&SalaryMultiplier{name: "Test Employee".to_owned(), percentage: 25}).unwrap();
-        assert_eq!(1000, old_salary.amount);
-        assert_eq!(1250, salary.amount);
+        let mut salary = UncheckedEmployeeSalary{amount_minor: 1000, currency: "RUB".to_owned()}.check().unwrap();
+        let old_salary = salary.increase_by_percentage(&SalaryPercentage{percentage: Percentage::whole(50), rounding: RoundingMode::Ceil}).unwrap();
+        assert_eq!(1000, old_salary.amount.amount_minor);
+        assert_eq!(1500, salary.amount.amount_minor);
+
+        let mut salary = UncheckedEmployeeSalary{amount_minor: 1000, currency: "RUB".to_owned()}.check().unwrap();
+        let old_salary = salary.increase_by_percentage(&SalaryPercentage{percentage: Percentage::whole(100), rounding: RoundingMode::Ceil}).unwrap();
+        assert_eq!(1000, old_salary.amount.amount_minor);
+        assert_eq!(2000, salary.amount.amount_minor);
+
+        let mut salary = UncheckedEmployeeSalary{amount_minor: 1000, currency: "RUB".to_owned()}.check().unwrap();
+        let old_salary = salary.increase_by_percentage(&SalaryPercentage{percentage: Percentage::whole(200), rounding: RoundingMode::Ceil}).unwrap();
+        assert_eq!(1000, old_salary.amount.amount_minor);
+        assert_eq!(3000, salary.amount.amount_minor);
+    }
 
-        let mut salary = UncheckedEmployeeSalary{amount: 1000}.check().unwrap();
-        let old_salary = salary.increase_by_percentage(&SalaryMultiplier{name: "Test Employee".to_owned(), percentage: 50}).unwrap();
-        assert_eq!(1000, old_salary.amount);
-        assert_eq!(1500, salary.amount);
+    #[test]
+    fn employee_salary_fractional_increase_test(){
+        // 1000.01 * 3.5% = 35.00035
+        let mut salary = UncheckedEmployeeSalary{amount_minor: 100001, currency: "RUB".to_owned()}.check().unwrap();
+        salary.increase_by_percentage(&SalaryPercentage{percentage: Percentage::new(350), rounding: RoundingMode::Ceil}).unwrap();
+        assert_eq!(103502, salary.amount.amount_minor);
+        let mut salary = UncheckedEmployeeSalary{amount_minor: 100001, currency: "RUB".to_owned()}.check().unwrap();
+        salary.increase_by_percentage(&SalaryPercentage{percentage: Percentage::new(350), rounding: RoundingMode::HalfUp}).unwrap();
+        assert_eq!(103501, salary.amount.amount_minor);
 
-        let mut salary = UncheckedEmployeeSalary{amount: 1000}.check().unwrap();
-        let old_salary = salary.increase_by_percentage(&SalaryMultiplier{name: "Test Employee".to_owned(), percentage: 100}).unwrap();
-        assert_eq!(1000, old_salary.amount);
-        assert_eq!(2000, salary.amount);
+        // 0.50 * 1% = 0.005, ровно половина копейки
+        for (rounding, expected) in [(RoundingMode::HalfUp, 51), (RoundingMode::HalfEven, 50), (RoundingMode::Floor, 50), (RoundingMode::Ceil, 51)] {
+            let mut salary = UncheckedEmployeeSalary{amount_minor: 50, currency: "RUB".to_owned()}.check().unwrap();
+            salary.increase_by_percentage(&SalaryPercentage{percentage: Percentage::whole(1), rounding}).unwrap();
+            assert_eq!(expected, salary.amount.amount_minor, "{rounding}");
+        }
+    }
 
-        let mut salary = UncheckedEmployeeSalary{amount: 1000}.check().unwrap();
-        let old_salary = salary.increase_by_percentage(&SalaryMultiplier{name: "Test Employee".to_owned(), percentage: 200}).unwrap();
-        assert_eq!(1000, old_salary.amount);
-        assert_eq!(3000, salary.amount);
+    #[test]
+    fn employee_salary_increase_wide_arithmetic_test(){
+        // Произведение суммы на процент не помещается в i64, но результат допустим
+        let mut salary = UncheckedEmployeeSalary{amount_minor: MAX_AMOUNT_MINOR / 4, currency: "RUB".to_owned()}.check().unwrap();
+        salary.increase_by_percentage(&SalaryPercentage{percentage: Percentage::whole(100), rounding: RoundingMode::Floor}).unwrap();
+        assert_eq!(MAX_AMOUNT_MINOR / 4 * 2, salary.amount.amount_minor);
+    }
+
+    #[test]
+    fn salary_adjustment_check_test(){
+        let adjustment = |operation: &str, value: &str, currency: Option<&str>, rounding: Option<&str>| UncheckedSalaryAdjustment{
+            operation: operation.to_owned(),
+            value: value.to_owned(),
+            currency: currency.map(str::to_owned),
+            rounding: rounding.map(str::to_owned),
+        }.check();
+        assert_eq!(SalaryAdjustment::Percentage{percentage: Percentage::new(-250), rounding: RoundingMode::Floor}, adjustment("percentage", "-2.5", None, Some("floor")).unwrap());
+        assert_eq!(SalaryAdjustment::Delta{amount_minor: -150050, currency: Some(Currency::Byn)}, adjustment("delta", "-1500.50", Some("BYN"), None).unwrap());
+        assert_eq!(SalaryAdjustment::Set{amount_minor: 9000000, currency: None}, adjustment("set", "90000", None, None).unwrap());
+
+        assert!(matches!(adjustment("bonus", "1", None, None).unwrap_err(), AppError::Validation{field: "operation", ..}));
+        assert!(matches!(adjustment("percentage", "0", None, None).unwrap_err(), AppError::Validation{field: "value", ..}));
+        assert!(matches!(adjustment("percentage", "-100", None, None).unwrap_err(), AppError::Validation{field: "value", ..}));
+        assert!(matches!(adjustment("percentage", "5", Some("RUB"), None).unwrap_err(), AppError::Validation{field: "currency", ..}));
+        assert!(matches!(adjustment("delta", "0", None, None).unwrap_err(), AppError::Validation{field: "value", ..}));
+        assert!(matches!(adjustment("delta", "5", None, Some("ceil")).unwrap_err(), AppError::Validation{field: "rounding", ..}));
+        assert!(matches!(adjustment("set", "-5", None, None).unwrap_err(), AppError::Validation{field: "value", ..}));
+    }
+
+    #[test]
+    fn employee_salary_adjust_test(){
+        let salary = || UncheckedEmployeeSalary{amount_minor: 10000, currency: "RUB".to_owned()}.check().unwrap();
+
+        let mut cut = salary();
+        let old_salary = cut.adjust(&SalaryAdjustment::Percentage{percentage: Percentage::new(-3333), rounding: RoundingMode::HalfUp}).unwrap();
+        assert_eq!((10000, 6667), (old_salary.amount.amount_minor, cut.amount.amount_minor));
+
+        // Снижение на процент не опускает зарплату ниже копейки
+        let mut cut = UncheckedEmployeeSalary{amount_minor: 1, currency: "RUB".to_owned()}.check().unwrap();
+        cut.adjust(&SalaryAdjustment::Percentage{percentage: Percentage::new(-9999), rounding: RoundingMode::Floor}).unwrap();
+        assert_eq!(1, cut.amount.amount_minor);
+
+        let mut delta = salary();
+        delta.adjust(&SalaryAdjustment::Delta{amount_minor: -9999, currency: Some(Currency::Rub)}).unwrap();
+        assert_eq!(1, delta.amount.amount_minor);
+        assert!(salary().adjust(&SalaryAdjustment::Delta{amount_minor: -10000, currency: None}).is_err());
+        assert!(matches!(salary().adjust(&SalaryAdjustment::Delta{amount_minor: MAX_AMOUNT_MINOR, currency: None}).unwrap_err(), AppError::Overflow(_)));
+        assert!(matches!(salary().adjust(&SalaryAdjustment::Set{amount_minor: 500, currency: Some(Currency::Kzt)}).unwrap_err(), AppError::Validation{field: "currency", ..}));
+
+        let mut set = salary();
+        set.adjust(&SalaryAdjustment::Set{amount_minor: 500, currency: None}).unwrap();
+        assert_eq!(Money::new(500, Currency::Rub), set.amount);
     }
 
     #[test]
     fn employee_salary_increase_failing(){
-        let mut salary = UncheckedEmployeeSalary{amount: 2147483647}.check().unwrap();
-        if let Ok(val) = salary.increase_by_percentage(&SalaryMultiplier{name: "Test Employee".to_owned(), percentage: 100}){
-            panic!("Impossible increase in salary was performed on value: {}", val.amount);
+        let mut salary = UncheckedEmployeeSalary{amount_minor: MAX_AMOUNT_MINOR, currency: "RUB".to_owned()}.check().unwrap();
+        match salary.increase_by_percentage(&SalaryPercentage{percentage: Percentage::whole(100), rounding: RoundingMode::Ceil}){
+            Ok(val) => panic!("Impossible increase in salary was performed on value: {}", val.amount),
+            Err(e) => assert!(matches!(e, AppError::Overflow(_))),
+        }
+    }
+
+    #[test]
+    fn validation_error_field_test(){
+        let error = (UncheckedEmployeeData{name: "Test Employee".to_owned(), salary: "0".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check().unwrap_err();
+        assert_eq!(AppError::validation("salary", "employee's salary cannot be less than or equal to zero"), error);
+        let error = (UncheckedSalaryMultiplier{percentage: "10".to_owned(), rounding: None, name: " ".to_owned()}).check().unwrap_err();
+        assert!(matches!(error, AppError::Validation{field: "name", ..}));
+    }
+
+    #[test]
+    fn person_name_parse_legacy_test(){
+        let parts = PersonName::parse_legacy("Масленников Владимир Евгеньевич");
+        assert_eq!(("Масленников", Some("Владимир"), Some("Евгеньевич"), None), (parts.surname.as_str(), parts.given_name.as_deref(), parts.patronymic.as_deref(), parts.display_name.as_deref()));
+        // Порядок "Имя Отчество Фамилия" узнается по отчеству, исходная запись остается отображаемым именем
+        let parts = PersonName::parse_legacy("Владимир  Евгеньевич Масленников");
+        assert_eq!(("Масленников", Some("Владимир"), Some("Евгеньевич")), (parts.surname.as_str(), parts.given_name.as_deref(), parts.patronymic.as_deref()));
+        assert_eq!("Масленников Владимир Евгеньевич", parts.full_name());
+        assert_eq!("Владимир Евгеньевич Масленников", parts.display());
+        let parts = PersonName::parse_legacy("Петрова Анна");
+        assert_eq!(("Петрова", Some("Анна"), None), (parts.surname.as_str(), parts.given_name.as_deref(), parts.patronymic.as_deref()));
+        let parts = PersonName::parse_legacy("Мамедов Гусейн Али оглы");
+        assert_eq!((Some("Гусейн"), Some("Али оглы")), (parts.given_name.as_deref(), parts.patronymic.as_deref()));
+        assert_eq!(None, parts.display_name);
+        let parts = PersonName::parse_legacy("Цой");
+        assert_eq!(("Цой", None, None), (parts.surname.as_str(), parts.given_name.as_deref(), parts.patronymic.as_deref()));
+    }
+
+    #[test]
+    fn employee_data_structured_name_test(){
+        let data = UncheckedEmployeeData{
+            surname: Some(" Иванова ".to_owned()),
+            given_name: Some("Мария".to_owned()),
+            patronymic: Some("Петровна".to_owned()),
+            salary: "5000".to_owned(),
+            ..Default::default()
+        }.check().unwrap();
+        assert_eq!("Иванова Мария Петровна", data.name);
+        assert_eq!("Иванова", data.name_parts.surname);
+        let data = UncheckedEmployeeData{
+            surname: Some("Иванова".to_owned()),
+            given_name: Some("Мария".to_owned()),
+            display_name: Some("Мария Иванова".to_owned()),
+            salary: "5000".to_owned(),
+            ..Default::default()
+        }.check().unwrap();
+        assert_eq!("Мария Иванова", data.name);
+        let error = UncheckedEmployeeData{
+            name: "Иванова Мария".to_owned(),
+            patronymic: Some("Петровна1".to_owned()),
+            salary: "5000".to_owned(),
+            ..Default::default()
+        }.check().unwrap_err();
+        let fields: Vec<&str> = error.field_errors().into_iter().map(|error| error.field).collect();
+        assert_eq!(vec!["name", "surname", "given_name", "patronymic"], fields);
+    }
+
+    #[test]
+    fn validation_reports_all_fields_test(){
+        let error = (UncheckedEmployeeData{name: "Ivan\u{0007}".to_owned(), salary: "-5".to_owned(), currency: Some("USD".to_owned()), personnel_number: Some("".to_owned()), ..Default::default()}).check().unwrap_err();
+        let fields: Vec<&str> = error.field_errors().into_iter().map(|error| error.field).collect();
+        assert_eq!(vec!["name", "currency", "salary", "personnel_number"], fields);
+        let error = (UncheckedEmployeeData{name: "x".repeat(300), salary: "5000".to_owned(), currency: None, personnel_number: None, ..Default::default()}).check().unwrap_err();
+        assert!(matches!(error, AppError::Validation{field: "name", ..}));
+        let policy = NamePolicy{max_length: 300, ..NamePolicy::default()};
+        let error = (UncheckedEmployeeUpdate{name: Some("Иван 2".to_owned()), personnel_number: Some("7".repeat(65))}).check_with(&policy).unwrap_err();
+        assert_eq!(2, error.field_errors().len());
+    }
+
+    #[test]
+    fn salary_percentage_check_test(){
+        let percentage = UncheckedSalaryPercentage{percentage: "15".to_owned(), rounding: None}.check().unwrap();
+        assert_eq!(Percentage::whole(15), percentage.percentage);
+        assert!(UncheckedSalaryPercentage{percentage: "0".to_owned(), rounding: None}.check().is_err());
+        assert!(UncheckedSalaryPercentage{percentage: "-5".to_owned(), rounding: None}.check().is_err());
+        let percentage = UncheckedSalaryPercentage{percentage: "3.5".to_owned(), rounding: Some("half_even".to_owned())}.check().unwrap();
+        assert_eq!((Percentage::new(350), RoundingMode::HalfEven), (percentage.percentage, percentage.rounding));
+        assert!(UncheckedSalaryPercentage{percentage: "3.555".to_owned(), rounding: None}.check().is_err());
+        assert!(UncheckedSalaryPercentage{percentage: "3".to_owned(), rounding: Some("up".to_owned())}.check().is_err());
+    }
+
+    #[test]
+    fn employee_update_check_test(){
+        let update = UncheckedEmployeeUpdate{name: Some("Иван Петров".to_owned()), personnel_number: None}.check().unwrap();
+        assert_eq!(EmployeeUpdate{name: Some("Иван Петров".to_owned()), name_parts: Some(PersonName::parse_legacy("Иван Петров")), personnel_number: None}, update);
+        assert!(UncheckedEmployeeUpdate::default().check().is_err());
+        assert!(matches!(UncheckedEmployeeUpdate{name: Some("  ".to_owned()), personnel_number: None}.check().unwrap_err(), AppError::Validation{field: "name", ..}));
+        assert!(matches!(UncheckedEmployeeUpdate{name: None, personnel_number: Some("".to_owned())}.check().unwrap_err(), AppError::Validation{field: "personnel_number", ..}));
+    }
+
+    #[test]
+    fn employee_list_query_check_test(){
+        let query = UncheckedEmployeeListQuery::default().check().unwrap();
+        assert_eq!((EmployeeSort::default(), 20, EmployeeFilter::default()), (query.sort, query.page.per_page, query.filter));
+        let query = UncheckedEmployeeListQuery{
+            sort: Some("-salary".to_owned()),
+            name_prefix: Some(" Иван  П".to_owned()),
+            currency: Some("RUB".to_owned()),
+            min_salary: Some("100.5".to_owned()),
+            ..Default::default()
+        }.check().unwrap();
+        assert_eq!(EmployeeSort{field: EmployeeSortField::Salary, descending: true}, query.sort);
+        assert_eq!(Some("иван п".to_owned()), query.filter.name_prefix);
+        assert_eq!(Some(Money::new(10050, Currency::Rub)), query.filter.min_salary);
+
+        assert!(matches!(UncheckedEmployeeListQuery{sort: Some("age".to_owned()), ..Default::default()}.check().unwrap_err(), AppError::Validation{field: "sort", ..}));
+        assert!(matches!(UncheckedEmployeeListQuery{name_prefix: Some(" ".to_owned()), ..Default::default()}.check().unwrap_err(), AppError::Validation{field: "name_prefix", ..}));
+        assert!(matches!(UncheckedEmployeeListQuery{max_salary: Some("10".to_owned()), ..Default::default()}.check().unwrap_err(), AppError::Validation{field: "currency", ..}));
+        let query = UncheckedEmployeeListQuery{currency: Some("RUB".to_owned()), min_salary: Some("0".to_owned()), max_salary: Some("10".to_owned()), ..Default::default()}.check().unwrap();
+        assert_eq!((Some(Money::new(0, Currency::Rub)), Some(Money::new(1000, Currency::Rub))), (query.filter.min_salary, query.filter.max_salary));
+        for (min_salary, max_salary, field) in [("-5", "10", "min_salary"), ("-0.01", "10", "min_salary"), ("0", "-0.5", "max_salary"), ("0", "0.00", "max_salary")] {
+            let query = UncheckedEmployeeListQuery{currency: Some("RUB".to_owned()), min_salary: Some(min_salary.to_owned()), max_salary: Some(max_salary.to_owned()), ..Default::default()};
+            assert!(matches!(query.check().unwrap_err(), AppError::Validation{field: f, ..} if f == field), "{min_salary}..{max_salary}");
         }
+        assert!(matches!(UncheckedEmployeeListQuery{per_page: Some(500), ..Default::default()}.check().unwrap_err(), AppError::Validation{field: "per_page", ..}));
+    }
+
+    #[test]
+    fn employee_search_check_test(){
+        let search = UncheckedEmployeeSearch{q: "  Владимир   МАСЛЕННИКОВ ".to_owned(), limit: None}.check().unwrap();
+        assert_eq!(EmployeeSearch{query: "владимир масленников".to_owned(), limit: 10}, search);
+        assert!(matches!(UncheckedEmployeeSearch{q: "   ".to_owned(), limit: None}.check().unwrap_err(), AppError::Validation{field: "q", ..}));
+        assert!(matches!(UncheckedEmployeeSearch{q: "Иван".to_owned(), limit: Some(51)}.check().unwrap_err(), AppError::Validation{field: "limit", ..}));
+    }
+
+    #[test]
+    fn employee_id_check_test(){
+        assert_eq!(7, UncheckedEmployeeId{id: 7}.check().unwrap().id);
+        assert!(UncheckedEmployeeId{id: 0}.check().is_err());
+        assert!(UncheckedEmployeeId{id: -1}.check().is_err());
+    }
+
+    #[test]
+    fn page_check_test(){
+        let page = UncheckedPage::default().check().unwrap();
+        assert_eq!((1, 20, 0), (page.page, page.per_page, page.offset()));
+        let page = UncheckedPage{page: Some(3), per_page: Some(50)}.check().unwrap();
+        assert_eq!(100, page.offset());
+        assert!(UncheckedPage{page: Some(0), per_page: None}.check().is_err());
+        assert!(UncheckedPage{page: None, per_page: Some(0)}.check().is_err());
+        assert!(UncheckedPage{page: None, per_page: Some(101)}.check().is_err());
+        assert_eq!(99_999_900, UncheckedPage{page: Some(MAX_PAGE), per_page: Some(100)}.check().unwrap().offset());
+        let error = UncheckedPage{page: Some(100_000_000_000_000_000), per_page: Some(100)}.check().unwrap_err();
+        assert!(matches!(error, AppError::Validation{field: "page", ..}));
     }
 
     #[test]
     fn salary_multipier_check_test(){
-        let salary = UncheckedSalaryMultiplier{percentage: 100, name: "Test Employee".to_owned()}.check().unwrap();
+        let salary = UncheckedSalaryMultiplier{percentage: "100".to_owned(), rounding: None, name: "Test Employee".to_owned()}.check().unwrap();
         assert_eq!("Test Employee".to_owned(), salary.name);
-        assert_eq!(100, salary.percentage);
+        assert_eq!(Percentage::whole(100), salary.percentage);
+        assert_eq!(RoundingMode::Ceil, salary.rounding);
     }
 
     #[test]
     fn salary_multiplier_check_test_failing(){
-        if let Ok(val) = (UncheckedSalaryMultiplier{percentage: 0, name: "Test Employee".to_owned()}).check(){
+        if let Ok(val) = (UncheckedSalaryMultiplier{percentage: "0".to_owned(), rounding: None, name: "Test Employee".to_owned()}).check(){
             panic!("Bad data somehow passsed the check: {:?}", val);
         }
-        if let Ok(val) = (UncheckedSalaryMultiplier{percentage: 20, name: "".to_owned()}).check(){
+        if let Ok(val) = (UncheckedSalaryMultiplier{percentage: "20".to_owned(), rounding: None, name: "".to_owned()}).check(){
             panic!("Bad data somehow passsed the check: {:?}", val);
         }
-        if let Ok(val) = (UncheckedSalaryMultiplier{percentage: -32, name: "Test Employee".to_owned()}).check(){
+        if let Ok(val) = (UncheckedSalaryMultiplier{percentage: "-32".to_owned(), rounding: None, name: "Test Employee".to_owned()}).check(){
             panic!("Bad data somehow passsed the check: {:?}", val);
         }
-        if let Ok(val) = (UncheckedSalaryMultiplier{percentage: 0, name: "".to_owned()}).check(){
+        if let Ok(val) = (UncheckedSalaryMultiplier{percentage: "0".to_owned(), rounding: None, name: "".to_owned()}).check(){
             panic!("Bad data somehow passsed the check: {:?}", val);
         }
     }
@@ -0,0 +1,530 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use serde::de::{self, Deserializer, Visitor};
+use serde_json::json;
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{PartialSchema, ToSchema};
+use crate::errors::AppError;
+
+
+/// Наибольшая сумма в минорных единицах, которая помещается в колонку NUMERIC(18,2)
+pub const MAX_AMOUNT_MINOR: i64 = 999_999_999_999_999_999;
+
+
+/// Валюта по ISO 4217
+///
+/// Перечислены только валюты, в которых выплачивается зарплата
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default, utoipa::ToSchema)]
+pub enum Currency{
+    #[default]
+    #[serde(rename = "RUB")]
+    Rub,
+    #[serde(rename = "KZT")]
+    Kzt,
+    #[serde(rename = "BYN")]
+    Byn,
+}
+
+impl Currency{
+    /// Буквенный код валюты
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Rub => "RUB",
+            Currency::Kzt => "KZT",
+            Currency::Byn => "BYN",
+        }
+    }
+
+    /// Количество знаков минорной единицы после запятой
+    ///
+    /// У всех поддерживаемых валют по два знака, что совпадает с масштабом колонок в базе
+    pub fn minor_digits(&self) -> u32 {
+        match self {
+            Currency::Rub | Currency::Kzt | Currency::Byn => 2,
+        }
+    }
+
+    /// Количество минорных единиц в одной основной
+    pub fn minor_per_major(&self) -> i64 {
+        10_i64.pow(self.minor_digits())
+    }
+}
+
+impl FromStr for Currency{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_uppercase().as_str() {
+            "RUB" => Ok(Currency::Rub),
+            "KZT" => Ok(Currency::Kzt),
+            "BYN" => Ok(Currency::Byn),
+            _ => Err(AppError::validation("currency", "currency must be one of RUB, KZT, BYN")),
+        }
+    }
+}
+
+impl Display for Currency{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+
+/// Денежная сумма
+///
+/// Хранится целым числом минорных единиц (копеек, тиынов), поэтому вычисления не теряют точность.
+/// В JSON представляется строкой с десятичной точкой и кодом валюты:
+/// {"amount": "8000.50", "currency": "RUB"}
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "MoneyRepr", into = "MoneyRepr")]
+pub struct Money{
+    pub amount_minor: i64,
+    pub currency: Currency,
+}
+
+impl Money{
+    pub fn new(amount_minor: i64, currency: Currency) -> Money {
+        Money{amount_minor, currency}
+    }
+
+    /// Разобрать десятичную запись суммы
+    ///
+    /// Допускается знак минус и не больше знаков после точки, чем у минорной единицы валюты.
+    /// Ошибка разбора относится к полю запроса field
+    pub fn parse(amount: &str, currency: Currency, field: &'static str) -> Result<Money, AppError> {
+        let amount_minor = parse_scaled(amount, currency.minor_digits()).map_err(|e| match e {
+            DecimalError::Invalid => AppError::validation(field, &format!(
+                "amount must be a decimal number with at most {} digits after the point", currency.minor_digits()
+            )),
+            DecimalError::OutOfRange => AppError::validation(field, "amount is too large"),
+        })?;
+        Ok(Money{amount_minor, currency})
+    }
+
+    /// Увеличить сумму на процент
+    ///
+    /// Промежуточное произведение считается в i128 и не переполняется ни при каких входных данных,
+    /// поэтому ошибкой завершается только результат, не помещающийся в i64
+    pub fn add_percentage(&self, percentage: Percentage, rounding: RoundingMode) -> Result<Money, AppError> {
+        let scale = 100 * PERCENTAGE_SCALE as i128;
+        let numerator = self.amount_minor as i128 * (scale + percentage.hundredths as i128);
+        let amount_minor = rounding.divide(numerator, scale).try_into()
+            .map_err(|_| AppError::Overflow("employee's salary is too high to perform math operations".to_owned()))?;
+        Ok(Money{amount_minor, currency: self.currency})
+    }
+
+    /// Десятичная запись суммы без кода валюты
+    pub fn format_amount(&self) -> String {
+        format_scaled(self.amount_minor, self.currency.minor_digits())
+    }
+}
+
+impl Display for Money{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.format_amount(), self.currency)
+    }
+}
+
+
+/// Представление суммы в JSON
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+struct MoneyRepr{
+    #[serde(deserialize_with = "deserialize_decimal")]
+    #[schema(pattern = r"^-?\d+(\.\d{1,2})?$", example = "8000.50")]
+    amount: String,
+    currency: Currency,
+}
+
+impl TryFrom<MoneyRepr> for Money{
+    type Error = AppError;
+
+    fn try_from(value: MoneyRepr) -> Result<Self, Self::Error> {
+        Money::parse(&value.amount, value.currency, "amount")
+    }
+}
+
+impl From<Money> for MoneyRepr{
+    fn from(value: Money) -> Self {
+        MoneyRepr{amount: value.format_amount(), currency: value.currency}
+    }
+}
+
+impl PartialSchema for Money{
+    fn schema() -> RefOr<Schema> {
+        MoneyRepr::schema()
+    }
+}
+
+impl ToSchema for Money{
+    fn schemas(schemas: &mut Vec<(String, RefOr<Schema>)>) {
+        MoneyRepr::schemas(schemas);
+    }
+}
+
+
+/// Количество сотых долей в одном проценте
+const PERCENTAGE_SCALE: i32 = 100;
+
+/// Процент с точностью до сотых
+///
+/// Хранится целым числом сотых долей процента: 3.5% - это 350.
+/// В JSON представляется строкой с десятичной точкой: "3.50"
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct Percentage{
+    pub hundredths: i32,
+}
+
+impl Percentage{
+    pub fn new(hundredths: i32) -> Percentage {
+        Percentage{hundredths}
+    }
+
+    /// Целое число процентов
+    pub fn whole(percent: i32) -> Percentage {
+        Percentage{hundredths: percent * PERCENTAGE_SCALE}
+    }
+
+    /// Разобрать десятичную запись процента
+    ///
+    /// Допускается не больше двух знаков после точки
+    pub fn parse(percentage: &str, field: &'static str) -> Result<Percentage, AppError> {
+        let hundredths = parse_scaled(percentage, 2)
+            .ok()
+            .and_then(|hundredths| i32::try_from(hundredths).ok())
+            .ok_or_else(|| AppError::validation(field, &format!(
+                "percentage must be a decimal number with at most 2 digits after the point and at most {}",
+                Percentage::new(i32::MAX),
+            )))?;
+        Ok(Percentage{hundredths})
+    }
+}
+
+impl Display for Percentage{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_scaled(self.hundredths as i64, 2))
+    }
+}
+
+impl TryFrom<String> for Percentage{
+    type Error = AppError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Percentage::parse(&value, "percentage")
+    }
+}
+
+impl PartialSchema for Percentage{
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .pattern(Some(r"^-?\d+(\.\d{1,2})?$"))
+            .examples([json!("3.50")])
+            .into()
+    }
+}
+
+impl ToSchema for Percentage{}
+
+impl From<Percentage> for String{
+    fn from(value: Percentage) -> Self {
+        value.to_string()
+    }
+}
+
+
+/// Способ округления до минорной единицы
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode{
+    /// Половина округляется от нуля
+    HalfUp,
+    /// Половина округляется к четному (банковское округление)
+    HalfEven,
+    /// К минус бесконечности
+    Floor,
+    /// К плюс бесконечности. Используется по умолчанию, так как сотрудник не теряет копейки
+    #[default]
+    Ceil,
+}
+
+impl RoundingMode{
+    pub fn name(&self) -> &'static str {
+        match self {
+            RoundingMode::HalfUp => "half_up",
+            RoundingMode::HalfEven => "half_even",
+            RoundingMode::Floor => "floor",
+            RoundingMode::Ceil => "ceil",
+        }
+    }
+
+    /// Разделить с округлением
+    ///
+    /// Делитель должен быть положительным
+    pub fn divide(&self, numerator: i128, denominator: i128) -> i128 {
+        let quotient = numerator.div_euclid(denominator);
+        let remainder = numerator.rem_euclid(denominator);
+        if remainder == 0 {
+            return quotient;
+        }
+        let round_up = match self {
+            RoundingMode::Floor => false,
+            RoundingMode::Ceil => true,
+            RoundingMode::HalfUp | RoundingMode::HalfEven => match (2 * remainder).cmp(&denominator) {
+                std::cmp::Ordering::Less => false,
+                std::cmp::Ordering::Greater => true,
+                std::cmp::Ordering::Equal => match self {
+                    // Частное уже округлено к минус бесконечности, от нуля - значит вверх для положительных
+                    RoundingMode::HalfUp => quotient >= 0,
+                    _ => quotient % 2 != 0,
+                },
+            },
+        };
+        if round_up { quotient + 1 } else { quotient }
+    }
+}
+
+impl FromStr for RoundingMode{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "half_up" => Ok(RoundingMode::HalfUp),
+            "half_even" => Ok(RoundingMode::HalfEven),
+            "floor" => Ok(RoundingMode::Floor),
+            "ceil" => Ok(RoundingMode::Ceil),
+            _ => Err(AppError::validation("rounding", "rounding must be one of half_up, half_even, floor, ceil")),
+        }
+    }
+}
+
+impl Display for RoundingMode{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+
+/// Ошибка разбора десятичной записи
+enum DecimalError{
+    Invalid,
+    OutOfRange,
+}
+
+/// Разобрать десятичную запись в целое число с фиксированным количеством знаков после точки
+fn parse_scaled(value: &str, digits: u32) -> Result<i64, DecimalError> {
+    let value = value.trim();
+    let (negative, unsigned) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let (major, minor) = match unsigned.split_once('.') {
+        Some((major, minor)) => (major, minor),
+        None => (unsigned, ""),
+    };
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if major.is_empty() || !is_digits(major) || !is_digits(minor) || minor.len() > digits as usize {
+        Err(DecimalError::Invalid)?;
+    }
+    if unsigned.contains('.') && minor.is_empty() {
+        Err(DecimalError::Invalid)?;
+    }
+    let mut scaled: i64 = major.parse().map_err(|_| DecimalError::OutOfRange)?;
+    scaled = scaled.checked_mul(10_i64.pow(digits)).ok_or(DecimalError::OutOfRange)?;
+    if !minor.is_empty() {
+        let padded = format!("{:0<width$}", minor, width = digits as usize);
+        let fraction: i64 = padded.parse().map_err(|_| DecimalError::Invalid)?;
+        scaled = scaled.checked_add(fraction).ok_or(DecimalError::OutOfRange)?;
+    }
+    Ok(if negative { -scaled } else { scaled })
+}
+
+/// Десятичная запись целого числа с фиксированным количеством знаков после точки
+fn format_scaled(value: i64, digits: u32) -> String {
+    let scale = 10_u64.pow(digits);
+    let sign = if value < 0 { "-" } else { "" };
+    let absolute = value.unsigned_abs();
+    format!("{sign}{}.{:0width$}", absolute / scale, absolute % scale, width = digits as usize)
+}
+
+
+/// Десериализовать десятичное число, записанное строкой или целым числом
+///
+/// Дробные числа JSON не принимаются, так как теряют точность еще до разбора
+pub fn deserialize_decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    struct AmountVisitor;
+
+    impl<'de> Visitor<'de> for AmountVisitor{
+        type Value = String;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a decimal number as a string or an integer")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(v.to_owned())
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(v.to_string())
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(v.to_string())
+        }
+    }
+
+    deserializer.deserialize_any(AmountVisitor)
+}
+
+/// Десериализовать необязательное десятичное число, записанное строкой или целым числом
+pub fn deserialize_optional_decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(serde::Deserialize)]
+    struct Decimal(#[serde(deserialize_with = "deserialize_decimal")] String);
+
+    let decimal: Option<Decimal> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(decimal.map(|Decimal(value)| value))
+}
+
+
+#[cfg(test)]
+mod tests{
+    use proptest::prelude::*;
+    use super::{Currency, Money, Percentage, RoundingMode, MAX_AMOUNT_MINOR};
+
+    /// Эталонное округление для сравнения со RoundingMode::divide
+    ///
+    /// Выбирает ближайшее из двух соседних целых, сравнивая расстояния до точного значения
+    fn reference_divide(numerator: i128, denominator: i128, rounding: RoundingMode) -> i128 {
+        let mut lower = numerator / denominator;
+        if lower * denominator > numerator {
+            lower -= 1;
+        }
+        if lower * denominator == numerator {
+            return lower;
+        }
+        let upper = lower + 1;
+        let to_lower = numerator - lower * denominator;
+        let to_upper = upper * denominator - numerator;
+        match rounding {
+            RoundingMode::Floor => lower,
+            RoundingMode::Ceil => upper,
+            _ if to_lower < to_upper => lower,
+            _ if to_lower > to_upper => upper,
+            RoundingMode::HalfUp => if numerator > 0 { upper } else { lower },
+            RoundingMode::HalfEven => if lower % 2 == 0 { lower } else { upper },
+        }
+    }
+
+    fn rounding_mode() -> impl Strategy<Value = RoundingMode> {
+        prop_oneof![
+            Just(RoundingMode::HalfUp),
+            Just(RoundingMode::HalfEven),
+            Just(RoundingMode::Floor),
+            Just(RoundingMode::Ceil),
+        ]
+    }
+
+    proptest!{
+        #[test]
+        fn divide_matches_reference(numerator in any::<i64>(), denominator in 1..1_000_000_i64, rounding in rounding_mode()){
+            let (numerator, denominator) = (numerator as i128, denominator as i128);
+            prop_assert_eq!(reference_divide(numerator, denominator, rounding), rounding.divide(numerator, denominator));
+        }
+
+        #[test]
+        fn add_percentage_matches_reference(amount in 1..=MAX_AMOUNT_MINOR, hundredths in 1..=i32::MAX, rounding in rounding_mode()){
+            let money = Money::new(amount, Currency::Rub);
+            let numerator = amount as i128 * (10_000 + hundredths as i128);
+            let expected = reference_divide(numerator, 10_000, rounding);
+            match money.add_percentage(Percentage::new(hundredths), rounding) {
+                Ok(result) => prop_assert_eq!(expected, result.amount_minor as i128),
+                Err(_) => prop_assert!(expected > i64::MAX as i128),
+            }
+        }
+
+        #[test]
+        fn add_percentage_is_bounded_by_floor_and_ceil(amount in 1..=MAX_AMOUNT_MINOR / 1_000, hundredths in 1..=100_000, rounding in rounding_mode()){
+            let money = Money::new(amount, Currency::Rub);
+            let percentage = Percentage::new(hundredths);
+            let floor = money.add_percentage(percentage, RoundingMode::Floor).unwrap().amount_minor;
+            let ceil = money.add_percentage(percentage, RoundingMode::Ceil).unwrap().amount_minor;
+            let result = money.add_percentage(percentage, rounding).unwrap().amount_minor;
+            prop_assert!(amount <= floor && floor <= result && result <= ceil && ceil - floor <= 1);
+        }
+
+        #[test]
+        fn money_format_parse_roundtrip(amount in any::<i64>().prop_filter("not representable", |amount| *amount != i64::MIN)){
+            let money = Money::new(amount, Currency::Kzt);
+            prop_assert_eq!(money, Money::parse(&money.format_amount(), Currency::Kzt, "amount").unwrap());
+        }
+
+        #[test]
+        fn percentage_format_parse_roundtrip(hundredths in 0..=i32::MAX){
+            let percentage = Percentage::new(hundredths);
+            prop_assert_eq!(percentage, Percentage::parse(&percentage.to_string(), "percentage").unwrap());
+        }
+    }
+
+    #[test]
+    fn rounding_mode_parse_test(){
+        assert_eq!(RoundingMode::HalfEven, "half_even".parse().unwrap());
+        assert_eq!(RoundingMode::Ceil, RoundingMode::default());
+        assert!("up".parse::<RoundingMode>().is_err());
+    }
+
+    #[test]
+    fn percentage_parse_test(){
+        assert_eq!(Percentage::new(350), Percentage::parse("3.5", "percentage").unwrap());
+        assert_eq!(Percentage::whole(20), Percentage::parse("20", "percentage").unwrap());
+        assert_eq!("3.50", Percentage::new(350).to_string());
+        for percentage in ["", "3.555", "1e2", "99999999999"] {
+            assert!(Percentage::parse(percentage, "percentage").is_err(), "{percentage:?}");
+        }
+    }
+
+    #[test]
+    fn money_parse_test(){
+        assert_eq!(Money::new(800050, Currency::Rub), Money::parse("8000.50", Currency::Rub, "salary").unwrap());
+        assert_eq!(Money::new(800050, Currency::Kzt), Money::parse("8000.5", Currency::Kzt, "salary").unwrap());
+        assert_eq!(Money::new(800000, Currency::Byn), Money::parse(" 8000 ", Currency::Byn, "salary").unwrap());
+        assert_eq!(Money::new(-7, Currency::Rub), Money::parse("-0.07", Currency::Rub, "salary").unwrap());
+        assert_eq!(MAX_AMOUNT_MINOR, Money::parse("9999999999999999.99", Currency::Rub, "salary").unwrap().amount_minor);
+    }
+
+    #[test]
+    fn money_parse_failing_test(){
+        for amount in ["", "abc", "1.234", "1.", ".5", "1e5", "1,5", "--1", "+1", "99999999999999999999"] {
+            if let Ok(money) = Money::parse(amount, Currency::Rub, "salary") {
+                panic!("Bad amount {amount:?} somehow passed the check: {money}");
+            }
+        }
+    }
+
+    #[test]
+    fn money_format_test(){
+        assert_eq!("8000.50", Money::new(800050, Currency::Rub).format_amount());
+        assert_eq!("0.07", Money::new(7, Currency::Rub).format_amount());
+        assert_eq!("-1.00", Money::new(-100, Currency::Rub).format_amount());
+        assert_eq!("12.34 KZT", Money::new(1234, Currency::Kzt).to_string());
+    }
+
+    #[test]
+    fn money_serde_test(){
+        let money = Money::new(800050, Currency::Byn);
+        let json = serde_json::to_string(&money).unwrap();
+        assert_eq!(r#"{"amount":"8000.50","currency":"BYN"}"#, json);
+        assert_eq!(money, serde_json::from_str(&json).unwrap());
+        assert_eq!(Money::new(800000, Currency::Rub), serde_json::from_str(r#"{"amount":8000,"currency":"RUB"}"#).unwrap());
+        assert!(serde_json::from_str::<Money>(r#"{"amount":8000.5,"currency":"RUB"}"#).is_err());
+        assert!(serde_json::from_str::<Money>(r#"{"amount":"1","currency":"USD"}"#).is_err());
+    }
+
+    #[test]
+    fn currency_parse_test(){
+        assert_eq!(Currency::Kzt, "kzt".parse().unwrap());
+        assert_eq!(Currency::Rub, "RUB".parse().unwrap());
+        assert!("USD".parse::<Currency>().is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use crate::errors::AppError;
+
+
+/// Наибольшая длина имени в символах
+///
+/// Совпадает с размером колонки name VARCHAR(255)
+pub const MAX_NAME_LENGTH: usize = 255;
+
+
+/// Письменность, к которой относится буква
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Script{
+    Latin,
+    Cyrillic,
+    Greek,
+    Other,
+}
+
+impl Script{
+    fn of(c: char) -> Script {
+        match c {
+            'A'..='Z' | 'a'..='z' | '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}' => Script::Latin,
+            '\u{0400}'..='\u{052F}' | '\u{1C80}'..='\u{1C8F}' | '\u{2DE0}'..='\u{2DFF}' | '\u{A640}'..='\u{A69F}' => Script::Cyrillic,
+            '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Script::Greek,
+            _ => Script::Other,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Script::Latin => "Latin",
+            Script::Cyrillic => "Cyrillic",
+            Script::Greek => "Greek",
+            Script::Other => "other",
+        }
+    }
+}
+
+
+/// Невидимые символы форматирования
+///
+/// Мягкий перенос, пробелы нулевой ширины, управление направлением текста
+fn is_invisible(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{00AD}' | '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{2064}' | '\u{FEFF}')
+}
+
+/// Правила проверки имен сотрудников
+///
+/// Задаются разделом names конфигурации или переменными окружения NAME_MAX_LENGTH, NAME_MIXED_SCRIPTS, NAME_DIGITS и NAME_PUNCTUATION
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamePolicy{
+    /// Наибольшая длина имени в символах, не больше MAX_NAME_LENGTH
+    pub max_length: usize,
+    /// Разрешено ли смешивать в имени буквы разных письменностей
+    pub mixed_scripts: bool,
+    /// Разрешены ли цифры
+    pub digits: bool,
+    /// Разрешенные знаки препинания помимо букв и пробелов
+    pub punctuation: String,
+}
+
+impl Default for NamePolicy{
+    fn default() -> Self {
+        NamePolicy{
+            max_length: MAX_NAME_LENGTH,
+            mixed_scripts: false,
+            digits: false,
+            punctuation: "-'’.".to_owned(),
+        }
+    }
+}
+
+impl NamePolicy{
+    /// Привести имя к каноническому виду
+    ///
+    /// Нормализация Unicode NFC, пробелы по краям отбрасываются, а внутри схлопываются в один
+    pub fn clean(name: &str) -> String {
+        name.nfc().collect::<String>().split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+
+    /// Проверить имя и привести его к каноническому виду
+    ///
+    /// Возвращает все нарушенные правила разом, каждое как ошибку поля field
+    pub fn check(&self, field: &'static str, name: &str) -> Result<String, AppError> {
+        let name = NamePolicy::clean(name);
+        if name.is_empty() {
+            Err(AppError::validation(field, "name cannot consist of whitespaces or have zero length"))?;
+        }
+        let mut violations = Vec::new();
+        let length = name.chars().count();
+        if length > self.max_length {
+            violations.push(AppError::validation(field, &format!("name is {length} characters long, at most {} allowed", self.max_length)));
+        }
+        if let Some(c) = name.chars().find(|c| is_invisible(*c)) {
+            violations.push(AppError::validation(field, &format!("name cannot contain control or invisible characters (U+{:04X})", c as u32)));
+        }
+        if !self.digits && name.chars().any(char::is_numeric) {
+            violations.push(AppError::validation(field, "name cannot contain digits"));
+        }
+        if let Some(c) = name.chars().find(|c| !(c.is_alphabetic()
+            || c.is_numeric()
+            || *c == ' '
+            || is_combining_mark(*c)
+            || is_invisible(*c)
+            || self.punctuation.contains(*c))) {
+            violations.push(AppError::validation(field, &format!("name cannot contain '{c}'")));
+        }
+        if !self.mixed_scripts {
+            let mut scripts: Vec<Script> = Vec::new();
+            for script in name.chars().filter(|c| c.is_alphabetic()).map(Script::of) {
+                if !scripts.contains(&script) {
+                    scripts.push(script);
+                }
+            }
+            if scripts.len() > 1 {
+                let names: Vec<&str> = scripts.iter().map(Script::name).collect();
+                violations.push(AppError::validation(field, &format!("name mixes {} letters", names.join(" and "))));
+            }
+        }
+        match AppError::from_violations(violations) {
+            Some(error) => Err(error),
+            None => Ok(name),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests{
+    use super::{NamePolicy, MAX_NAME_LENGTH};
+    use crate::errors::AppError;
+
+    #[test]
+    fn clean_test(){
+        assert_eq!("Иван Петров", NamePolicy::clean("  Иван \t  Петров \n"));
+        // "й" из "и" и комбинируемого бревиса собирается в один символ
+        assert_eq!("Андрей", NamePolicy::clean("Андреи\u{0306}"));
+        assert_eq!(6, NamePolicy::clean("Андреи\u{0306}").chars().count());
+    }
+
+    #[test]
+    fn check_accepts_regular_names_test(){
+        let policy = NamePolicy::default();
+        assert_eq!("Анна-Мария Д'Арк", policy.check("name", " Анна-Мария  Д'Арк ").unwrap());
+        assert_eq!("John O’Neil Jr.", policy.check("name", "John O’Neil Jr.").unwrap());
+        assert_eq!("José Müller", policy.check("name", "Jose\u{0301} Müller").unwrap());
+    }
+
+    #[test]
+    fn check_rejects_test(){
+        let policy = NamePolicy::default();
+        assert!(matches!(policy.check("name", " \t "), Err(AppError::Validation{field: "name", ..})));
+        assert!(matches!(policy.check("name", &"a".repeat(MAX_NAME_LENGTH + 1)), Err(AppError::Validation{field: "name", ..})));
+        assert!(policy.check("name", &"я".repeat(MAX_NAME_LENGTH)).is_ok());
+        assert!(matches!(policy.check("name", "Ivan\u{0007}"), Err(AppError::Validation{field: "name", ..})));
+        assert!(matches!(policy.check("name", "Iva\u{200B}n"), Err(AppError::Validation{field: "name", ..})));
+        assert!(matches!(policy.check("name", "Ivan 2"), Err(AppError::Validation{field: "name", ..})));
+        assert!(matches!(policy.check("name", "Ivan <b>"), Err(AppError::Validation{field: "name", ..})));
+        // Латинская "a" среди кириллицы
+        assert!(matches!(policy.check("name", "Ивaн"), Err(AppError::Validation{field: "name", ..})));
+    }
+
+    #[test]
+    fn check_reports_all_violations_test(){
+        let policy = NamePolicy{max_length: 5, ..NamePolicy::default()};
+        let error = policy.check("name", "Иван Smith 3%").unwrap_err();
+        let messages: Vec<String> = error.field_errors().into_iter().map(|error| error.message).collect();
+        assert_eq!(4, messages.len(), "{messages:?}");
+        assert!(messages.iter().any(|message| message.contains("at most 5")));
+        assert!(messages.iter().any(|message| message.contains("digits")));
+        assert!(messages.iter().any(|message| message.contains("'%'")));
+        assert!(messages.iter().any(|message| message.contains("Cyrillic and Latin")));
+    }
+
+    #[test]
+    fn configurable_policy_test(){
+        let policy = NamePolicy{mixed_scripts: true, digits: true, punctuation: "-_".to_owned(), ..NamePolicy::default()};
+        assert_eq!("Иван Smith_2", policy.check("name", "Иван Smith_2").unwrap());
+        assert!(policy.check("name", "O'Neil").is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use actix_web::{get, HttpResponse};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use crate::server;
+
+
+/// Эндпоинты /employee
+#[derive(OpenApi)]
+#[openapi(paths(
+    server::list_employees,
+    server::search_employees,
+    server::increase_employee_salary,
+    server::get_employee_salary,
+    server::add_new_employee,
+    server::get_employee_by_id,
+    server::get_employee_salary_by_id,
+    server::increase_employee_salary_by_id,
+    server::adjust_employee_salary,
+    server::increase_salaries,
+    server::get_salary_history,
+    server::update_employee,
+    server::terminate_employee,
+))]
+struct EmployeeApi;
+
+
+/// Административные эндпоинты /admin
+#[derive(OpenApi)]
+#[openapi(paths(
+    server::delete_employee,
+))]
+struct AdminApi;
+
+
+/// Проверки состояния /health
+#[derive(OpenApi)]
+#[openapi(paths(
+    server::liveness_probe,
+    server::readiness_probe,
+))]
+struct HealthApi;
+
+
+/// Способы проверки подлинности, на которые ссылаются эндпоинты /employee и /admin
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes{
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Api-Key"))));
+        components.add_security_scheme("bearer", SecurityScheme::Http(HttpBuilder::new()
+            .scheme(HttpAuthScheme::Bearer)
+            .bearer_format("JWT")
+            .build()));
+    }
+}
+
+
+/// Спецификация OpenAPI всего приложения
+///
+/// Пути и схемы собираются из аннотаций обработчиков и моделей
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Employee salaries API"),
+    modifiers(&SecuritySchemes),
+    nest(
+        (path = "/employee", api = EmployeeApi),
+        (path = "/admin", api = AdminApi),
+        (path = "/health", api = HealthApi),
+    ),
+    tags(
+        (name = "employee", description = "Сотрудники и их зарплаты"),
+        (name = "admin", description = "Операции, которые нельзя отменить"),
+        (name = "health", description = "Проверки состояния для Docker и Kubernetes"),
+    ),
+)]
+pub struct ApiDoc;
+
+
+/// Страница документации Redoc
+///
+/// Версия сборки Redoc закреплена, чтобы страница не менялась вслед за последним выпуском на CDN
+const REDOC_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>Employee salaries API</title>
+    <meta charset="utf-8"/>
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+  </head>
+  <body>
+    <redoc spec-url="/openapi.json"></redoc>
+    <script src="https://cdn.redoc.ly/redoc/v2.1.5/bundles/redoc.standalone.js"></script>
+  </body>
+</html>
+"#;
+
+
+/// Спецификация OpenAPI в формате JSON
+#[get("/openapi.json")]
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+
+/// Документация по спецификации
+#[get("/docs")]
+pub async fn docs() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(REDOC_PAGE)
+}
+
+
+#[cfg(test)]
+mod tests{
+    use std::collections::{BTreeSet, HashMap};
+    use actix_service::Service;
+    use actix_web::dev::{ResourceMap, ServiceResponse};
+    use actix_web::http::{Method, StatusCode};
+    use serial_test::serial;
+    use utoipa::OpenApi;
+    use crate::config::{AuthConfig, Config};
+    use crate::metrics::route_label;
+    use crate::server::Server;
+    use super::ApiDoc;
+
+    /// Все операции спецификации в виде пар (метод, путь)
+    fn spec_operations() -> Vec<(Method, String)> {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let mut operations = Vec::new();
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            for method in item.as_object().unwrap().keys() {
+                if let Ok(method) = method.to_uppercase().parse::<Method>() {
+                    operations.push((method, path.clone()));
+                }
+            }
+        }
+        operations
+    }
+
+    #[test]
+    fn spec_test(){
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let add = &spec["paths"]["/employee/add"]["put"];
+        assert!(add["requestBody"]["content"]["application/json"].is_object());
+        assert!(add["requestBody"]["content"]["application/x-www-form-urlencoded"].is_object());
+        assert!(add["responses"]["200"].is_object());
+        assert!(spec["paths"]["/employee/{id}"]["patch"]["parameters"].as_array().unwrap().iter().any(|parameter| parameter["in"] == "path"));
+        assert_eq!("X-Api-Key", spec["components"]["securitySchemes"]["api_key"]["name"]);
+        assert_eq!("bearer", spec["components"]["securitySchemes"]["bearer"]["scheme"]);
+        assert!(add["responses"]["401"].is_object());
+        assert!(add["responses"]["403"].is_object());
+        assert!(add["security"].is_array());
+        assert!(spec["paths"]["/health/ready"]["get"]["security"].is_null());
+        // Нижняя граница зарплаты может быть нулевой, верхняя - только положительной
+        let parameters = spec["paths"]["/employee"]["get"]["parameters"].as_array().unwrap();
+        let pattern = |name: &str| parameters.iter().find(|parameter| parameter["name"] == name).unwrap()["schema"]["pattern"].clone();
+        assert_eq!(r"^\d+(\.\d{1,2})?$", pattern("min_salary"));
+        assert_eq!(r"^(?!0+(\.0{1,2})?$)\d+(\.\d{1,2})?$", pattern("max_salary"));
+        for schema in ["Employee", "Money", "Currency", "RoundingMode", "ErrorResponse"] {
+            assert!(spec["components"]["schemas"][schema].is_object(), "{schema} schema is missing");
+        }
+    }
+
+    /// Приложение с заглушкой базы и без проверки подлинности
+    ///
+    /// Проверка подлинности ответила бы 401 на любой путь, включая несуществующие
+    async fn test_app() -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error> {
+        dotenv::dotenv().ok();
+        let config = Config{auth: AuthConfig{enabled: false, ..AuthConfig::default()}, ..Config::default()};
+        Server::builder()
+            .config(config)
+            .host("localhost".to_owned())
+            .port(8080)
+            .build()
+            .test_start()
+            .await
+            .unwrap()
+    }
+
+    /// Имена всех маршрутов, зарегистрированных в приложении
+    ///
+    /// ResourceMap не позволяет обойти себя, но выводит в Debug каждый вложенный ResourceDef с его именем,
+    /// а #[get], #[post] и другие макросы называют маршрут по имени обработчика
+    fn route_names(map: &ResourceMap) -> BTreeSet<String> {
+        format!("{map:?}")
+            .split("name: Some(\"")
+            .skip(1)
+            .filter_map(|rest| rest.split('"').next())
+            .map(str::to_owned)
+            .collect()
+    }
+
+    /// Каждая операция спецификации обслуживается зарегистрированным маршрутом
+    #[actix_web::test]
+    #[serial]
+    async fn spec_matches_routes_test(){
+        let app = test_app().await;
+        let operations = spec_operations();
+        assert!(!operations.is_empty());
+        for (method, path) in operations {
+            let uri = path.replace("{id}", "1");
+            let request = actix_web::test::TestRequest::default()
+                .method(method.clone())
+                .uri(&uri)
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            let status = response.status();
+            let body = actix_web::test::read_body(response).await;
+            assert_ne!(StatusCode::METHOD_NOT_ALLOWED, status, "{method} {path} is not routed");
+            assert!(status != StatusCode::NOT_FOUND || !body.is_empty(), "{method} {path} is not routed");
+        }
+    }
+
+    /// Каждый зарегистрированный маршрут описан в спецификации операцией с именем его обработчика
+    /// и тем же путем. Метод сверяет spec_matches_routes_test: маршрут с другим методом ответил бы 404
+    #[actix_web::test]
+    #[serial]
+    async fn routes_are_documented_test(){
+        // Документация и метрики не входят в спецификацию API
+        const UNDOCUMENTED: &[&str] = &["openapi_json", "docs", "prometheus_metrics"];
+        let app = test_app().await;
+        let request = actix_web::test::TestRequest::get()
+            .uri("/health/live")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        let request = response.request();
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let mut documented = HashMap::new();
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            for operation in item.as_object().unwrap().values() {
+                if let Some(operation_id) = operation["operationId"].as_str() {
+                    documented.insert(operation_id.to_owned(), path.clone());
+                }
+            }
+        }
+
+        let names = route_names(request.resource_map());
+        assert!(names.contains("list_employees") && names.contains("prometheus_metrics"), "{names:?}");
+        let mut routes = 0;
+        for name in names.iter().filter(|name| !UNDOCUMENTED.contains(&name.as_str())) {
+            routes += 1;
+            let url = request.url_for(name, ["1"]).unwrap();
+            let pattern = request.resource_map().match_pattern(url.path()).unwrap();
+            assert_eq!(Some(&route_label(&pattern)), documented.get(name), "route {name} ({pattern}) is not documented");
+        }
+        assert_eq!(routes, spec_operations().len(), "routes and OpenAPI operations differ");
+    }
+}
@@ -0,0 +1,66 @@
+use actix_web::dev::Payload;
+use actix_web::http::header::{CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING};
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use futures::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use crate::errors::AppError;
+
+
+/// Есть ли у запроса тело
+///
+/// Без заголовка Content-Length о теле говорят Content-Type или Transfer-Encoding
+fn has_body(request: &HttpRequest) -> bool {
+    let length = request.headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+    match length {
+        Some(length) => length > 0,
+        None => request.headers().contains_key(CONTENT_TYPE) || request.headers().contains_key(TRANSFER_ENCODING),
+    }
+}
+
+
+/// Параметры запроса из строки запроса или тела
+///
+/// Тело разбирается по заголовку Content-Type как JSON или application/x-www-form-urlencoded,
+/// без тела параметры берутся из строки запроса. Смешивать строку запроса и тело нельзя,
+/// тело в другом формате отклоняется со статусом 415
+pub struct Params<T>(pub T);
+
+impl<T> Params<T>{
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Params<T>{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        if !has_body(request) {
+            let query = web::Query::<T>::from_request(request, payload);
+            return Box::pin(async move { Ok(Params(query.await?.into_inner())) });
+        }
+        if !request.query_string().is_empty() {
+            let error = AppError::validation("query", "parameters must be passed either in the query string or in the body");
+            return Box::pin(async move { Err(error.into()) });
+        }
+        let mime = request.mime_type().ok().flatten();
+        match mime.as_ref().map(|mime| (mime.type_(), mime.subtype(), mime.suffix())) {
+            Some((mime::APPLICATION, mime::JSON, _)) | Some((mime::APPLICATION, _, Some(mime::JSON))) => {
+                let json = web::Json::<T>::from_request(request, payload);
+                Box::pin(async move { Ok(Params(json.await?.into_inner())) })
+            },
+            Some((mime::APPLICATION, mime::WWW_FORM_URLENCODED, _)) => {
+                let form = web::Form::<T>::from_request(request, payload);
+                Box::pin(async move { Ok(Params(form.await?.into_inner())) })
+            },
+            _ => {
+                let error = AppError::UnsupportedMediaType("request body must be application/json or application/x-www-form-urlencoded".to_owned());
+                Box::pin(async move { Err(error.into()) })
+            },
+        }
+    }
+}
@@ -1,134 +1,642 @@
 use async_trait::async_trait;
-use sqlx::postgres::{PgPoolOptions, PgConnectOptions, Postgres};
-use sqlx::Pool;
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgArguments, PgConnection, Postgres};
+use sqlx::query::QueryAs;
+use sqlx::{Connection, Pool, Transaction};
 use mockall::automock;
-use std::error::Error;
-use std::env;
-use crate::models::{EmployeeData, EmployeeName, EmployeeSalary, UncheckedEmployeeSalary, SalaryMultiplier};
+use std::str::FromStr;
+use crate::config::{Config, DatabaseConfig};
+use crate::access::RaiseLimit;
+use crate::errors::AppError;
+use crate::migrations::{self, MigrationMode, MigrationStatus};
+use crate::telemetry::{self, statement};
+use crate::models::{normalize_name, Employee, EmployeeData, EmployeeUpdate, EmployeeTermination, EmployeeId, EmployeeName, UncheckedEmployee, UncheckedEmployeeSalary, SalaryMultiplier, SalaryChange, SalaryPercentage, SalaryAdjustment, SalaryBatch, SalaryBatchTarget, SalaryBatchReport, SalaryBatchItemReport, BatchMode, EmployeeFilter, EmployeeListQuery, EmployeePage, PoolStatus, EmployeeSortField, EmployeeSearch, EmployeeMatch, UncheckedEmployeeMatch, UncheckedSalaryHistoryEntry, SalaryHistoryPage, Page};
+
+/// Колонки записи о сотруднике в том виде, в котором их ожидает UncheckedEmployee
+///
+/// Зарплата хранится как NUMERIC(18,2) и переводится в минорные единицы на стороне базы
+const EMPLOYEE_COLUMNS: &str = "id, name, surname, given_name, patronymic, display_name,
+    (salary * 100)::BIGINT AS salary_minor, currency::TEXT AS currency, personnel_number";
+
+/// Условие выбора работающих сотрудников по EmployeeFilter
+///
+/// Параметры с $1 по $5 подставляются функцией bind_filter, незаданные условия передаются как NULL
+const EMPLOYEE_FILTER: &str = "terminated_at IS NULL
+    AND ($1::TEXT IS NULL OR currency = $1)
+    AND ($2::BIGINT IS NULL OR salary >= $2::NUMERIC / 100)
+    AND ($3::BIGINT IS NULL OR salary <= $3::NUMERIC / 100)
+    AND ($4::TEXT IS NULL OR normalized_name LIKE $4 || '%')
+    AND ($5::TEXT IS NULL OR normalized_surname LIKE $5 || '%')
+    AND ($6::INT IS NULL OR id > $6)";
+
+/// Подставить параметры фильтра в запрос с условием EMPLOYEE_FILTER
+fn bind_filter<'q, O>(query: QueryAs<'q, Postgres, O, PgArguments>, filter: &EmployeeFilter) -> QueryAs<'q, Postgres, O, PgArguments> {
+    // Символы шаблонов LIKE в начале имени сравниваются буквально
+    let escape = |prefix: &Option<String>| prefix.as_ref().map(|prefix| prefix
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_"));
+    query
+        .bind(filter.currency.map(|currency| currency.code()))
+        .bind(filter.min_salary.map(|salary| salary.amount_minor))
+        .bind(filter.max_salary.map(|salary| salary.amount_minor))
+        .bind(escape(&filter.name_prefix))
+        .bind(escape(&filter.surname_prefix))
+        .bind(filter.after_id)
+}
 
 #[automock]
 #[async_trait]
 pub trait DBClient: Send + Sync{
-    async fn init_db(&self) -> Result<(), Box<dyn Error>>; 
-    async fn init_db_clear(&self) -> Result<(), Box<dyn Error>>; 
-    async fn get_employee_salary(&self, data: EmployeeName) -> Result<EmployeeSalary, Box<dyn Error>>; 
-    async fn add_new_employee(&self, data: EmployeeData) -> Result<(), Box<dyn Error>>;
-    async fn increase_employee_salary(&self, data: SalaryMultiplier) -> Result<EmployeeSalary, Box<dyn Error>>;
+    async fn init_db(&self) -> Result<(), AppError>; 
+    async fn init_db_clear(&self) -> Result<(), AppError>; 
+    async fn get_employee_salary(&self, data: EmployeeName) -> Result<Employee, AppError>; 
+    async fn add_new_employee(&self, data: EmployeeData) -> Result<Employee, AppError>;
+    async fn increase_employee_salary(&self, data: SalaryMultiplier, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError>;
+    async fn get_employee_by_id(&self, id: EmployeeId) -> Result<Employee, AppError>;
+    async fn increase_employee_salary_by_id(&self, id: EmployeeId, percentage: SalaryPercentage, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError>;
+    async fn adjust_employee_salary(&self, id: EmployeeId, adjustment: SalaryAdjustment, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError>;
+    async fn increase_salaries(&self, batch: SalaryBatch, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryBatchReport, AppError>;
+    async fn update_employee(&self, id: EmployeeId, update: EmployeeUpdate) -> Result<Employee, AppError>;
+    async fn terminate_employee(&self, id: EmployeeId) -> Result<EmployeeTermination, AppError>;
+    async fn delete_employee(&self, id: EmployeeId) -> Result<Employee, AppError>;
+    async fn list_employees(&self, query: EmployeeListQuery) -> Result<EmployeePage, AppError>;
+    async fn search_employees(&self, search: EmployeeSearch) -> Result<Vec<EmployeeMatch>, AppError>;
+    async fn get_salary_history(&self, id: EmployeeId, page: Page) -> Result<SalaryHistoryPage, AppError>;
+    async fn ping(&self) -> Result<(), AppError>;
+    async fn migration_status(&self) -> Result<MigrationStatus, AppError>;
+    fn pool_status(&self) -> PoolStatus;
+}
+
+/// Политика уникальности сотрудников
+///
+/// Определяет, по какому признаку два сотрудника считаются одним и тем же человеком.
+/// Задается настройкой database.uniqueness (EMPLOYEE_UNIQUENESS) и закрепляется уникальным индексом в схеме
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum UniquenessPolicy{
+    /// Имена сравниваются без учета регистра и лишних пробелов
+    #[default]
+    NormalizedName,
+    /// Сотрудники различаются внешним табельным номером, который обязателен при добавлении
+    PersonnelNumber,
+}
+
+impl FromStr for UniquenessPolicy{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(UniquenessPolicy::NormalizedName),
+            "personnel_number" => Ok(UniquenessPolicy::PersonnelNumber),
+            _ => Err(AppError::validation("EMPLOYEE_UNIQUENESS", "uniqueness policy must be either \"name\" or \"personnel_number\"")),
+        }
+    }
 }
 
 /// Обертка над клиентом базы данных
 ///
-/// Подключается к постгресу с помощью переменных окружения
+/// Подключается к постгресу по настройкам из раздела database конфигурации
 #[derive(Debug)]
 pub struct DBClientPostgres{
-    inner_client: Pool<Postgres> 
+    inner_client: Pool<Postgres>,
+    uniqueness: UniquenessPolicy,
+    migrations: MigrationMode,
 }
 
 impl DBClientPostgres{
     /// Новое подключение к базе данных
     ///
     /// Использовать для основного подключения
-    pub async fn new() -> Result<DBClientPostgres, Box<dyn Error>> {
-        let options = PgConnectOptions::new()
-            .host(&env::var("DB_CONTAINER_NAME").unwrap_or("localhost".to_owned()))
-            .username(&env::var("DB_USERNAME").unwrap_or("username".to_owned()))
-            .password(&env::var("DB_PASSWORD").unwrap_or("password".to_owned()))
-            .database(&env::var("DB_NAME").unwrap_or("username".to_owned()))
-            .port(5432);
-        let client = PgPoolOptions::new()
-            .max_connections(7)
-            .connect_with(options)
+    pub async fn new(config: &DatabaseConfig) -> Result<DBClientPostgres, AppError> {
+        let client = config.pool_options()
+            .connect_with(config.connect_options()?)
             .await?;
 
-        Ok(DBClientPostgres{inner_client: client})
+        Ok(DBClientPostgres{inner_client: client, uniqueness: config.uniqueness, migrations: config.migrations})
     }
     
-    pub async fn new_test() -> Result<DBClientPostgres, Box<dyn Error>> {
-        let options = PgConnectOptions::new()
-            .host(&env::var("DB_CONTAINER_NAME").unwrap_or("localhost".to_owned()))
-            .username(&env::var("DB_USERNAME").unwrap_or("username".to_owned()))
-            .password(&env::var("DB_PASSWORD").unwrap_or("password".to_owned()))
-            .database(&env::var("DB_NAME").unwrap_or("username".to_owned()))
-            .port(5432);
-        let client = PgPoolOptions::new()
-            .max_connections(7)
-            .connect_with(options)
+    pub async fn new_test() -> Result<DBClientPostgres, AppError> {
+        let client = DBClientPostgres::new(&Config::test()?.database).await?;
+        Ok(client.with_uniqueness_policy(UniquenessPolicy::default()).with_migration_mode(MigrationMode::Apply))
+    }
+
+    /// Заменить политику уникальности сотрудников
+    ///
+    /// Вступает в силу при следующей инициализации схемы
+    pub fn with_uniqueness_policy(mut self, policy: UniquenessPolicy) -> Self {
+        self.uniqueness = policy;
+        self
+    }
+
+    /// Заменить режим работы с миграциями
+    ///
+    /// Вступает в силу при следующей инициализации схемы
+    pub fn with_migration_mode(mut self, mode: MigrationMode) -> Self {
+        self.migrations = mode;
+        self
+    }
+
+    /// Взять соединение из пула
+    ///
+    /// Ожидание свободного соединения записывается в спан pool.acquire
+    async fn acquire(&self) -> Result<PoolConnection<Postgres>, AppError> {
+        Ok(telemetry::pool_wait("pool.acquire", self.pool_status(), self.inner_client.acquire()).await?)
+    }
+
+    /// Взять соединение из пула и начать на нем транзакцию
+    ///
+    /// Спан pool.begin включает ожидание соединения и выполнение BEGIN
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, AppError> {
+        Ok(telemetry::pool_wait("pool.begin", self.pool_status(), self.inner_client.begin()).await?)
+    }
+
+    /// Применить политику уникальности к схеме БД
+    ///
+    /// Создает либо удаляет уникальный индекс по нормализованным именам согласно политике уникальности.
+    /// Если работающие сотрудники уже делят одно имя, индекс не создается, а ошибка перечисляет их идентификаторы
+    async fn apply_uniqueness_policy(&self, conn: &mut PgConnection) -> Result<(), AppError> {
+        match self.uniqueness {
+            UniquenessPolicy::NormalizedName => {
+                let duplicates: Vec<(Vec<i32>,)> = statement("select_duplicate_names", sqlx::query_as(r#"SELECT array_agg(id ORDER BY id) FROM employees WHERE terminated_at IS NULL GROUP BY normalized_name HAVING COUNT(*) > 1 ORDER BY MIN(id)"#)
+                    .fetch_all(&mut *conn))
+                    .await?;
+                if !duplicates.is_empty() {
+                    let groups: Vec<String> = duplicates.into_iter().map(|(ids,)| format!("{ids:?}")).collect();
+                    Err(AppError::Conflict(format!("employees with ids {} share names, rename or terminate them or use the personnel_number uniqueness policy", groups.join(", "))))?;
+                }
+                statement("create_name_index", sqlx::query(r#"CREATE UNIQUE INDEX IF NOT EXISTS employees_normalized_name_key ON employees (normalized_name) WHERE terminated_at IS NULL"#)
+                .execute(&mut *conn))
+                .await?;
+            },
+            UniquenessPolicy::PersonnelNumber => {
+                statement("drop_name_index", sqlx::query(r#"DROP INDEX IF EXISTS employees_normalized_name_key"#)
+                .execute(&mut *conn))
+                .await?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Проверить, что схема соответствует политике уникальности
+    ///
+    /// Ничего не изменяет: уникальный индекс по нормализованным именам
+    /// должен существовать только при политике уникальности по имени
+    async fn check_uniqueness_policy(&self, conn: &mut PgConnection) -> Result<(), AppError> {
+        let (exists,): (bool,) = statement("select_name_index", sqlx::query_as(r#"SELECT to_regclass('employees_normalized_name_key') IS NOT NULL"#)
+            .fetch_one(&mut *conn))
+            .await?;
+        match (self.uniqueness, exists) {
+            (UniquenessPolicy::NormalizedName, false) => Err(AppError::Storage("unique index on employee names is missing, apply the uniqueness policy".to_owned())),
+            (UniquenessPolicy::PersonnelNumber, true) => Err(AppError::Storage("unique index on employee names is still present, apply the uniqueness policy".to_owned())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Преобразовать нарушение уникальности в понятную ошибку конфликта
+    fn map_unique_violation(error: sqlx::Error) -> AppError {
+        if let sqlx::Error::Database(db_error) = &error {
+            match db_error.constraint() {
+                Some("employees_normalized_name_key") => return AppError::Conflict("employee with this name already exists".to_owned()),
+                Some("employees_personnel_number_key") => return AppError::Conflict("employee with this personnel number already exists".to_owned()),
+                _ => {},
+            }
+        }
+        error.into()
+    }
+
+    /// Найти идентификатор сотрудника по имени
+    ///
+    /// Имена сравниваются в нормализованной форме, уволенные сотрудники не учитываются.
+    /// Если имени соответствует несколько сотрудников, возвращает ошибку со списком их идентификаторов
+    async fn find_employee_id(conn: &mut PgConnection, name: &EmployeeName) -> Result<EmployeeId, AppError> {
+        let ids: Vec<(i32,)> = statement("find_employee_id", sqlx::query_as(r#"SELECT id FROM employees WHERE normalized_name = $1 AND terminated_at IS NULL ORDER BY id"#)
+            .bind(normalize_name(&name.name))
+            .fetch_all(&mut *conn))
+            .await?;
+        match ids.as_slice() {
+            [] => Err(AppError::NotFound(format!("employee with name {:?} does not exist", name.name))),
+            [(id,)] => Ok(EmployeeId{id: *id}),
+            _ => Err(AppError::Ambiguous{
+                message: format!("several employees are named {:?}, address them by id", name.name),
+                candidates: ids.into_iter().map(|(id,)| id).collect(),
+            }),
+        }
+    }
+
+    /// Получить запись о работающем сотруднике по идентификатору
+    async fn fetch_employee(conn: &mut PgConnection, id: EmployeeId) -> Result<Employee, AppError> {
+        let employee: Option<UncheckedEmployee> = statement("select_employee", sqlx::query_as(&format!("SELECT {EMPLOYEE_COLUMNS} FROM employees WHERE id = $1 AND terminated_at IS NULL"))
+            .bind(id.id)
+            .fetch_optional(&mut *conn))
+            .await?;
+        let employee = employee.ok_or(AppError::NotFound(format!("employee with id {} does not exist", id.id)))?;
+        employee.check()
+    }
+
+    /// Идентификаторы работающих сотрудников, подходящих под фильтр
+    ///
+    /// Строки сотрудников блокируются до конца транзакции в порядке идентификаторов
+    async fn find_employee_ids(conn: &mut PgConnection, filter: &EmployeeFilter) -> Result<Vec<EmployeeId>, AppError> {
+        let query = format!("SELECT id FROM employees WHERE {EMPLOYEE_FILTER} ORDER BY id FOR UPDATE");
+        let ids: Vec<(i32,)> = statement("lock_filtered_employees", bind_filter(sqlx::query_as(&query), filter)
+            .fetch_all(&mut *conn))
             .await?;
+        Ok(ids.into_iter().map(|(id,)| EmployeeId{id}).collect())
+    }
 
-        Ok(DBClientPostgres{inner_client: client})
+    /// Изменить зарплату сотрудника с заданным идентификатором
+    ///
+    /// Должна вызываться внутри транзакции: строка сотрудника блокируется до ее завершения,
+    /// а запись в журнал изменений фиксируется вместе с новой зарплатой.
+    /// Предел повышения limit сравнивается с итоговой зарплатой после округления
+    async fn adjust_salary(conn: &mut PgConnection, id: EmployeeId, adjustment: &SalaryAdjustment, limit: Option<&RaiseLimit>, actor: &str) -> Result<SalaryChange, AppError> {
+        let employee_salary_raw: Option<UncheckedEmployeeSalary> = statement("lock_employee_salary", sqlx::query_as(r#"SELECT (salary * 100)::BIGINT AS amount_minor, currency::TEXT AS currency FROM employees WHERE id = $1 AND terminated_at IS NULL FOR UPDATE"#)
+            .bind(id.id)
+            .fetch_optional(&mut *conn))
+            .await?;
+        let employee_salary_raw = employee_salary_raw.ok_or(AppError::NotFound(format!("employee with id {} does not exist", id.id)))?;
+        let mut employee_salary = employee_salary_raw.check()?;
+
+        let old_employee_salary = employee_salary.adjust(adjustment)?;
+        if let Some(limit) = limit {
+            limit.check(adjustment, &old_employee_salary.amount, &employee_salary.amount)?;
+        }
+        let employee: UncheckedEmployee = statement("update_employee_salary", sqlx::query_as(&format!("UPDATE employees SET salary = $1::NUMERIC / 100 WHERE id = $2 RETURNING {EMPLOYEE_COLUMNS}"))
+            .bind(employee_salary.amount.amount_minor)
+            .bind(id.id)
+            .fetch_one(&mut *conn))
+            .await?;
+        let employee = employee.check()?;
+        let (percentage, rounding) = match adjustment {
+            SalaryAdjustment::Percentage{percentage, rounding} => (Some(percentage.hundredths), Some(rounding.name())),
+            SalaryAdjustment::Delta{..} | SalaryAdjustment::Set{..} => (None, None),
+        };
+        statement("insert_salary_history", sqlx::query(r#"INSERT INTO salary_history(employee_id, old_salary, new_salary, currency, operation, percentage, rounding, actor)
+                    VALUES ($1, $2::NUMERIC / 100, $3::NUMERIC / 100, $4, $5, $6::NUMERIC / 100, $7, $8)"#)
+            .bind(id.id)
+            .bind(old_employee_salary.amount.amount_minor)
+            .bind(employee.salary.amount_minor)
+            .bind(employee.salary.currency.code())
+            .bind(adjustment.operation().name())
+            .bind(percentage)
+            .bind(rounding)
+            .bind(actor)
+            .execute(&mut *conn))
+            .await?;
+        Ok(SalaryChange{new_salary: employee.salary, employee, old_salary: old_employee_salary.amount})
     }
 }
 
 #[async_trait]
 impl DBClient for DBClientPostgres{
     /// Инициализация схемы БД без стирания предыдущих данных
-    async fn init_db(&self) -> Result<(), Box<dyn Error>> {
-        sqlx::query(r#"CREATE TABLE IF NOT EXISTS employees (
-                    id SERIAL PRIMARY KEY,
-                    name VARCHAR(255) NOT NULL,
-                    salary INT NOT NULL
-                    )"#)
-        .execute(&self.inner_client)
-        .await?;
+    ///
+    /// В зависимости от режима применяет недостающие миграции и политику уникальности
+    /// либо только проверяет их и отказывается продолжать, если схема отстает
+    async fn init_db(&self) -> Result<(), AppError> {
+        match self.migrations {
+            MigrationMode::Apply => {
+                migrations::apply(&self.inner_client).await?;
+                let mut conn = self.acquire().await?;
+                self.apply_uniqueness_policy(&mut conn).await?;
+            },
+            MigrationMode::Check => {
+                let mut conn = self.acquire().await?;
+                migrations::check(&mut conn).await?;
+                self.check_uniqueness_policy(&mut conn).await?;
+            },
+        }
         Ok(())
     }
     
     /// Инициализация схемы БД с удалением существующих данных
-    async fn init_db_clear(&self) -> Result<(), Box<dyn Error>> {
-        let mut tx = self.inner_client.begin().await?;
-        sqlx::query(r#"CREATE TABLE IF NOT EXISTS employees (id SERIAL PRIMARY KEY, name VARCHAR(255) NOT NULL, salary INT NOT NULL)"#)
-        .execute(&mut *tx)
-        .await?;
-        sqlx::query("TRUNCATE TABLE employees")
-        .execute(&mut *tx)
+    ///
+    /// Схема пересоздается с нуля применением всех миграций
+    async fn init_db_clear(&self) -> Result<(), AppError> {
+        statement("drop_tables", sqlx::query("DROP TABLE IF EXISTS employees, salary_history, _sqlx_migrations")
+        .execute(&self.inner_client))
         .await?;
-        tx.commit().await?;
-        Ok(())
+        migrations::apply(&self.inner_client).await?;
+        let mut conn = self.acquire().await?;
+        self.apply_uniqueness_policy(&mut conn).await
     }
 
     /// Получить зарплату работника
     ///
-    /// Обращается к базе и возвращает проверенные данные о зарплате сотрудника
-    async fn get_employee_salary(&self, data: EmployeeName) -> Result<EmployeeSalary, Box<dyn Error>> {
-        let employee_salary_raw: UncheckedEmployeeSalary = sqlx::query_as(r#"SELECT salary AS amount FROM employees WHERE name = $1"#)
-            .bind(data.name)
-            .fetch_one(&self.inner_client)
-            .await?;
-        Ok(employee_salary_raw.check()?)
+    /// Обращается к базе и возвращает запись о сотруднике с проверенной зарплатой
+    /// Если имени соответствует несколько сотрудников, запрос отклоняется
+    async fn get_employee_salary(&self, data: EmployeeName) -> Result<Employee, AppError> {
+        let mut conn = self.acquire().await?;
+        let id = Self::find_employee_id(&mut conn, &data).await?;
+        Self::fetch_employee(&mut conn, id).await
     }
     
     /// Добавить нового сотрудника
     ///
     /// Обращается к базе и добавляет в нее новые данные о сотруднике
-    async fn add_new_employee(&self, data: EmployeeData) -> Result<(), Box<dyn Error>> {
-        sqlx::query(r#"INSERT INTO employees(name, salary) VALUES ($1 , $2)"#)
-        .bind(data.name)
-        .bind(data.salary)
-        .execute(&self.inner_client)
-        .await?;
-        Ok(())
+    /// Если сотрудник уже существует согласно политике уникальности, возвращает ошибку конфликта
+    /// Возвращает созданную запись вместе с ее идентификатором
+    async fn add_new_employee(&self, data: EmployeeData) -> Result<Employee, AppError> {
+        if self.uniqueness == UniquenessPolicy::PersonnelNumber && data.personnel_number.is_none() {
+            Err(AppError::validation("personnel_number", "personnel number is required to tell employees apart"))?;
+        }
+        let mut conn = self.acquire().await?;
+        let employee: UncheckedEmployee = statement("insert_employee", sqlx::query_as(&format!("INSERT INTO employees(name, normalized_name, salary, currency, personnel_number,
+                    surname, given_name, patronymic, display_name, normalized_surname)
+                    VALUES ($1, $2, $3::NUMERIC / 100, $4, $5, $6, $7, $8, $9, $10) RETURNING {EMPLOYEE_COLUMNS}"))
+        .bind(&data.name)
+        .bind(normalize_name(&data.name))
+        .bind(data.salary.amount_minor)
+        .bind(data.salary.currency.code())
+        .bind(data.personnel_number)
+        .bind(&data.name_parts.surname)
+        .bind(&data.name_parts.given_name)
+        .bind(&data.name_parts.patronymic)
+        .bind(&data.name_parts.display_name)
+        .bind(normalize_name(&data.name_parts.surname))
+        .fetch_one(&mut *conn))
+        .await
+        .map_err(Self::map_unique_violation)?;
+        employee.check()
     }
 
     /// Увеличить зарплату сотрудника
     ///
     /// Обращается к базе и изменяет значение зарплаты сотрудника с совпадающим именем
-    /// Возвращает предыдущее значение зарплаты
-    async fn increase_employee_salary(&self, data: SalaryMultiplier) -> Result<EmployeeSalary, Box<dyn Error>> {
-        let mut tx = self.inner_client.begin().await?;
-        let employee_salary_raw: UncheckedEmployeeSalary = sqlx::query_as(r#"SELECT salary AS amount FROM employees WHERE name = $1"#)
-            .bind(&data.name)
-            .fetch_one(&mut *tx)
+    /// Если имени соответствует несколько сотрудников, запрос отклоняется
+    /// Возвращает обновленную запись и предыдущее значение зарплаты
+    async fn increase_employee_salary(&self, data: SalaryMultiplier, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError> {
+        let mut tx = self.begin().await?;
+        let id = Self::find_employee_id(&mut tx, &data.get_name()).await?;
+        let increase = Self::adjust_salary(&mut tx, id, &data.get_percentage().to_adjustment(), limit.as_ref(), &actor).await?;
+        statement("commit", tx.commit()).await?;
+        Ok(increase)
+    }
+
+    /// Получить запись о сотруднике по идентификатору
+    async fn get_employee_by_id(&self, id: EmployeeId) -> Result<Employee, AppError> {
+        let mut conn = self.acquire().await?;
+        Self::fetch_employee(&mut conn, id).await
+    }
+
+    /// Увеличить зарплату сотрудника по идентификатору
+    ///
+    /// Предел повышения limit сравнивается с зарплатой после округления.
+    /// Возвращает обновленную запись и предыдущее значение зарплаты
+    async fn increase_employee_salary_by_id(&self, id: EmployeeId, percentage: SalaryPercentage, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError> {
+        let mut tx = self.begin().await?;
+        let increase = Self::adjust_salary(&mut tx, id, &percentage.to_adjustment(), limit.as_ref(), &actor).await?;
+        statement("commit", tx.commit()).await?;
+        Ok(increase)
+    }
+
+    /// Изменить зарплату сотрудника по идентификатору
+    ///
+    /// Изменение на процент, на сумму или установка нового значения.
+    /// Предел повышения limit сравнивается с зарплатой, заблокированной в той же транзакции.
+    /// Возвращает обновленную запись, предыдущее и новое значение зарплаты
+    async fn adjust_employee_salary(&self, id: EmployeeId, adjustment: SalaryAdjustment, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError> {
+        let mut tx = self.begin().await?;
+        let change = Self::adjust_salary(&mut tx, id, &adjustment, limit.as_ref(), &actor).await?;
+        statement("commit", tx.commit()).await?;
+        Ok(change)
+    }
+
+    /// Повысить зарплату нескольким сотрудникам
+    ///
+    /// Пакет применяется в одной транзакции. В атомарном режиме первая ошибка отменяет весь пакет
+    /// и возвращается с идентификатором сотрудника. В режиме best effort каждое повышение
+    /// выполняется в своей точке сохранения, а ошибки перечисляются в отчете.
+    /// Предел повышения limit проверяется для каждого сотрудника по зарплате после округления
+    async fn increase_salaries(&self, batch: SalaryBatch, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryBatchReport, AppError> {
+        let mut tx = self.begin().await?;
+        let items = match batch.target {
+            SalaryBatchTarget::Items(items) => {
+                // Блокировка в порядке идентификаторов не дает двум пакетам взаимно ждать друг друга
+                let mut ids: Vec<i32> = items.iter().map(|item| item.id.id).collect();
+                ids.sort();
+                statement("lock_batch_employees", sqlx::query(r#"SELECT id FROM employees WHERE id = ANY($1) ORDER BY id FOR UPDATE"#)
+                    .bind(ids)
+                    .execute(&mut *tx))
+                    .await?;
+                items.into_iter().map(|item| (item.id, item.percentage)).collect()
+            },
+            SalaryBatchTarget::Filter{percentage, filter} => Self::find_employee_ids(&mut tx, &filter).await?
+                .into_iter()
+                .map(|id| (id, percentage.clone()))
+                .collect::<Vec<_>>(),
+        };
+
+        let mut report = SalaryBatchReport{mode: batch.mode, applied: 0, failed: 0, items: Vec::with_capacity(items.len())};
+        for (id, percentage) in items {
+            let result = match batch.mode {
+                BatchMode::Atomic => Self::adjust_salary(&mut tx, id, &percentage.to_adjustment(), limit.as_ref(), &actor).await
+                    .map_err(|e| e.for_employee(id.id))?,
+                BatchMode::BestEffort => {
+                    let mut savepoint = statement("savepoint", Connection::begin(&mut *tx)).await?;
+                    match Self::adjust_salary(&mut savepoint, id, &percentage.to_adjustment(), limit.as_ref(), &actor).await {
+                        Ok(change) => {
+                            statement("release_savepoint", savepoint.commit()).await?;
+                            change
+                        },
+                        Err(e) => {
+                            statement("rollback_savepoint", savepoint.rollback()).await?;
+                            report.failed += 1;
+                            report.items.push(SalaryBatchItemReport{id: id.id, change: None, error: Some(e.to_response().error)});
+                            continue;
+                        },
+                    }
+                },
+            };
+            report.applied += 1;
+            report.items.push(SalaryBatchItemReport{id: id.id, change: Some(result), error: None});
+        }
+        statement("commit", tx.commit()).await?;
+        Ok(report)
+    }
+
+    /// Изменить имя или табельный номер сотрудника
+    ///
+    /// Незаданные поля не изменяются, новое имя заменяет и все части имени. Уволенного сотрудника изменить нельзя
+    /// Если изменение нарушает политику уникальности, возвращает ошибку конфликта
+    async fn update_employee(&self, id: EmployeeId, update: EmployeeUpdate) -> Result<Employee, AppError> {
+        let parts = update.name_parts.as_ref();
+        let mut conn = self.acquire().await?;
+        let employee: Option<UncheckedEmployee> = statement("update_employee", sqlx::query_as(&format!("UPDATE employees
+                    SET name = COALESCE($1, name),
+                        normalized_name = COALESCE($2, normalized_name),
+                        personnel_number = COALESCE($3, personnel_number),
+                        surname = COALESCE($5, surname),
+                        given_name = CASE WHEN $1::TEXT IS NULL THEN given_name ELSE $6 END,
+                        patronymic = CASE WHEN $1::TEXT IS NULL THEN patronymic ELSE $7 END,
+                        display_name = CASE WHEN $1::TEXT IS NULL THEN display_name ELSE $8 END,
+                        normalized_surname = COALESCE($9, normalized_surname)
+                    WHERE id = $4 AND terminated_at IS NULL
+                    RETURNING {EMPLOYEE_COLUMNS}"))
+            .bind(&update.name)
+            .bind(update.name.as_deref().map(normalize_name))
+            .bind(&update.personnel_number)
+            .bind(id.id)
+            .bind(parts.map(|parts| &parts.surname))
+            .bind(parts.and_then(|parts| parts.given_name.as_ref()))
+            .bind(parts.and_then(|parts| parts.patronymic.as_ref()))
+            .bind(parts.and_then(|parts| parts.display_name.as_ref()))
+            .bind(parts.map(|parts| normalize_name(&parts.surname)))
+            .fetch_optional(&mut *conn))
+            .await
+            .map_err(Self::map_unique_violation)?;
+        let employee = employee.ok_or(AppError::NotFound(format!("employee with id {} does not exist", id.id)))?;
+        employee.check()
+    }
+
+    /// Уволить сотрудника
+    ///
+    /// Запись и журнал изменений зарплаты сохраняются, но сотрудник пропадает из обычных запросов
+    async fn terminate_employee(&self, id: EmployeeId) -> Result<EmployeeTermination, AppError> {
+        let mut tx = self.begin().await?;
+        let employee = Self::fetch_employee(&mut tx, id).await?;
+        let (terminated_at,) = statement("terminate_employee", sqlx::query_as(r#"UPDATE employees SET terminated_at = now()
+                    WHERE id = $1 AND terminated_at IS NULL
+                    RETURNING terminated_at"#)
+            .bind(id.id)
+            .fetch_optional(&mut *tx))
+            .await?
+            .ok_or(AppError::NotFound(format!("employee with id {} does not exist", id.id)))?;
+        statement("commit", tx.commit()).await?;
+        Ok(EmployeeTermination{employee, terminated_at})
+    }
+
+    /// Удалить сотрудника безвозвратно
+    ///
+    /// Удаляет и работающих, и уволенных сотрудников. Журнал изменений зарплаты сохраняется
+    async fn delete_employee(&self, id: EmployeeId) -> Result<Employee, AppError> {
+        let mut conn = self.acquire().await?;
+        let employee: Option<UncheckedEmployee> = statement("delete_employee", sqlx::query_as(&format!("DELETE FROM employees WHERE id = $1 RETURNING {EMPLOYEE_COLUMNS}"))
+            .bind(id.id)
+            .fetch_optional(&mut *conn))
             .await?;
-        let mut employee_salary = employee_salary_raw.check()?;
+        let employee = employee.ok_or(AppError::NotFound(format!("employee with id {} does not exist", id.id)))?;
+        employee.check()
+    }
 
-        let old_employee_salary = employee_salary.increase_by_percentage(&data)?;
-        sqlx::query(r#"UPDATE employees SET salary = $1 WHERE name = $2"#)
-            .bind(employee_salary.amount)
-            .bind(&data.name)
-            .execute(&mut *tx)
+    /// Получить список работающих сотрудников
+    ///
+    /// Возвращает страницу сотрудников, подходящих под фильтр, в заданном порядке
+    async fn list_employees(&self, query: EmployeeListQuery) -> Result<EmployeePage, AppError> {
+        let mut conn = self.acquire().await?;
+        let count = format!("SELECT COUNT(*) FROM employees WHERE {EMPLOYEE_FILTER}");
+        let (total,): (i64,) = statement("count_employees", bind_filter(sqlx::query_as(&count), &query.filter)
+            .fetch_one(&mut *conn))
+            .await?;
+        let order: &[&str] = match query.sort.field {
+            EmployeeSortField::Id => &["id"],
+            EmployeeSortField::Name => &["normalized_name", "id"],
+            EmployeeSortField::Surname => &["normalized_surname", "normalized_name", "id"],
+            EmployeeSortField::Salary => &["salary", "id"],
+        };
+        // Суммы в разных валютах несравнимы, поэтому зарплаты упорядочиваются внутри каждой валюты
+        let grouping = match query.sort.field {
+            EmployeeSortField::Salary => "currency ASC, ",
+            _ => "",
+        };
+        let direction = if query.sort.descending { "DESC" } else { "ASC" };
+        let order = order.iter()
+            .map(|column| format!("{column} {direction}"))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let select = format!("SELECT {EMPLOYEE_COLUMNS} FROM employees WHERE {EMPLOYEE_FILTER}
+                    ORDER BY {grouping}{order}
+                    LIMIT $7 OFFSET $8");
+        let employees: Vec<UncheckedEmployee> = statement("select_employees_page", bind_filter(sqlx::query_as(&select), &query.filter)
+            .bind(query.page.per_page)
+            .bind(query.page.offset())
+            .fetch_all(&mut *conn))
             .await?;
-        tx.commit().await?;
-        Ok(old_employee_salary)
+        let employees = employees.into_iter()
+            .map(|employee| employee.check())
+            .collect::<Result<Vec<_>, AppError>>()?;
+        Ok(EmployeePage{employees, page: query.page.page, per_page: query.page.per_page, total})
+    }
+
+    /// Найти сотрудников по похожему имени
+    ///
+    /// Сравнивает нормализованные имена по триграммам, поэтому находит имена с опечатками
+    /// и по отдельным словам. Кандидаты упорядочены от самого похожего, точное совпадение идет первым
+    async fn search_employees(&self, search: EmployeeSearch) -> Result<Vec<EmployeeMatch>, AppError> {
+        let mut conn = self.acquire().await?;
+        let matches: Vec<UncheckedEmployeeMatch> = statement("search_employees", sqlx::query_as(&format!("SELECT {EMPLOYEE_COLUMNS},
+                    GREATEST(similarity(normalized_name, $1), word_similarity($1, normalized_name)) AS score
+                    FROM employees
+                    WHERE terminated_at IS NULL AND (normalized_name % $1 OR $1 <% normalized_name)
+                    ORDER BY normalized_name = $1 DESC, score DESC, id
+                    LIMIT $2"))
+            .bind(&search.query)
+            .bind(search.limit)
+            .fetch_all(&mut *conn))
+            .await?;
+        matches.into_iter()
+            .map(|employee_match| employee_match.check())
+            .collect()
+    }
+
+    /// Получить журнал изменений зарплаты сотрудника
+    ///
+    /// Возвращает страницу записей от новых к старым. Журнал уволенных сотрудников тоже доступен.
+    /// Сотрудник не найден, только если нет ни записи о нем, ни журнала
+    async fn get_salary_history(&self, id: EmployeeId, page: Page) -> Result<SalaryHistoryPage, AppError> {
+        let mut conn = self.acquire().await?;
+        let (total,): (i64,) = statement("count_salary_history", sqlx::query_as(r#"SELECT COUNT(*) FROM salary_history WHERE employee_id = $1"#)
+            .bind(id.id)
+            .fetch_one(&mut *conn))
+            .await?;
+        if total == 0 {
+            let (exists,): (bool,) = statement("employee_exists", sqlx::query_as(r#"SELECT EXISTS(SELECT 1 FROM employees WHERE id = $1)"#)
+                .bind(id.id)
+                .fetch_one(&mut *conn))
+                .await?;
+            if !exists {
+                Err(AppError::NotFound(format!("employee with id {} does not exist", id.id)))?;
+            }
+        }
+        let entries: Vec<UncheckedSalaryHistoryEntry> = statement("select_salary_history", sqlx::query_as(r#"SELECT id, employee_id,
+                    (old_salary * 100)::BIGINT AS old_salary_minor, (new_salary * 100)::BIGINT AS new_salary_minor,
+                    currency::TEXT AS currency, operation, (percentage * 100)::INT AS percentage_hundredths,
+                    rounding, changed_at, actor
+                    FROM salary_history
+                    WHERE employee_id = $1
+                    ORDER BY id DESC
+                    LIMIT $2 OFFSET $3"#)
+            .bind(id.id)
+            .bind(page.per_page)
+            .bind(page.offset())
+            .fetch_all(&mut *conn))
+            .await?;
+        let entries = entries.into_iter()
+            .map(|entry| entry.check())
+            .collect::<Result<Vec<_>, AppError>>()?;
+        Ok(SalaryHistoryPage{entries, page: page.page, per_page: page.per_page, total})
+    }
+
+    /// Проверить, что база данных отвечает
+    async fn ping(&self) -> Result<(), AppError> {
+        let mut conn = self.acquire().await?;
+        statement("ping", conn.ping()).await?;
+        Ok(())
+    }
+
+    /// Состояние миграций схемы БД
+    async fn migration_status(&self) -> Result<MigrationStatus, AppError> {
+        let mut conn = self.acquire().await?;
+        migrations::status(&mut conn).await
+    }
+
+    /// Загруженность пула соединений
+    fn pool_status(&self) -> PoolStatus {
+        PoolStatus::new(
+            self.inner_client.size(),
+            self.inner_client.num_idle() as u32,
+            self.inner_client.options().get_max_connections(),
+        )
     }
 }
 
@@ -136,11 +644,17 @@ impl DBClient for DBClientPostgres{
 mod tests{
     use serial_test::serial;
     use super::*;
+    use crate::models::{PersonName, SalaryOperation, SalaryBatchItem, EmployeeSort};
+    use crate::money::{Currency, Money, Percentage, RoundingMode};
 
     fn set_env_vars(){
         dotenv::dotenv().ok();
     }
 
+    fn employee_data(name: &str, salary: Money) -> EmployeeData {
+        EmployeeData{name_parts: PersonName::parse_legacy(name), name: name.to_owned(), salary, personnel_number: None}
+    }
+
     #[actix_web::test]
     #[serial]
     async fn test_client_init_ok(){
@@ -149,13 +663,29 @@ mod tests{
         client.init_db_clear().await.unwrap();
     }
 
+    #[actix_web::test]
+    #[serial]
+    async fn test_client_health(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        client.ping().await.unwrap();
+        assert!(client.migration_status().await.unwrap().is_up_to_date());
+        let pool = client.pool_status();
+        assert!(pool.size >= 1);
+        assert_eq!(pool.size, pool.idle + pool.in_use);
+        assert_eq!(7, pool.max_connections);
+    }
+
     #[actix_web::test]
     #[serial]
     async fn test_employee_addition(){
         set_env_vars();
         let client = DBClientPostgres::new_test().await.unwrap();
         client.init_db_clear().await.unwrap();
-        client.add_new_employee(EmployeeData{name: "Test Employee".to_owned(), salary: 5000}).await.unwrap();
+        let employee = client.add_new_employee(employee_data("Test Employee", Money::new(500000, Currency::Rub))).await.unwrap();
+        assert_eq!("Test Employee", employee.name);
+        assert_eq!(500000, employee.salary.amount_minor);
     }
 
     #[actix_web::test]
@@ -164,9 +694,9 @@ mod tests{
         set_env_vars();
         let client = DBClientPostgres::new_test().await.unwrap();
         client.init_db_clear().await.unwrap();
-        client.add_new_employee(EmployeeData{name: "Test Employee".to_owned(), salary: 5000}).await.unwrap();
-        let salary = client.get_employee_salary(EmployeeName { name: "Test Employee".to_owned() }).await.unwrap();
-        assert_eq!(5000, salary.amount);
+        client.add_new_employee(employee_data("Test Employee", Money::new(500000, Currency::Rub))).await.unwrap();
+        let employee = client.get_employee_salary(EmployeeName { name: "Test Employee".to_owned() }).await.unwrap();
+        assert_eq!(500000, employee.salary.amount_minor);
     }
 
     #[actix_web::test]
@@ -175,10 +705,567 @@ mod tests{
         set_env_vars();
         let client = DBClientPostgres::new_test().await.unwrap();
         client.init_db_clear().await.unwrap();
-        client.add_new_employee(EmployeeData{name: "Test Employee".to_owned(), salary: 100}).await.unwrap();
-        let old_salary = client.increase_employee_salary(SalaryMultiplier { name: "Test Employee".to_owned(), percentage: 25 }).await.unwrap();
-        assert_eq!(100, old_salary.amount);
-        let salary = client.get_employee_salary(EmployeeName { name: "Test Employee".to_owned() }).await.unwrap();
-        assert_eq!(125, salary.amount);
+        client.add_new_employee(employee_data("Test Employee", Money::new(10000, Currency::Rub))).await.unwrap();
+        let increase = client.increase_employee_salary(SalaryMultiplier { name: "Test Employee".to_owned(), percentage: Percentage::whole(25), rounding: RoundingMode::Ceil }, None, "tester".to_owned()).await.unwrap();
+        assert_eq!(10000, increase.old_salary.amount_minor);
+        assert_eq!(12500, increase.employee.salary.amount_minor);
+        let employee = client.get_employee_salary(EmployeeName { name: "Test Employee".to_owned() }).await.unwrap();
+        assert_eq!(12500, employee.salary.amount_minor);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_by_id(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let added = client.add_new_employee(employee_data("Test Employee", Money::new(10000, Currency::Rub))).await.unwrap();
+        let id = EmployeeId{id: added.id};
+        assert_eq!(added, client.get_employee_by_id(id).await.unwrap());
+        let increase = client.increase_employee_salary_by_id(id, SalaryPercentage{percentage: Percentage::whole(50), rounding: RoundingMode::Ceil}, None, "tester".to_owned()).await.unwrap();
+        assert_eq!(10000, increase.old_salary.amount_minor);
+        assert_eq!(15000, increase.employee.salary.amount_minor);
+        let missing = client.get_employee_by_id(EmployeeId{id: added.id + 1}).await.unwrap_err();
+        assert!(matches!(missing, AppError::NotFound(_)));
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_ambiguous_name(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap()
+            .with_uniqueness_policy(UniquenessPolicy::PersonnelNumber);
+        client.init_db_clear().await.unwrap();
+        let first = client.add_new_employee(EmployeeData{personnel_number: Some("1".to_owned()), ..employee_data("Иван Иванов", Money::new(10000, Currency::Rub))}).await.unwrap();
+        let second = client.add_new_employee(EmployeeData{personnel_number: Some("2".to_owned()), ..employee_data("Иван Иванов", Money::new(20000, Currency::Rub))}).await.unwrap();
+        let error = client.get_employee_salary(EmployeeName{name: "Иван Иванов".to_owned()}).await.unwrap_err();
+        assert_eq!(AppError::Ambiguous{
+            message: "several employees are named \"Иван Иванов\", address them by id".to_owned(),
+            candidates: vec![first.id, second.id],
+        }, error);
+        let error = client.increase_employee_salary(SalaryMultiplier{name: "Иван Иванов".to_owned(), percentage: Percentage::whole(10), rounding: RoundingMode::Ceil}, None, "tester".to_owned()).await.unwrap_err();
+        assert!(matches!(error, AppError::Ambiguous{..}));
+        assert_eq!(10000, client.get_employee_by_id(EmployeeId{id: first.id}).await.unwrap().salary.amount_minor);
+        assert_eq!(20000, client.get_employee_by_id(EmployeeId{id: second.id}).await.unwrap().salary.amount_minor);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_name_uniqueness(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        client.add_new_employee(employee_data("Владимир Масленников", Money::new(10000, Currency::Rub))).await.unwrap();
+        let error = client.add_new_employee(employee_data("  владимир   МАСЛЕННИКОВ ", Money::new(20000, Currency::Rub))).await.unwrap_err();
+        assert_eq!(AppError::Conflict("employee with this name already exists".to_owned()), error);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_personnel_number_uniqueness(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap()
+            .with_uniqueness_policy(UniquenessPolicy::PersonnelNumber);
+        client.init_db_clear().await.unwrap();
+        client.add_new_employee(EmployeeData{personnel_number: Some("A-1".to_owned()), ..employee_data("Test Employee", Money::new(10000, Currency::Rub))}).await.unwrap();
+        let error = client.add_new_employee(EmployeeData{personnel_number: Some("A-1".to_owned()), ..employee_data("Other Employee", Money::new(10000, Currency::Rub))}).await.unwrap_err();
+        assert_eq!(AppError::Conflict("employee with this personnel number already exists".to_owned()), error);
+        let error = client.add_new_employee(employee_data("Other Employee", Money::new(10000, Currency::Rub))).await.unwrap_err();
+        assert!(matches!(error, AppError::Validation{field: "personnel_number", ..}));
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_name_uniqueness_conflicts(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap()
+            .with_uniqueness_policy(UniquenessPolicy::PersonnelNumber);
+        client.init_db_clear().await.unwrap();
+        let first = client.add_new_employee(EmployeeData{personnel_number: Some("1".to_owned()), ..employee_data("Иван Иванов", Money::new(10000, Currency::Rub))}).await.unwrap();
+        let second = client.add_new_employee(EmployeeData{personnel_number: Some("2".to_owned()), ..employee_data("иван  ИВАНОВ", Money::new(10000, Currency::Rub))}).await.unwrap();
+        client.add_new_employee(EmployeeData{personnel_number: Some("3".to_owned()), ..employee_data("Петр Петров", Money::new(10000, Currency::Rub))}).await.unwrap();
+
+        // Уникальный индекс по имени нельзя построить, пока имена повторяются
+        let client = client.with_uniqueness_policy(UniquenessPolicy::NormalizedName);
+        let error = client.init_db().await.unwrap_err();
+        assert_eq!(AppError::Conflict(format!("employees with ids [{}, {}] share names, rename or terminate them or use the personnel_number uniqueness policy", first.id, second.id)), error);
+    }
+
+    #[test]
+    fn uniqueness_policy_parse_test(){
+        assert_eq!(UniquenessPolicy::NormalizedName, "name".parse().unwrap());
+        assert_eq!(UniquenessPolicy::PersonnelNumber, "personnel_number".parse().unwrap());
+        assert!("surname".parse::<UniquenessPolicy>().is_err());
+    }
+
+    /// Удалить все таблицы, включая учет миграций
+    async fn drop_schema(client: &DBClientPostgres){
+        sqlx::query("DROP TABLE IF EXISTS employees, salary_history, _sqlx_migrations")
+            .execute(&client.inner_client)
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_migrations_preserve_legacy_rows(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        drop_schema(&client).await;
+        // Схема в том виде, в котором ее создавала версия без миграций
+        sqlx::query(r#"CREATE TABLE IF NOT EXISTS employees (id SERIAL PRIMARY KEY, name VARCHAR(255) NOT NULL, salary INT NOT NULL)"#)
+            .execute(&client.inner_client)
+            .await
+            .unwrap();
+        sqlx::query(r#"INSERT INTO employees(name, salary) VALUES ('Владимир Масленников', 5000), ('Abdula Ibn Nurahmat', 7000)"#)
+            .execute(&client.inner_client)
+            .await
+            .unwrap();
+
+        client.init_db().await.unwrap();
+        let status = client.migration_status().await.unwrap();
+        assert!(status.is_up_to_date());
+
+        let employee = client.get_employee_salary(EmployeeName{name: "Владимир Масленников".to_owned()}).await.unwrap();
+        assert_eq!(500000, employee.salary.amount_minor);
+        let employee = client.get_employee_salary(EmployeeName{name: "Abdula Ibn Nurahmat".to_owned()}).await.unwrap();
+        assert_eq!(700000, employee.salary.amount_minor);
+        // Нормализованные имена заполнены, поэтому политика уникальности действует и для старых записей
+        let error = client.add_new_employee(employee_data("владимир масленников", Money::new(10000, Currency::Rub))).await.unwrap_err();
+        assert!(matches!(error, AppError::Conflict(_)));
+
+        // Повторная инициализация ничего не меняет
+        client.init_db().await.unwrap();
+        assert_eq!(status, client.migration_status().await.unwrap());
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_migrations_check_mode(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap()
+            .with_migration_mode(MigrationMode::Check);
+        drop_schema(&client).await;
+        let status = client.migration_status().await.unwrap();
+        assert!(status.applied.is_empty());
+        assert_eq!(migrations::MIGRATOR.iter().count(), status.pending.len());
+        let error = client.init_db().await.unwrap_err();
+        assert!(matches!(error, AppError::Storage(_)));
+
+        let client = client.with_migration_mode(MigrationMode::Apply);
+        client.init_db().await.unwrap();
+        let client = client.with_migration_mode(MigrationMode::Check);
+        client.init_db().await.unwrap();
+
+        // Проверка не подстраивает индекс под другую политику уникальности
+        let client = client.with_uniqueness_policy(UniquenessPolicy::PersonnelNumber);
+        let error = client.init_db().await.unwrap_err();
+        assert!(matches!(error, AppError::Storage(_)));
+        let client = client.with_uniqueness_policy(UniquenessPolicy::NormalizedName);
+        client.init_db().await.unwrap();
+
+        // И не заполняет данные за миграции
+        sqlx::query(r#"INSERT INTO employees(name, salary) VALUES ('Владимир Масленников', 5000)"#)
+            .execute(&client.inner_client)
+            .await
+            .unwrap();
+        let error = client.init_db().await.unwrap_err();
+        assert!(matches!(error, AppError::Storage(_)));
+        let (unfilled,): (i64,) = sqlx::query_as(r#"SELECT COUNT(*) FROM employees WHERE normalized_name IS NULL"#)
+            .fetch_one(&client.inner_client)
+            .await
+            .unwrap();
+        assert_eq!(1, unfilled);
+        let client = client.with_migration_mode(MigrationMode::Apply);
+        client.init_db().await.unwrap();
+        let client = client.with_migration_mode(MigrationMode::Check);
+        client.init_db().await.unwrap();
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_salary_history(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let added = client.add_new_employee(employee_data("Test Employee", Money::new(10000, Currency::Rub))).await.unwrap();
+        let id = EmployeeId{id: added.id};
+        client.increase_employee_salary_by_id(id, SalaryPercentage{percentage: Percentage::whole(10), rounding: RoundingMode::Ceil}, None, "first".to_owned()).await.unwrap();
+        client.increase_employee_salary(SalaryMultiplier{name: "Test Employee".to_owned(), percentage: Percentage::whole(50), rounding: RoundingMode::Ceil}, None, "second".to_owned()).await.unwrap();
+        client.increase_employee_salary_by_id(id, SalaryPercentage{percentage: Percentage::whole(100), rounding: RoundingMode::Ceil}, None, "third".to_owned()).await.unwrap();
+
+        let history = client.get_salary_history(id, Page{page: 1, per_page: 2}).await.unwrap();
+        assert_eq!(3, history.total);
+        assert_eq!(2, history.entries.len());
+        assert_eq!((16500, 33000, Some(Percentage::whole(100)), "third"), (history.entries[0].old_salary.amount_minor, history.entries[0].new_salary.amount_minor, history.entries[0].percentage, &*history.entries[0].actor));
+        assert_eq!((11000, 16500, Some(Percentage::whole(50)), "second"), (history.entries[1].old_salary.amount_minor, history.entries[1].new_salary.amount_minor, history.entries[1].percentage, &*history.entries[1].actor));
+        assert_eq!(Currency::Rub, history.entries[0].new_salary.currency);
+        let history = client.get_salary_history(id, Page{page: 2, per_page: 2}).await.unwrap();
+        assert_eq!(1, history.entries.len());
+        assert_eq!((10000, 11000, "first"), (history.entries[0].old_salary.amount_minor, history.entries[0].new_salary.amount_minor, &*history.entries[0].actor));
+
+        // Журнал только пополняется
+        let error = sqlx::query("UPDATE salary_history SET new_salary = 1")
+            .execute(&client.inner_client)
+            .await;
+        assert!(error.is_err());
+        let error = sqlx::query("DELETE FROM salary_history")
+            .execute(&client.inner_client)
+            .await;
+        assert!(error.is_err());
+
+        // Неудачное повышение не оставляет записи в журнале
+        sqlx::query("UPDATE employees SET salary = 9999999999999999.99 WHERE id = $1")
+            .bind(id.id)
+            .execute(&client.inner_client)
+            .await
+            .unwrap();
+        client.increase_employee_salary_by_id(id, SalaryPercentage{percentage: Percentage::whole(10), rounding: RoundingMode::Ceil}, None, "fourth".to_owned()).await.unwrap_err();
+        assert_eq!(3, client.get_salary_history(id, Page{page: 1, per_page: 20}).await.unwrap().total);
+
+        let error = client.get_salary_history(EmployeeId{id: added.id + 1}, Page{page: 1, per_page: 20}).await.unwrap_err();
+        assert!(matches!(error, AppError::NotFound(_)));
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_money(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let salary = Money::new(999_999_999_999_999_999, Currency::Kzt);
+        let added = client.add_new_employee(employee_data("Test Employee", salary)).await.unwrap();
+        assert_eq!(salary, added.salary);
+        assert_eq!(salary, client.get_employee_by_id(EmployeeId{id: added.id}).await.unwrap().salary);
+
+        let salary = Money::new(1_000_001, Currency::Byn);
+        let added = client.add_new_employee(employee_data("Other Employee", salary)).await.unwrap();
+        let increase = client.increase_employee_salary_by_id(EmployeeId{id: added.id}, SalaryPercentage{percentage: Percentage::whole(10), rounding: RoundingMode::Ceil}, None, "tester".to_owned()).await.unwrap();
+        assert_eq!(salary, increase.old_salary);
+        // 10000.01 BYN * 1.1 = 11000.011 BYN, округляется вверх до копейки
+        assert_eq!(Money::new(1_100_002, Currency::Byn), increase.employee.salary);
+        let history = client.get_salary_history(EmployeeId{id: added.id}, Page{page: 1, per_page: 20}).await.unwrap();
+        assert_eq!(Money::new(1_100_002, Currency::Byn), history.entries[0].new_salary);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_fractional_percentage(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let added = client.add_new_employee(employee_data("Test Employee", Money::new(100_001, Currency::Rub))).await.unwrap();
+        let id = EmployeeId{id: added.id};
+        // 1000.01 * 1.035 = 1035.01035
+        let increase = client.increase_employee_salary_by_id(id, SalaryPercentage{percentage: Percentage::new(350), rounding: RoundingMode::HalfEven}, None, "tester".to_owned()).await.unwrap();
+        assert_eq!(103_501, increase.employee.salary.amount_minor);
+        let history = client.get_salary_history(id, Page{page: 1, per_page: 20}).await.unwrap();
+        assert_eq!((Some(Percentage::new(350)), Some(RoundingMode::HalfEven)), (history.entries[0].percentage, history.entries[0].rounding));
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_salary_batch(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let first = client.add_new_employee(employee_data("First Employee", Money::new(10000, Currency::Rub))).await.unwrap();
+        let second = client.add_new_employee(employee_data("Second Employee", Money::new(20000, Currency::Rub))).await.unwrap();
+        let third = client.add_new_employee(employee_data("Third Employee", Money::new(30000, Currency::Kzt))).await.unwrap();
+        let item = |id: i32, percent: i32| SalaryBatchItem{id: EmployeeId{id}, percentage: SalaryPercentage{percentage: Percentage::whole(percent), rounding: RoundingMode::Ceil}};
+        let missing = third.id + 1;
+
+        // Атомарный пакет с несуществующим сотрудником не меняет ничего
+        let batch = SalaryBatch{mode: BatchMode::Atomic, target: SalaryBatchTarget::Items(vec![item(first.id, 10), item(missing, 10)])};
+        let error = client.increase_salaries(batch, None, "batch".to_owned()).await.unwrap_err();
+        assert_eq!(AppError::NotFound(format!("employee {missing}: employee with id {missing} does not exist")), error);
+        assert_eq!(10000, client.get_employee_by_id(EmployeeId{id: first.id}).await.unwrap().salary.amount_minor);
+        assert_eq!(0, client.get_salary_history(EmployeeId{id: first.id}, Page{page: 1, per_page: 20}).await.unwrap().total);
+
+        // В режиме best effort ошибка одного сотрудника не мешает остальным
+        let batch = SalaryBatch{mode: BatchMode::BestEffort, target: SalaryBatchTarget::Items(vec![item(second.id, 50), item(missing, 10), item(first.id, 10)])};
+        let report = client.increase_salaries(batch, None, "batch".to_owned()).await.unwrap();
+        assert_eq!((2, 1), (report.applied, report.failed));
+        assert_eq!(vec![second.id, missing, first.id], report.items.iter().map(|item| item.id).collect::<Vec<_>>());
+        assert_eq!("not_found", report.items[1].error.as_ref().unwrap().code);
+        assert_eq!(30000, client.get_employee_by_id(EmployeeId{id: second.id}).await.unwrap().salary.amount_minor);
+        assert_eq!(11000, client.get_employee_by_id(EmployeeId{id: first.id}).await.unwrap().salary.amount_minor);
+
+        // Фильтр выбирает рублевые зарплаты не выше 200 рублей
+        let filter = EmployeeFilter{currency: Some(Currency::Rub), max_salary: Some(Money::new(20000, Currency::Rub)), ..Default::default()};
+        let batch = SalaryBatch{mode: BatchMode::Atomic, target: SalaryBatchTarget::Filter{percentage: SalaryPercentage{percentage: Percentage::whole(100), rounding: RoundingMode::Ceil}, filter}};
+        let report = client.increase_salaries(batch, None, "batch".to_owned()).await.unwrap();
+        assert_eq!(vec![first.id], report.items.iter().map(|item| item.id).collect::<Vec<_>>());
+        assert_eq!(22000, client.get_employee_by_id(EmployeeId{id: first.id}).await.unwrap().salary.amount_minor);
+        assert_eq!(30000, client.get_employee_by_id(EmployeeId{id: third.id}).await.unwrap().salary.amount_minor);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_lifecycle(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let added = client.add_new_employee(employee_data("Мария Иванова", Money::new(10000, Currency::Rub))).await.unwrap();
+        let other = client.add_new_employee(employee_data("Other Employee", Money::new(10000, Currency::Rub))).await.unwrap();
+        let id = EmployeeId{id: added.id};
+
+        let renamed = client.update_employee(id, EmployeeUpdate{name: Some("Мария Петрова".to_owned()), name_parts: Some(PersonName::parse_legacy("Мария Петрова")), personnel_number: None}).await.unwrap();
+        assert_eq!("Мария Петрова", renamed.name);
+        assert_eq!(renamed, client.get_employee_salary(EmployeeName{name: "Мария Петрова".to_owned()}).await.unwrap());
+        let error = client.update_employee(id, EmployeeUpdate{name: Some("other employee".to_owned()), name_parts: Some(PersonName::parse_legacy("other employee")), personnel_number: None}).await.unwrap_err();
+        assert_eq!(AppError::Conflict("employee with this name already exists".to_owned()), error);
+
+        client.increase_employee_salary_by_id(id, SalaryPercentage{percentage: Percentage::whole(10), rounding: RoundingMode::Ceil}, None, "tester".to_owned()).await.unwrap();
+        let termination = client.terminate_employee(id).await.unwrap();
+        assert_eq!(renamed.name, termination.employee.name);
+
+        // Уволенный сотрудник пропадает из обычных запросов, но его журнал доступен
+        assert!(matches!(client.get_employee_by_id(id).await.unwrap_err(), AppError::NotFound(_)));
+        assert!(matches!(client.get_employee_salary(EmployeeName{name: "Мария Петрова".to_owned()}).await.unwrap_err(), AppError::NotFound(_)));
+        assert!(matches!(client.terminate_employee(id).await.unwrap_err(), AppError::NotFound(_)));
+        assert!(matches!(client.update_employee(id, EmployeeUpdate{name: Some("x".to_owned()), name_parts: Some(PersonName::parse_legacy("x")), personnel_number: None}).await.unwrap_err(), AppError::NotFound(_)));
+        let error = client.adjust_employee_salary(id, SalaryAdjustment::Set{amount_minor: 1, currency: None}, None, "tester".to_owned()).await.unwrap_err();
+        assert!(matches!(error, AppError::NotFound(_)));
+        assert_eq!(1, client.get_salary_history(id, Page{page: 1, per_page: 20}).await.unwrap().total);
+
+        // Имя уволенного сотрудника можно занять снова
+        client.add_new_employee(employee_data("Мария Петрова", Money::new(10000, Currency::Rub))).await.unwrap();
+
+        let deleted = client.delete_employee(id).await.unwrap();
+        assert_eq!(11000, deleted.salary.amount_minor);
+        assert!(matches!(client.delete_employee(id).await.unwrap_err(), AppError::NotFound(_)));
+        // Журнал удаленного сотрудника остается доступен для проверки
+        let history = client.get_salary_history(id, Page{page: 1, per_page: 20}).await.unwrap();
+        assert_eq!((1, "tester"), (history.total, &*history.entries[0].actor));
+        // Без записи и без журнала сотрудник не найден
+        client.delete_employee(EmployeeId{id: other.id}).await.unwrap();
+        assert!(matches!(client.get_salary_history(EmployeeId{id: other.id}, Page{page: 1, per_page: 20}).await.unwrap_err(), AppError::NotFound(_)));
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_list(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        for (name, salary, currency) in [
+            ("Иван Петров", 30000, Currency::Rub),
+            ("иван сидоров", 10000, Currency::Rub),
+            ("Пётр Иванов", 20000, Currency::Rub),
+            ("Иван_Казахов", 50000, Currency::Kzt),
+        ] {
+            client.add_new_employee(employee_data(name, Money::new(salary, currency))).await.unwrap();
+        }
+        let terminated = client.add_new_employee(employee_data("Иван Уволенный", Money::new(1, Currency::Rub))).await.unwrap();
+        client.terminate_employee(EmployeeId{id: terminated.id}).await.unwrap();
+        let names = |page: &EmployeePage| page.employees.iter().map(|employee| employee.name.clone()).collect::<Vec<String>>();
+
+        let all = client.list_employees(EmployeeListQuery{filter: EmployeeFilter::default(), sort: EmployeeSort::default(), page: Page{page: 1, per_page: 20}}).await.unwrap();
+        assert_eq!(4, all.total);
+        assert_eq!(vec!["Иван Петров", "иван сидоров", "Пётр Иванов", "Иван_Казахов"], names(&all));
+
+        let sort = EmployeeSort{field: EmployeeSortField::Salary, descending: true};
+        let filter = EmployeeFilter{currency: Some(Currency::Rub), ..Default::default()};
+        let page = client.list_employees(EmployeeListQuery{filter: filter.clone(), sort, page: Page{page: 1, per_page: 2}}).await.unwrap();
+        assert_eq!(3, page.total);
+        assert_eq!(vec!["Иван Петров", "Пётр Иванов"], names(&page));
+        let page = client.list_employees(EmployeeListQuery{filter, sort, page: Page{page: 2, per_page: 2}}).await.unwrap();
+        assert_eq!(vec!["иван сидоров"], names(&page));
+
+        // Без фильтра по валюте зарплаты сравниваются только в пределах одной валюты
+        let sort = EmployeeSort{field: EmployeeSortField::Salary, descending: false};
+        let page = client.list_employees(EmployeeListQuery{filter: EmployeeFilter::default(), sort, page: Page{page: 1, per_page: 20}}).await.unwrap();
+        assert_eq!(vec!["Иван_Казахов", "иван сидоров", "Пётр Иванов", "Иван Петров"], names(&page));
+
+        let filter = EmployeeFilter{name_prefix: Some("иван ".to_owned()), ..Default::default()};
+        let sort = EmployeeSort{field: EmployeeSortField::Name, descending: false};
+        let page = client.list_employees(EmployeeListQuery{filter, sort, page: Page{page: 1, per_page: 20}}).await.unwrap();
+        assert_eq!(vec!["Иван Петров", "иван сидоров"], names(&page));
+
+        // Подчеркивание в начале имени не считается шаблоном LIKE
+        let filter = EmployeeFilter{name_prefix: Some("иван_".to_owned()), ..Default::default()};
+        let page = client.list_employees(EmployeeListQuery{filter, sort, page: Page{page: 1, per_page: 20}}).await.unwrap();
+        assert_eq!(vec!["Иван_Казахов"], names(&page));
+
+        let filter = EmployeeFilter{currency: Some(Currency::Rub), min_salary: Some(Money::new(15000, Currency::Rub)), max_salary: Some(Money::new(30000, Currency::Rub)), ..Default::default()};
+        let page = client.list_employees(EmployeeListQuery{filter, sort, page: Page{page: 1, per_page: 20}}).await.unwrap();
+        assert_eq!(vec!["Иван Петров", "Пётр Иванов"], names(&page));
+
+        // Обход по ключу начинается после заданного сотрудника
+        let filter = EmployeeFilter{after_id: Some(all.employees[1].id), ..Default::default()};
+        let page = client.list_employees(EmployeeListQuery{filter, sort: EmployeeSort::default(), page: Page{page: 1, per_page: 20}}).await.unwrap();
+        assert_eq!(2, page.total);
+        assert_eq!(vec!["Пётр Иванов", "Иван_Казахов"], names(&page));
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_structured_names(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let parts = PersonName{surname: "Иванова".to_owned(), given_name: Some("Мария".to_owned()), patronymic: Some("Петровна".to_owned()), display_name: Some("Мария Иванова".to_owned())};
+        let maria = client.add_new_employee(EmployeeData{name: parts.display(), name_parts: parts.clone(), salary: Money::new(10000, Currency::Rub), personnel_number: None}).await.unwrap();
+        assert_eq!(parts, maria.name_parts);
+        let vladimir = client.add_new_employee(employee_data("Владимир Евгеньевич Масленников", Money::new(10000, Currency::Rub))).await.unwrap();
+        assert_eq!("Масленников", vladimir.name_parts.surname);
+
+        // Записи, добавленные до появления частей имени, разбираются при старте
+        sqlx::query(r#"INSERT INTO employees(name, normalized_name, salary, currency) VALUES ('Абрамов Пётр', 'абрамов пётр', 100, 'RUB')"#)
+            .execute(&client.inner_client)
+            .await
+            .unwrap();
+        client.init_db().await.unwrap();
+
+        let surnames = |page: &EmployeePage| page.employees.iter().map(|employee| employee.name_parts.surname.clone()).collect::<Vec<String>>();
+        let sort = EmployeeSort{field: EmployeeSortField::Surname, descending: false};
+        let page = client.list_employees(EmployeeListQuery{filter: EmployeeFilter::default(), sort, page: Page{page: 1, per_page: 20}}).await.unwrap();
+        assert_eq!(vec!["Абрамов", "Иванова", "Масленников"], surnames(&page));
+        assert_eq!(Some("Пётр".to_owned()), page.employees[0].name_parts.given_name);
+
+        let filter = EmployeeFilter{surname_prefix: Some("иванов".to_owned()), ..Default::default()};
+        let page = client.list_employees(EmployeeListQuery{filter, sort, page: Page{page: 1, per_page: 20}}).await.unwrap();
+        assert_eq!(vec![maria.id], page.employees.iter().map(|employee| employee.id).collect::<Vec<i32>>());
+
+        // Переименование заменяет все части имени
+        let update = EmployeeUpdate{name: Some("Петрова Мария".to_owned()), name_parts: Some(PersonName::parse_legacy("Петрова Мария")), personnel_number: None};
+        let renamed = client.update_employee(EmployeeId{id: maria.id}, update).await.unwrap();
+        assert_eq!(PersonName::parse_legacy("Петрова Мария"), renamed.name_parts);
+        assert_eq!(None, renamed.name_parts.patronymic);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_search(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let vladimir = client.add_new_employee(employee_data("Владимир Масленников", Money::new(10000, Currency::Rub))).await.unwrap();
+        let vladislav = client.add_new_employee(employee_data("Владислав Масленников", Money::new(10000, Currency::Rub))).await.unwrap();
+        let ivan = client.add_new_employee(employee_data("Ivan Petrov", Money::new(10000, Currency::Rub))).await.unwrap();
+
+        // Точный поиск не зависит от регистра и лишних пробелов
+        let found = client.get_employee_salary(EmployeeName{name: "  владимир   МАСЛЕННИКОВ ".to_owned()}).await.unwrap();
+        assert_eq!(vladimir.id, found.id);
+
+        let ids = |matches: &[EmployeeMatch]| matches.iter().map(|employee_match| employee_match.employee.id).collect::<Vec<_>>();
+        let matches = client.search_employees(EmployeeSearch{query: "владимир масленников".to_owned(), limit: 10}).await.unwrap();
+        assert_eq!(vec![vladimir.id, vladislav.id], ids(&matches));
+        assert_eq!(1.0, matches[0].score);
+        assert!(matches[1].score < 1.0);
+
+        // Опечатка и поиск по одному слову
+        let matches = client.search_employees(EmployeeSearch{query: "владимр масленников".to_owned(), limit: 10}).await.unwrap();
+        assert_eq!(vladimir.id, matches[0].employee.id);
+        let matches = client.search_employees(EmployeeSearch{query: "масленников".to_owned(), limit: 1}).await.unwrap();
+        assert_eq!(1, matches.len());
+        let matches = client.search_employees(EmployeeSearch{query: "ivn petrov".to_owned(), limit: 10}).await.unwrap();
+        assert_eq!(vec![ivan.id], ids(&matches));
+        assert!(client.search_employees(EmployeeSearch{query: "совсем другой".to_owned(), limit: 10}).await.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_salary_adjustment(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let added = client.add_new_employee(employee_data("Test Employee", Money::new(100_000, Currency::Kzt))).await.unwrap();
+        let id = EmployeeId{id: added.id};
+
+        let change = client.adjust_employee_salary(id, SalaryAdjustment::Percentage{percentage: Percentage::whole(-10), rounding: RoundingMode::Floor}, None, "cut".to_owned()).await.unwrap();
+        assert_eq!((Money::new(100_000, Currency::Kzt), Money::new(90_000, Currency::Kzt)), (change.old_salary, change.new_salary));
+        let change = client.adjust_employee_salary(id, SalaryAdjustment::Delta{amount_minor: -150, currency: Some(Currency::Kzt)}, None, "delta".to_owned()).await.unwrap();
+        assert_eq!(89_850, change.new_salary.amount_minor);
+        let change = client.adjust_employee_salary(id, SalaryAdjustment::Set{amount_minor: 120_000, currency: None}, None, "set".to_owned()).await.unwrap();
+        assert_eq!((89_850, 120_000), (change.old_salary.amount_minor, change.employee.salary.amount_minor));
+
+        // Изменения, после которых зарплата не положительна или в другой валюте, отклоняются и не попадают в журнал
+        let error = client.adjust_employee_salary(id, SalaryAdjustment::Delta{amount_minor: -120_000, currency: None}, None, "delta".to_owned()).await.unwrap_err();
+        assert!(matches!(error, AppError::Validation{field: "value", ..}));
+        let error = client.adjust_employee_salary(id, SalaryAdjustment::Set{amount_minor: 100, currency: Some(Currency::Rub)}, None, "set".to_owned()).await.unwrap_err();
+        assert!(matches!(error, AppError::Validation{field: "currency", ..}));
+        // Предел повышения сравнивается с зарплатой, заблокированной в транзакции изменения
+        let limit = RaiseLimit{principal: "hr".to_owned(), max_raise: Percentage::whole(10)};
+        let error = client.adjust_employee_salary(id, SalaryAdjustment::Delta{amount_minor: 12_001, currency: None}, Some(limit), "delta".to_owned()).await.unwrap_err();
+        assert!(matches!(error, AppError::Forbidden(_)));
+        assert_eq!(120_000, client.get_employee_by_id(id).await.unwrap().salary.amount_minor);
+
+        let history = client.get_salary_history(id, Page{page: 1, per_page: 20}).await.unwrap();
+        assert_eq!(3, history.total);
+        assert_eq!((SalaryOperation::Set, None, None), (history.entries[0].operation, history.entries[0].percentage, history.entries[0].rounding));
+        assert_eq!(SalaryOperation::Delta, history.entries[1].operation);
+        assert_eq!((SalaryOperation::Percentage, Some(Percentage::whole(-10))), (history.entries[2].operation, history.entries[2].percentage));
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_raise_limit_rounding(){
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let first = client.add_new_employee(employee_data("First Employee", Money::new(10001, Currency::Rub))).await.unwrap();
+        let second = client.add_new_employee(employee_data("Second Employee", Money::new(20003, Currency::Rub))).await.unwrap();
+        let third = client.add_new_employee(employee_data("Third Employee", Money::new(1, Currency::Rub))).await.unwrap();
+        let limit = RaiseLimit{principal: "hr".to_owned(), max_raise: Percentage::whole(10)};
+        let percentage = |percent| SalaryPercentage{percentage: Percentage::whole(percent), rounding: RoundingMode::Ceil};
+
+        // Повышение ровно на предел проходит, даже если округление вверх дает чуть больше 10%
+        let change = client.increase_employee_salary_by_id(EmployeeId{id: first.id}, percentage(10), Some(limit.clone()), "hr".to_owned()).await.unwrap();
+        assert_eq!((10001, 11002), (change.old_salary.amount_minor, change.new_salary.amount_minor));
+        let multiplier = SalaryMultiplier{name: "Second Employee".to_owned(), percentage: Percentage::whole(10), rounding: RoundingMode::Ceil};
+        let change = client.increase_employee_salary(multiplier, Some(limit.clone()), "hr".to_owned()).await.unwrap();
+        assert_eq!(22004, change.new_salary.amount_minor);
+
+        // Атомарный пакет индексации на 10% применяется ко всем сотрудникам
+        let item = |id: i32| SalaryBatchItem{id: EmployeeId{id}, percentage: percentage(10)};
+        let batch = SalaryBatch{mode: BatchMode::Atomic, target: SalaryBatchTarget::Items(vec![item(first.id), item(second.id), item(third.id)])};
+        let report = client.increase_salaries(batch, Some(limit.clone()), "hr".to_owned()).await.unwrap();
+        assert_eq!((3, 0), (report.applied, report.failed));
+        let salaries = [first.id, second.id, third.id].map(|id| report.items.iter().find(|item| item.id == id).unwrap().change.as_ref().unwrap().new_salary.amount_minor);
+        assert_eq!([12103, 24205, 2], salaries);
+
+        // Повышение больше предела отклоняется и не попадает в журнал
+        let error = client.increase_employee_salary_by_id(EmployeeId{id: first.id}, percentage(11), Some(limit), "hr".to_owned()).await.unwrap_err();
+        assert!(matches!(error, AppError::Forbidden(_)));
+        assert_eq!(12103, client.get_employee_by_id(EmployeeId{id: first.id}).await.unwrap().salary.amount_minor);
+        assert_eq!(2, client.get_salary_history(EmployeeId{id: first.id}, Page{page: 1, per_page: 20}).await.unwrap().total);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_statement_spans(){
+        use std::sync::Arc;
+        use opentelemetry::trace::{FutureExt, TraceContextExt, Tracer as _};
+        use opentelemetry::Context;
+        use crate::telemetry::{SpanBuffer, Telemetry, TracedDBClient};
+
+        set_env_vars();
+        let client = DBClientPostgres::new_test().await.unwrap();
+        client.init_db_clear().await.unwrap();
+        let added = client.add_new_employee(employee_data("Test Employee", Money::new(10000, Currency::Rub))).await.unwrap();
+        let max_connections = client.pool_status().max_connections;
+        let buffer = SpanBuffer::default();
+        let telemetry = Telemetry::with_writer(buffer.clone());
+        let client = TracedDBClient::new(Arc::new(client), telemetry.tracer());
+
+        let cx = Context::current_with_span(telemetry.tracer().start("request"));
+        client.increase_employee_salary_by_id(EmployeeId{id: added.id}, SalaryPercentage{percentage: Percentage::whole(10), rounding: RoundingMode::Ceil}, None, "Test Actor".to_owned())
+            .with_context(cx.clone())
+            .await
+            .unwrap();
+        cx.span().end();
+
+        // Ожидание пула и каждый запрос повышения видны отдельными спанами внутри спана метода
+        let spans = buffer.spans();
+        let names: Vec<&str> = spans.iter().map(|span| span["name"].as_str().unwrap()).collect();
+        assert_eq!(vec!["pool.begin", "lock_employee_salary", "update_employee_salary", "insert_salary_history", "commit", "increase_employee_salary_by_id", "request"], names);
+        let method = &spans[5];
+        for span in &spans[..5] {
+            assert_eq!(method["span_id"], span["parent_span_id"]);
+        }
+        assert_eq!("update_employee_salary", spans[2]["attributes"]["db.statement.name"]);
+        assert_eq!(max_connections, spans[0]["attributes"]["db.client.connection.pool.max"]);
     }
 }
@@ -1,12 +1,24 @@
-use actix_web::dev::ServiceResponse;
-use actix_web::{get, put, post, App, HttpServer, Responder, HttpResponse, web};
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::error::{JsonPayloadError, UrlencodedError};
+use actix_web::{get, put, post, patch, delete, App, HttpServer, HttpResponse, Scope, web};
 use std::sync::Arc;
 use super::postgres_client::{DBClientPostgres, DBClient, MockDBClient};
-use super::models::{UncheckedEmployeeName, UncheckedSalaryMultiplier, UncheckedEmployeeData, EmployeeSalary};
+use super::models::{normalize_name, PersonName, UncheckedEmployeeName, UncheckedSalaryMultiplier, UncheckedEmployeeData, UncheckedEmployeeId, EmployeeId, UncheckedSalaryPercentage, UncheckedSalaryAdjustment, UncheckedSalaryBatch, UncheckedEmployeeUpdate, UncheckedEmployeeListQuery, UncheckedEmployeeSearch, UncheckedPage, EmployeeSalary, Employee, SalaryChange, SalaryHistoryEntry, SalaryHistoryPage, SalaryOperation, SalaryBatchTarget, SalaryBatchReport, SalaryBatchItemReport, BatchMode, EmployeeTermination, EmployeePage, EmployeeMatch, LivenessReport, ReadinessReport, PoolStatus, ApiResponse, ErrorResponse};
+use super::config::Config;
+use super::errors::AppError;
+use super::access::{AccessPolicy, Permission};
+use super::auth::{self, Authenticator, Principal};
+use super::migrations::MigrationStatus;
+use super::health::{check_readiness, shutdown_on_signal, Readiness};
+use super::logging;
+use super::metrics::{self, MeteredDBClient, Metrics};
+use super::telemetry::{self, Telemetry, TracedDBClient};
+use super::names::NamePolicy;
+use super::openapi;
+use super::params::Params;
+use super::money::{Currency, Money, Percentage, RoundingMode};
 use std::error::Error;
-use log::{info, error};
-use simplelog::{CombinedLogger, Config, LevelFilter, WriteLogger};
-use std::fs::File;
+use log::{info, warn, error};
 
 
 // Эндпоинты приложения
@@ -14,77 +26,602 @@ use std::fs::File;
 /// Получить зарплату работника по имени
 ///
 /// Пример: /salary?name="Василий Петрович"
+#[utoipa::path(
+    get,
+    path = "/salary",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeName),
+    responses(
+        (status = 200, description = "Сотрудник с зарплатой", body = ApiResponse<Employee>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 409, description = "Имя соответствует нескольким сотрудникам", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
 #[get("/salary")]
-async fn get_employee_salary(query: web::Query<UncheckedEmployeeName>, db_client: web::Data<dyn DBClient>) -> impl Responder {
-    // let db_client = db_client.lock().unwrap();
-    let employee_name = match query.into_inner().check(){
-        Ok(name) => name,
-        Err(e) => {
-            error!("Bad request: {e}");
-            return HttpResponse::BadRequest().body(format!("{e}"))
-        }
-    };
-    match db_client.get_employee_salary(employee_name.clone()).await {
-        Ok(salary) => {
-            info!("Sent salary of employee with name {:?}", employee_name);
-            HttpResponse::Ok().body(format!("{}", salary.amount))
-        },
-        Err(e) => {
-            error!("Internal error: {e}");
-            HttpResponse::BadRequest().body(format!("{e}"))
-        }
-    }
+async fn get_employee_salary(principal: Principal, policy: web::Data<AccessPolicy>, query: web::Query<UncheckedEmployeeName>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    policy.check_salary_search(&principal)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let employee_name = query.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let found = db_client.get_employee_salary(employee_name.clone()).await
+        .inspect_err(|e| error!("Request failed: {e}"));
+    let employee = policy.check_salary_found(&principal, found)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    info!("Sent salary of employee with name {:?}", employee_name);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(employee)))
 }
 
 
 /// Добавить нового работника
 ///
 /// Пример: /add?name="Василий Петрович"&salary=8000
+#[utoipa::path(
+    put,
+    path = "/add",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeData),
+    request_body(description = "Параметры в теле вместо строки запроса", content((Option<UncheckedEmployeeData> = "application/json"), (Option<UncheckedEmployeeData> = "application/x-www-form-urlencoded"))),
+    responses(
+        (status = 200, description = "Добавленный сотрудник", body = ApiResponse<Employee>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 409, description = "Сотрудник уже существует", body = ErrorResponse),
+        (status = 415, description = "Тело в неподдерживаемом формате", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
 #[put("/add")]
-async fn add_new_employee(query: web::Query<UncheckedEmployeeData>, db_client: web::Data<dyn DBClient>) -> impl Responder {
-    // let db_client = db_client.lock().unwrap();
-    let employee_data = match query.into_inner().check(){
-        Ok(data) => data,
-        Err(e) => {
-            error!("Bad Request: {e}");
-            return HttpResponse::BadRequest().body(format!("{e}"))
-        }
-    };
-    match db_client.add_new_employee(employee_data.clone()).await {
-        Ok(_) => {
-            info!{"Added new employee: {:?}", employee_data};
-            HttpResponse::Ok().body("Successfully added new employee".to_string())
-        },
-        Err(e) => {
-            error!("Internal error: {e}");
-            HttpResponse::BadRequest().body(format!("{e}"))
-        }
-    }
+async fn add_new_employee(principal: Principal, params: Params<UncheckedEmployeeData>, name_policy: web::Data<NamePolicy>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::ManageEmployees)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let employee_data = params.into_inner().check_with(&name_policy)
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let employee = db_client.add_new_employee(employee_data).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!{"Added new employee: {:?}", employee};
+    Ok(HttpResponse::Ok().json(ApiResponse::new(employee)))
 }
 
 
 /// Увеличить зарплату сотруднику
 ///
-/// Пример: /increase?name="Василий Петрович"&percentage=20
+/// Пример: /increase?name="Василий Петрович"&percentage=3.5&rounding=half_even
+#[utoipa::path(
+    post,
+    path = "/increase",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedSalaryMultiplier),
+    request_body(description = "Параметры в теле вместо строки запроса", content((Option<UncheckedSalaryMultiplier> = "application/json"), (Option<UncheckedSalaryMultiplier> = "application/x-www-form-urlencoded"))),
+    responses(
+        (status = 200, description = "Изменение зарплаты", body = ApiResponse<SalaryChange>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 409, description = "Имя соответствует нескольким сотрудникам", body = ErrorResponse),
+        (status = 415, description = "Тело в неподдерживаемом формате", body = ErrorResponse),
+        (status = 422, description = "Зарплата не помещается в колонку", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
 #[post("/increase")]
-async fn increase_employee_salary(query: web::Query<UncheckedSalaryMultiplier>, db_client: web::Data<dyn DBClient>) -> impl Responder {
-    // let db_client = db_client.lock().unwrap();
-    let salary_multiplier = match query.into_inner().check(){
-        Ok(multiplier) => multiplier,
-        Err(e) => {
-            error!{"Bad Request: {e}"};
-            return HttpResponse::BadRequest().body(format!("{e}"))
-        }
-    };
-    match db_client.increase_employee_salary(salary_multiplier.clone()).await {
-        Ok(old_salary) => {
-            info!("Increased the salary with data {:?}", salary_multiplier);
-            HttpResponse::Ok().body(format!("{}", old_salary.amount))
-        },
-        Err(e) => {
-            error!("Internal error: {e}");
-            HttpResponse::BadRequest().body(format!("{e}"))
-        }
+async fn increase_employee_salary(principal: Principal, policy: web::Data<AccessPolicy>, params: Params<UncheckedSalaryMultiplier>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::ChangeSalaries)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let salary_multiplier = params.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let limit = policy.check_raise(&principal, &salary_multiplier.percentage)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let increase = db_client.increase_employee_salary(salary_multiplier.clone(), limit, principal.name).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Increased the salary with data {:?}", salary_multiplier);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(increase)))
+}
+
+
+/// Получить список сотрудников
+///
+/// Пример: ?page=2&per_page=50&sort=-salary&name_prefix=Иван&currency=RUB&min_salary=30000
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeListQuery),
+    responses(
+        (status = 200, description = "Страница сотрудников", body = ApiResponse<EmployeePage>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[get("")]
+async fn list_employees(principal: Principal, query: web::Query<UncheckedEmployeeListQuery>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::ReadSalaries)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let list_query = query.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let page = db_client.list_employees(list_query.clone()).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Sent list of employees for {:?}", list_query);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(page)))
+}
+
+
+/// Найти сотрудников по похожему имени
+///
+/// Пример: /search?q="владимир масленников"&limit=5
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeSearch),
+    responses(
+        (status = 200, description = "Кандидаты по убыванию сходства", body = ApiResponse<Vec<EmployeeMatch>>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[get("/search")]
+async fn search_employees(principal: Principal, query: web::Query<UncheckedEmployeeSearch>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::ReadSalaries)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let search = query.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let matches = db_client.search_employees(search.clone()).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Found {} employees for {:?}", matches.len(), search);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(matches)))
+}
+
+
+/// Получить запись о сотруднике по идентификатору
+///
+/// Пример: /17
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeId),
+    responses(
+        (status = 200, description = "Сотрудник", body = ApiResponse<Employee>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[get("/{id:\\d+}")]
+async fn get_employee_by_id(principal: Principal, policy: web::Data<AccessPolicy>, path: web::Path<UncheckedEmployeeId>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    let employee_id = path.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    policy.check_salary_read(&principal, employee_id.id)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let employee = db_client.get_employee_by_id(employee_id).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Sent employee with id {}", employee_id.id);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(employee)))
+}
+
+
+/// Изменить имя или табельный номер сотрудника
+///
+/// Пример: /17?name="Василий Петрович Иванов"
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeId, UncheckedEmployeeUpdate),
+    request_body(description = "Параметры в теле вместо строки запроса", content((Option<UncheckedEmployeeUpdate> = "application/json"), (Option<UncheckedEmployeeUpdate> = "application/x-www-form-urlencoded"))),
+    responses(
+        (status = 200, description = "Обновленный сотрудник", body = ApiResponse<Employee>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 409, description = "Сотрудник уже существует", body = ErrorResponse),
+        (status = 415, description = "Тело в неподдерживаемом формате", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[patch("/{id:\\d+}")]
+async fn update_employee(principal: Principal, path: web::Path<UncheckedEmployeeId>, params: Params<UncheckedEmployeeUpdate>, name_policy: web::Data<NamePolicy>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::ManageEmployees)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let employee_id = path.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let update = params.into_inner().check_with(&name_policy)
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let employee = db_client.update_employee(employee_id, update).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Updated employee: {:?}", employee);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(employee)))
+}
+
+
+/// Уволить сотрудника
+///
+/// Пример: DELETE /17
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeId),
+    responses(
+        (status = 200, description = "Уволенный сотрудник", body = ApiResponse<EmployeeTermination>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[delete("/{id:\\d+}")]
+async fn terminate_employee(principal: Principal, path: web::Path<UncheckedEmployeeId>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::ManageEmployees)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let employee_id = path.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let termination = db_client.terminate_employee(employee_id).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Terminated employee with id {} at {}", employee_id.id, termination.terminated_at);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(termination)))
+}
+
+
+/// Удалить сотрудника безвозвратно
+///
+/// Пример: DELETE /admin/employee/17
+#[utoipa::path(
+    delete,
+    path = "/employee/{id}",
+    tag = "admin",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeId),
+    responses(
+        (status = 200, description = "Удаленная запись", body = ApiResponse<Employee>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[delete("/employee/{id:\\d+}")]
+async fn delete_employee(principal: Principal, path: web::Path<UncheckedEmployeeId>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::DeleteEmployees)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let employee_id = path.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let employee = db_client.delete_employee(employee_id).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Deleted employee: {:?}", employee);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(employee)))
+}
+
+
+/// Получить зарплату работника по идентификатору
+///
+/// Пример: /17/salary
+#[utoipa::path(
+    get,
+    path = "/{id}/salary",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeId),
+    responses(
+        (status = 200, description = "Сотрудник с зарплатой", body = ApiResponse<Employee>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[get("/{id:\\d+}/salary")]
+async fn get_employee_salary_by_id(principal: Principal, policy: web::Data<AccessPolicy>, path: web::Path<UncheckedEmployeeId>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    let employee_id = path.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    policy.check_salary_read(&principal, employee_id.id)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let employee = db_client.get_employee_by_id(employee_id).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Sent salary of employee with id {}", employee_id.id);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(employee)))
+}
+
+
+/// Увеличить зарплату сотруднику по идентификатору
+///
+/// Пример: /17/increase?percentage=3.5&rounding=half_even
+#[utoipa::path(
+    post,
+    path = "/{id}/increase",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeId, UncheckedSalaryPercentage),
+    request_body(description = "Параметры в теле вместо строки запроса", content((Option<UncheckedSalaryPercentage> = "application/json"), (Option<UncheckedSalaryPercentage> = "application/x-www-form-urlencoded"))),
+    responses(
+        (status = 200, description = "Изменение зарплаты", body = ApiResponse<SalaryChange>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 415, description = "Тело в неподдерживаемом формате", body = ErrorResponse),
+        (status = 422, description = "Зарплата не помещается в колонку", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[post("/{id:\\d+}/increase")]
+async fn increase_employee_salary_by_id(principal: Principal, policy: web::Data<AccessPolicy>, path: web::Path<UncheckedEmployeeId>, params: Params<UncheckedSalaryPercentage>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::ChangeSalaries)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let employee_id = path.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let percentage = params.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let limit = policy.check_raise(&principal, &percentage.percentage)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let increase = db_client.increase_employee_salary_by_id(employee_id, percentage.clone(), limit, principal.name).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Increased the salary of employee with id {} by {:?}", employee_id.id, percentage);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(increase)))
+}
+
+
+/// Изменить зарплату сотруднику по идентификатору
+///
+/// Изменение на процент (в том числе отрицательный), на сумму или установка нового значения
+/// Пример: /17/adjust?operation=percentage&value=-5&rounding=floor
+/// Пример: /17/adjust?operation=delta&value=-1500.50&currency=RUB
+/// Пример: /17/adjust?operation=set&value=90000
+#[utoipa::path(
+    post,
+    path = "/{id}/adjust",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeId, UncheckedSalaryAdjustment),
+    request_body(description = "Параметры в теле вместо строки запроса", content((Option<UncheckedSalaryAdjustment> = "application/json"), (Option<UncheckedSalaryAdjustment> = "application/x-www-form-urlencoded"))),
+    responses(
+        (status = 200, description = "Изменение зарплаты", body = ApiResponse<SalaryChange>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 415, description = "Тело в неподдерживаемом формате", body = ErrorResponse),
+        (status = 422, description = "Зарплата не помещается в колонку", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[post("/{id:\\d+}/adjust")]
+async fn adjust_employee_salary(principal: Principal, policy: web::Data<AccessPolicy>, path: web::Path<UncheckedEmployeeId>, params: Params<UncheckedSalaryAdjustment>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::ChangeSalaries)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let employee_id = path.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let adjustment = params.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let limit = policy.raise_limit(&principal, &adjustment)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let change = db_client.adjust_employee_salary(employee_id, adjustment.clone(), limit, principal.name).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Adjusted the salary of employee with id {} with {:?}", employee_id.id, adjustment);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(change)))
+}
+
+
+/// Повысить зарплату нескольким сотрудникам
+///
+/// Пакет передается в теле запроса списком сотрудников или одним процентом с фильтром
+/// Пример: /batch/increase {"mode": "best_effort", "items": [{"id": 17, "percentage": "5"}, {"id": 18, "percentage": "7.5"}]}
+/// Пример: /batch/increase {"percentage": "4", "filter": {"currency": "RUB", "max_salary": "100000"}}
+#[utoipa::path(
+    post,
+    path = "/batch/increase",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    request_body = UncheckedSalaryBatch,
+    responses(
+        (status = 200, description = "Результат по каждому сотруднику", body = ApiResponse<SalaryBatchReport>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 415, description = "Тело в неподдерживаемом формате", body = ErrorResponse),
+        (status = 422, description = "Зарплата не помещается в колонку", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[post("/batch/increase")]
+async fn increase_salaries(principal: Principal, policy: web::Data<AccessPolicy>, body: web::Json<UncheckedSalaryBatch>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    principal.require(Permission::ChangeSalaries)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let batch = body.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let limit = policy.check_batch(&principal, &batch)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let report = db_client.increase_salaries(batch, limit, principal.name).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Increased salaries in {:?} mode: {} applied, {} failed", report.mode, report.applied, report.failed);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(report)))
+}
+
+
+/// Получить журнал изменений зарплаты сотрудника
+///
+/// Пример: /17/salary/history?page=2&per_page=50
+#[utoipa::path(
+    get,
+    path = "/{id}/salary/history",
+    tag = "employee",
+    security(("api_key" = []), ("bearer" = [])),
+    params(UncheckedEmployeeId, UncheckedPage),
+    responses(
+        (status = 200, description = "Страница журнала", body = ApiResponse<SalaryHistoryPage>),
+        (status = 400, description = "Некорректные параметры", body = ErrorResponse),
+        (status = 401, description = "Нет действительных учетных данных", body = ErrorResponse),
+        (status = 403, description = "Недостаточно прав", body = ErrorResponse),
+        (status = 404, description = "Сотрудник не найден", body = ErrorResponse),
+        (status = 503, description = "База данных недоступна", body = ErrorResponse),
+    ),
+)]
+#[get("/{id:\\d+}/salary/history")]
+async fn get_salary_history(principal: Principal, policy: web::Data<AccessPolicy>, path: web::Path<UncheckedEmployeeId>, query: web::Query<UncheckedPage>, db_client: web::Data<dyn DBClient>) -> Result<HttpResponse, AppError> {
+    let employee_id = path.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    policy.check_salary_read(&principal, employee_id.id)
+        .inspect_err(|e| warn!("Access denied: {e}"))?;
+    let page = query.into_inner().check()
+        .inspect_err(|e| error!("Bad request: {e}"))?;
+    let history = db_client.get_salary_history(employee_id, page).await
+        .inspect_err(|e| error!("Request failed: {e}"))?;
+    info!("Sent salary history of employee with id {}, page {:?}", employee_id.id, page);
+    Ok(HttpResponse::Ok().json(ApiResponse::new(history)))
+}
+
+
+/// Проверка жизнеспособности
+///
+/// Отвечает, пока процесс обслуживает запросы, и не обращается к базе
+#[utoipa::path(
+    get,
+    path = "/live",
+    tag = "health",
+    responses(
+        (status = 200, description = "Процесс обслуживает запросы", body = ApiResponse<LivenessReport>),
+    ),
+)]
+#[get("/live")]
+async fn liveness_probe() -> HttpResponse {
+    HttpResponse::Ok().json(ApiResponse::new(LivenessReport{alive: true}))
+}
+
+
+/// Проверка готовности принимать запросы
+///
+/// Проверяет соединение с базой и миграции схемы, сообщает загруженность пула.
+/// После сигнала остановки отвечает 503
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "Приложение готово", body = ApiResponse<ReadinessReport>),
+        (status = 503, description = "Приложение не готово, причина в теле ответа", body = ApiResponse<ReadinessReport>),
+    ),
+)]
+#[get("/ready")]
+async fn readiness_probe(db_client: web::Data<dyn DBClient>, readiness: web::Data<Readiness>) -> HttpResponse {
+    let report = check_readiness(db_client.get_ref(), &readiness).await;
+    if report.ready {
+        HttpResponse::Ok().json(ApiResponse::new(report))
+    } else {
+        warn!("Not ready: {:?}", report);
+        HttpResponse::ServiceUnavailable().json(ApiResponse::new(report))
+    }
+}
+
+
+/// Набор эндпоинтов с заданным префиксом
+///
+/// Ошибки разбора query-параметров, пути и тела возвращаются в том же формате, что и остальные ошибки
+fn api_scope(path: &str) -> Scope {
+    web::scope(path)
+        .app_data(web::QueryConfig::default()
+            .error_handler(|e, _| AppError::validation("query", &e.to_string()).into()))
+        .app_data(web::PathConfig::default()
+            .error_handler(|e, _| AppError::validation("path", &e.to_string()).into()))
+        .app_data(web::JsonConfig::default()
+            .error_handler(|e, _| match e {
+                JsonPayloadError::ContentType => AppError::UnsupportedMediaType("request body must be application/json".to_owned()).into(),
+                e => AppError::validation("body", &e.to_string()).into(),
+            }))
+        .app_data(web::FormConfig::default()
+            .error_handler(|e, _| match e {
+                UrlencodedError::ContentType => AppError::UnsupportedMediaType("request body must be application/x-www-form-urlencoded".to_owned()).into(),
+                e => AppError::validation("body", &e.to_string()).into(),
+            }))
+}
+
+
+/// Набор эндпоинтов /employee
+///
+/// Требуют проверки подлинности. Каждый запрос, в том числе отклоненный,
+/// учитывается в метриках по шаблону маршрута и статусу ответа
+fn employee_scope() -> Scope<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse, Error = actix_web::Error, InitError = ()>> {
+    api_scope("/employee")
+        .wrap_fn(auth::authenticate)
+        .wrap_fn(metrics::track_request)
+        .service(list_employees)
+        .service(search_employees)
+        .service(increase_employee_salary)
+        .service(get_employee_salary)
+        .service(add_new_employee)
+        .service(get_employee_by_id)
+        .service(get_employee_salary_by_id)
+        .service(increase_employee_salary_by_id)
+        .service(adjust_employee_salary)
+        .service(increase_salaries)
+        .service(get_salary_history)
+        .service(update_employee)
+        .service(terminate_employee)
+}
+
+
+/// Проверки состояния /health
+///
+/// Не требуют авторизации и предназначены для Docker и Kubernetes
+fn health_scope() -> Scope {
+    api_scope("/health")
+        .service(liveness_probe)
+        .service(readiness_probe)
+}
+
+
+/// Спецификация OpenAPI и страница документации, если они включены настройкой http.docs
+fn docs_routes(cfg: &mut web::ServiceConfig, enabled: bool) {
+    if enabled {
+        cfg.service(openapi::openapi_json)
+            .service(openapi::docs);
+    }
+}
+
+
+/// Набор административных эндпоинтов /admin
+///
+/// Операции, которые нельзя отменить. Требуют проверки подлинности
+fn admin_scope() -> Scope<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse, Error = actix_web::Error, InitError = ()>> {
+    api_scope("/admin")
+        .wrap_fn(auth::authenticate)
+        .wrap_fn(metrics::track_request)
+        .service(delete_employee)
+}
+
+
+/// Метрики Prometheus /metrics, если они включены настройкой http.metrics
+///
+/// Раскрывают нагрузку и число изменений зарплат, поэтому требуют проверки подлинности
+fn metrics_routes(cfg: &mut web::ServiceConfig, enabled: bool) {
+    if enabled {
+        cfg.service(api_scope("/metrics")
+            .wrap_fn(auth::authenticate)
+            .service(metrics::prometheus_metrics));
     }
 }
 
@@ -92,110 +629,353 @@ async fn increase_employee_salary(query: web::Query<UncheckedSalaryMultiplier>,
 // Сервер и его строитель
 
 pub struct Server{
-    host: String,
-    port: u16,
+    config: Config,
+    readiness: web::Data<Readiness>,
+    metrics: web::Data<Metrics>,
+    telemetry: Option<Telemetry>,
 }
 
 impl Server{
+    /// Признак остановки сервера, который видит проверка готовности
+    pub fn readiness(&self) -> web::Data<Readiness> {
+        self.readiness.clone()
+    }
+
+    /// Метрики сервера
+    pub fn metrics(&self) -> web::Data<Metrics> {
+        self.metrics.clone()
+    }
+
     pub fn builder() -> ServerBuilder {
         ServerBuilder {
+            config: None,
             host: None,
             port: None,
+            telemetry: None,
+        }
+    }
+
+    /// Трассировка, заданная при сборке сервера, либо созданная по настройкам
+    fn telemetry(&self) -> Result<Telemetry, AppError> {
+        match &self.telemetry {
+            Some(telemetry) => Ok(telemetry.clone()),
+            None => Telemetry::new(&self.config.tracing),
         }
     }
     
     pub async fn start(self) -> Result<(), Box<dyn Error>>{
-        let log_file = File::create("./app.log").unwrap();
-        CombinedLogger::init(
-            vec![
-                WriteLogger::new(
-                    LevelFilter::Debug,
-                    Config::default(),
-                    log_file,
-                ),
-                WriteLogger::new(
-                    LevelFilter::Debug,
-                    Config::default(),
-                    std::io::stdout()
-                )
-            ]
-        ).unwrap();
+        logging::init(&self.config.logging)?;
+        let authenticator = web::Data::new(Authenticator::new(&self.config.auth)?);
+        let access_policy = web::Data::new(AccessPolicy::new(self.config.auth.max_raise_percentage));
 
-        let postgres_client = DBClientPostgres::new().await?;
-        postgres_client.init_db().await?;
-        let postgres_client: Arc<dyn DBClient> = Arc::new(postgres_client);
+        let postgres_client = DBClientPostgres::new(&self.config.database).await?;
+        postgres_client.init_db().await
+            .inspect_err(|e| error!("Failed to initialize database schema: {e}"))?;
+        info!("Database schema is up to date: {:?}", postgres_client.migration_status().await?);
+        let telemetry = self.telemetry()?;
+        let postgres_client = TracedDBClient::new(Arc::new(postgres_client), telemetry.tracer());
+        let postgres_client: Arc<dyn DBClient> = Arc::new(MeteredDBClient::new(Arc::new(postgres_client), self.metrics.clone()));
         let data: web::Data<dyn DBClient> = web::Data::from(postgres_client);
-        HttpServer::new(move || {
+        let name_policy = web::Data::new(self.config.names.clone());
+        let readiness = self.readiness.clone();
+        let metrics = self.metrics.clone();
+        let tracing = web::Data::new(telemetry.clone());
+        let docs = self.config.http.docs;
+        let metrics_enabled = self.config.http.metrics;
+        let mut server = HttpServer::new(move || {
             App::new()
+                .wrap_fn(telemetry::trace_request)
+                .wrap_fn(logging::assign_request_id)
                 .app_data(data.clone())
-                .service(
-                    web::scope("/employee")
-                        .service(increase_employee_salary)
-                        .service(get_employee_salary)
-                        .service(add_new_employee)
-                )
+                .app_data(name_policy.clone())
+                .app_data(readiness.clone())
+                .app_data(metrics.clone())
+                .app_data(tracing.clone())
+                .app_data(authenticator.clone())
+                .app_data(access_policy.clone())
+                .service(employee_scope())
+                .service(admin_scope())
+                .service(health_scope())
+                .configure(|cfg| docs_routes(cfg, docs))
+                .configure(|cfg| metrics_routes(cfg, metrics_enabled))
         })
-        .bind((self.host, self.port))?
-        .run()
-        .await?;
+        // Сигналы обрабатывает shutdown_on_signal, чтобы до остановки успеть переключить готовность
+        .disable_signals()
+        .shutdown_timeout(self.config.http.shutdown_timeout.as_secs());
+        if let Some(workers) = self.config.http.workers {
+            server = server.workers(workers);
+        }
+        let server = server.bind((self.config.http.host, self.config.http.port))?.run();
+        actix_web::rt::spawn(shutdown_on_signal(server.handle(), self.readiness, self.config.http.shutdown_delay));
+        server.await?;
+        if let Err(e) = telemetry.shutdown() {
+            warn!("{e}");
+        }
         Ok(())
     }
 
     pub async fn test_start(self) -> Result<impl actix_service::Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>, Box<dyn Error>> {
         let mut mock_client = MockDBClient::new();
         
-        // В базе хранится только запись о Test Employee
+        // В базе хранится запись о Test Employee с идентификатором 1
+        // и две записи о Duplicate Employee с идентификаторами 2 и 3
         mock_client.expect_get_employee_salary()
             .returning(|name|{
                 match &*(name.name){
-                    "Test Employee" => {Ok(EmployeeSalary{amount: 100})},
-                    _ => {Err("bruh".into())}
+                    "Test Employee" => {Ok(employee(1, &name.name))},
+                    "Duplicate Employee" => {Err(AppError::Ambiguous{
+                        message: "several employees are named \"Duplicate Employee\", address them by id".to_owned(),
+                        candidates: vec![2, 3],
+                    })},
+                    _ => {Err(AppError::NotFound("employee does not exist".to_owned()))}
+                }
+            });
+
+        mock_client.expect_get_employee_by_id()
+            .returning(|id|{
+                match id.id {
+                    1 => {Ok(employee(1, "Test Employee"))},
+                    2 | 3 => {Ok(employee(id.id, "Duplicate Employee"))},
+                    _ => {Err(AppError::NotFound("employee does not exist".to_owned()))}
                 }
             });
 
-        // В базу можно добавить только Test Employee, все остальные заняты
+        // В базу можно добавить только Test Employee, все остальные заняты,
+        // как если бы их имена уже нарушали политику уникальности
         mock_client.expect_add_new_employee()
             .returning(|data|{
                 match &*(data.name){
-                    "Test Employee" => {Ok(())},
-                    _ => {Err("bruh".into())}
+                    "Test Employee" => {Ok(Employee{id: 1, name: data.name, name_parts: data.name_parts, salary: data.salary, personnel_number: data.personnel_number})},
+                    _ => {Err(AppError::Conflict("employee already exists".to_owned()))}
                 }
             });
 
         // Увеличить зарплату можно только для Test Employee
         mock_client.expect_increase_employee_salary()
-            .returning(|data| {
-                let mut salary = EmployeeSalary{amount: 100};
+            .returning(|data, _limit, _actor| {
+                let mut salary = EmployeeSalary{amount: Money::new(10000, Currency::Rub)};
                 match &*(data.name){
                     "Test Employee" => {
-                        Ok(
-                            salary.increase_by_percentage(&data).unwrap()
-                        )
+                        let old_salary = salary.increase_by_percentage(&data.get_percentage())?;
+                        Ok(SalaryChange{
+                            employee: Employee{salary: salary.amount, ..employee(1, &data.name)},
+                            old_salary: old_salary.amount,
+                            new_salary: salary.amount,
+                        })
+                    },
+                    _ => {Err(AppError::NotFound("employee does not exist".to_owned()))}
+                }
+            });
+
+        mock_client.expect_increase_employee_salary_by_id()
+            .returning(|id, percentage, _limit, _actor| {
+                let mut salary = EmployeeSalary{amount: Money::new(10000, Currency::Rub)};
+                match id.id {
+                    1 => {
+                        let old_salary = salary.increase_by_percentage(&percentage)?;
+                        Ok(SalaryChange{
+                            employee: Employee{salary: salary.amount, ..employee(1, "Test Employee")},
+                            old_salary: old_salary.amount,
+                            new_salary: salary.amount,
+                        })
+                    },
+                    _ => {Err(AppError::NotFound("employee does not exist".to_owned()))}
+                }
+            });
+
+        mock_client.expect_adjust_employee_salary()
+            .returning(|id, adjustment, limit, _actor| {
+                let mut salary = EmployeeSalary{amount: Money::new(10000, Currency::Rub)};
+                match id.id {
+                    1 => {
+                        let old_salary = salary.adjust(&adjustment)?;
+                        if let Some(limit) = limit {
+                            limit.check(&adjustment, &old_salary.amount, &salary.amount)?;
+                        }
+                        Ok(SalaryChange{
+                            employee: Employee{salary: salary.amount, ..employee(1, "Test Employee")},
+                            old_salary: old_salary.amount,
+                            new_salary: salary.amount,
+                        })
+                    },
+                    _ => {Err(AppError::NotFound("employee does not exist".to_owned()))}
+                }
+            });
+
+        // Пакет повышает зарплату только Test Employee, фильтр выбирает только его
+        mock_client.expect_increase_salaries()
+            .returning(|batch, _limit, _actor| {
+                let items = match batch.target {
+                    SalaryBatchTarget::Items(items) => items.into_iter().map(|item| (item.id, item.percentage)).collect(),
+                    SalaryBatchTarget::Filter{percentage, ..} => vec![(EmployeeId{id: 1}, percentage)],
+                };
+                let mut report = SalaryBatchReport{mode: batch.mode, applied: 0, failed: 0, items: Vec::new()};
+                for (id, percentage) in items {
+                    let mut salary = EmployeeSalary{amount: Money::new(10000, Currency::Rub)};
+                    let result = match id.id {
+                        1 => salary.increase_by_percentage(&percentage).map(|old_salary| SalaryChange{
+                            employee: Employee{salary: salary.amount, ..employee(1, "Test Employee")},
+                            old_salary: old_salary.amount,
+                            new_salary: salary.amount,
+                        }),
+                        _ => Err(AppError::NotFound("employee does not exist".to_owned())),
+                    };
+                    match (result, batch.mode) {
+                        (Ok(change), _) => {
+                            report.applied += 1;
+                            report.items.push(SalaryBatchItemReport{id: id.id, change: Some(change), error: None});
+                        },
+                        (Err(e), BatchMode::Atomic) => return Err(e.for_employee(id.id)),
+                        (Err(e), BatchMode::BestEffort) => {
+                            report.failed += 1;
+                            report.items.push(SalaryBatchItemReport{id: id.id, change: None, error: Some(e.to_response().error)});
+                        },
+                    }
+                }
+                Ok(report)
+            });
+
+        // Изменить, уволить и удалить можно только Test Employee, новое имя Duplicate Employee занято
+        mock_client.expect_update_employee()
+            .returning(|id, update| {
+                match (id.id, update.name.as_deref()) {
+                    (1, Some("Duplicate Employee")) => Err(AppError::Conflict("employee with this name already exists".to_owned())),
+                    (1, name) => Ok(Employee{
+                        name: name.unwrap_or("Test Employee").to_owned(),
+                        name_parts: update.name_parts.unwrap_or(PersonName::parse_legacy("Test Employee")),
+                        personnel_number: update.personnel_number,
+                        ..employee(1, "Test Employee")
+                    }),
+                    _ => Err(AppError::NotFound("employee does not exist".to_owned())),
+                }
+            });
+
+        mock_client.expect_terminate_employee()
+            .returning(|id| {
+                match id.id {
+                    1 => Ok(EmployeeTermination{
+                        employee: employee(1, "Test Employee"),
+                        terminated_at: chrono::DateTime::default(),
+                    }),
+                    _ => Err(AppError::NotFound("employee does not exist".to_owned())),
+                }
+            });
+
+        mock_client.expect_delete_employee()
+            .returning(|id| {
+                match id.id {
+                    1 => Ok(employee(1, "Test Employee")),
+                    _ => Err(AppError::NotFound("employee does not exist".to_owned())),
+                }
+            });
+
+        // В списке Test Employee и два Duplicate Employee, фильтр по началу имени применяется
+        mock_client.expect_list_employees()
+            .returning(|query| {
+                let employees: Vec<Employee> = [(1, "Test Employee"), (2, "Duplicate Employee"), (3, "Duplicate Employee")]
+                    .into_iter()
+                    .map(|(id, name)| employee(id, name))
+                    .filter(|employee| query.filter.name_prefix.as_ref().is_none_or(|prefix| normalize_name(&employee.name).starts_with(prefix)))
+                    .collect();
+                Ok(EmployeePage{
+                    total: employees.len() as i64,
+                    employees: employees.into_iter().skip(query.page.offset() as usize).take(query.page.per_page as usize).collect(),
+                    page: query.page.page,
+                    per_page: query.page.per_page,
+                })
+            });
+
+        // Поиск находит сотрудников, в нормализованном имени которых встречается строка поиска
+        mock_client.expect_search_employees()
+            .returning(|search| {
+                Ok([(1, "Test Employee"), (2, "Duplicate Employee"), (3, "Duplicate Employee")]
+                    .into_iter()
+                    .filter(|(_, name)| normalize_name(name).contains(&search.query))
+                    .map(|(id, name)| EmployeeMatch{
+                        score: if normalize_name(name) == search.query { 1.0 } else { 0.5 },
+                        employee: employee(id, name),
+                    })
+                    .take(search.limit as usize)
+                    .collect())
+            });
+
+        // Зарплату Test Employee повышали один раз
+        mock_client.expect_get_salary_history()
+            .returning(|id, page| {
+                match id.id {
+                    1 => {
+                        let entries = vec![SalaryHistoryEntry{
+                            id: 1,
+                            employee_id: 1,
+                            old_salary: Money::new(8000, Currency::Rub),
+                            new_salary: Money::new(10000, Currency::Rub),
+                            operation: SalaryOperation::Percentage,
+                            percentage: Some(Percentage::whole(25)),
+                            rounding: Some(RoundingMode::Ceil),
+                            changed_at: chrono::DateTime::default(),
+                            actor: "Test Actor".to_owned(),
+                        }];
+                        Ok(SalaryHistoryPage{
+                            entries: entries.into_iter().skip(page.offset() as usize).take(page.per_page as usize).collect(),
+                            page: page.page,
+                            per_page: page.per_page,
+                            total: 1,
+                        })
                     },
-                    _ => {Err("bruh".into())}
+                    _ => {Err(AppError::NotFound("employee does not exist".to_owned()))}
                 }
             });
-        let a: Arc<dyn DBClient> = Arc::new(mock_client);
+        mock_client.expect_ping()
+            .returning(|| Ok(()));
+        mock_client.expect_migration_status()
+            .returning(|| Ok(MigrationStatus{applied: vec![1], pending: Vec::new()}));
+        mock_client.expect_pool_status()
+            .returning(|| PoolStatus::new(1, 1, 7));
+
+        let authenticator = Authenticator::new(&self.config.auth)?;
+        let telemetry = self.telemetry()?;
+        let traced_client = TracedDBClient::new(Arc::new(mock_client), telemetry.tracer());
+        let a: Arc<dyn DBClient> = Arc::new(MeteredDBClient::new(Arc::new(traced_client), self.metrics.clone()));
         let data: web::Data<dyn DBClient> = web::Data::from(a);
         let app = actix_web::test::init_service(App::new()
+            .wrap_fn(telemetry::trace_request)
+            .wrap_fn(logging::assign_request_id)
             .app_data(data)
-            .service(
-                web::scope("/employee")
-                    .service(increase_employee_salary)
-                    .service(get_employee_salary)
-                    .service(add_new_employee)
-                )
+            .app_data(web::Data::new(telemetry))
+            .app_data(web::Data::new(authenticator))
+            .app_data(web::Data::new(AccessPolicy::new(self.config.auth.max_raise_percentage)))
+            .app_data(web::Data::new(self.config.names.clone()))
+            .app_data(self.readiness.clone())
+            .app_data(self.metrics.clone())
+            .service(employee_scope())
+            .service(admin_scope())
+            .service(health_scope())
+            .configure(|cfg| docs_routes(cfg, self.config.http.docs))
+            .configure(|cfg| metrics_routes(cfg, self.config.http.metrics))
         ).await;
         Ok(app)
     }
 }
 
+/// Запись о сотруднике в заглушке базы из test_start
+fn employee(id: i32, name: &str) -> Employee {
+    Employee{id, name: name.to_owned(), name_parts: PersonName::parse_legacy(name), salary: Money::new(10000, Currency::Rub), personnel_number: None}
+}
+
 pub struct ServerBuilder{
+    config: Option<Config>,
     host: Option<String>,
     port: Option<u16>,
+    telemetry: Option<Telemetry>,
 }
 
 impl ServerBuilder{
+    /// Конфигурация сервера, адрес и порт из host и port ее перекрывают
+    pub fn config(mut self, value: Config) -> Self {
+        self.config = Some(value);
+        self
+    }
+
     pub fn host(mut self, value: String) -> Self {
         self.host = Some(value);
         self
@@ -206,11 +986,21 @@ impl ServerBuilder{
         self
     }
 
+    /// Трассировка вместо создаваемой по разделу tracing конфигурации
+    pub fn telemetry(mut self, value: Telemetry) -> Self {
+        self.telemetry = Some(value);
+        self
+    }
+
     pub fn build(self) -> Server {
-        Server{
-            host: self.host.unwrap_or("localhost".to_string()),
-            port: self.port.unwrap_or(8080),
+        let mut config = self.config.unwrap_or_default();
+        if let Some(host) = self.host {
+            config.http.host = host;
+        }
+        if let Some(port) = self.port {
+            config.http.port = port;
         }
+        Server{config, readiness: web::Data::new(Readiness::default()), metrics: web::Data::new(Metrics::new()), telemetry: self.telemetry}
     }
 }
 
@@ -220,19 +1010,28 @@ mod tests {
     use serial_test::serial;
     use actix_service::Service;
     use actix_web::http::StatusCode;
+    use crate::models::ErrorResponse;
+    use crate::config::AuthConfig;
     use super::*;
 
     fn set_env_vars(){
         dotenv::dotenv().ok();
     }
 
+    /// Сервер для тестов обработчиков: проверка подлинности выключена
+    fn test_server() -> ServerBuilder {
+        let config = Config{auth: AuthConfig{enabled: false, ..AuthConfig::default()}, ..Config::default()};
+        Server::builder()
+            .config(config)
+            .host("localhost".to_owned())
+            .port(8080)
+    }
+
     #[actix_web::test]
     #[serial]
     async fn test_employee_addition() {
         set_env_vars();
-        let app = Server::builder()
-            .host("localhost".to_owned())
-            .port(8080)
+        let app = test_server()
             .build()
             .test_start()
             .await
@@ -243,15 +1042,24 @@ mod tests {
         let response = app.call(request).await.unwrap();
         let response_status = response.status();
         assert_eq!(response_status, StatusCode::OK);
+        let response_body: ApiResponse<Employee> = actix_web::test::read_body_json(response).await;
+        assert_eq!(Employee{salary: Money::new(200000, Currency::Rub), ..employee(1, "Test Employee")}, response_body.data);
+
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add?surname=Test&given_name=Employee&salary=2000")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<Employee> = actix_web::test::read_body_json(response).await;
+        assert_eq!("Test Employee", response_body.data.name);
+        assert_eq!("Test", response_body.data.name_parts.surname);
     }
 
     #[actix_web::test]
     #[serial]
     async fn test_employee_addition_failing_1() {
         set_env_vars();
-        let app = Server::builder()
-            .host("localhost".to_owned())
-            .port(8080)
+        let app = test_server()
             .build()
             .test_start()
             .await
@@ -262,6 +1070,9 @@ mod tests {
         let response = app.call(request).await.unwrap();
         let response_status = response.status();
         assert_eq!(response_status, StatusCode::BAD_REQUEST);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!("validation_error", response_body.error.code);
+        assert_eq!(Some("salary".to_owned()), response_body.error.field);
     }
 
 
@@ -269,9 +1080,7 @@ mod tests {
     #[serial]
     async fn test_employee_addition_failing_2() {
         set_env_vars();
-        let app = Server::builder()
-            .host("localhost".to_owned())
-            .port(8080)
+        let app = test_server()
             .build()
             .test_start()
             .await
@@ -288,9 +1097,7 @@ mod tests {
     #[serial]
     async fn test_employee_addition_failing_3() {
         set_env_vars();
-        let app = Server::builder()
-            .host("localhost".to_owned())
-            .port(8080)
+        let app = test_server()
             .build()
             .test_start()
             .await
@@ -303,13 +1110,67 @@ mod tests {
         assert_eq!(response_status, StatusCode::BAD_REQUEST);
     }
 
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_addition_malformed_query() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add?name=Test%20Employee")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        let response_status = response.status();
+        assert_eq!(response_status, StatusCode::BAD_REQUEST);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!("validation_error", response_body.error.code);
+        assert_eq!(Some("query".to_owned()), response_body.error.field);
+
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add?name=Test%20Employee&salary=lots")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!(Some("salary".to_owned()), response_body.error.field);
+
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add?name=Test%20Employee%20%3Cscript%3E&salary=-1&currency=USD")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        let fields: Vec<String> = response_body.error.errors.unwrap().into_iter().map(|error| error.field).collect();
+        assert_eq!(vec!["name", "currency", "salary"], fields);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_addition_conflict() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add?name=Other%20Employee&salary=2000")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        let response_status = response.status();
+        assert_eq!(response_status, StatusCode::CONFLICT);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!("conflict", response_body.error.code);
+    }
+
     #[actix_web::test]
     #[serial]
     async fn test_employee_salary_getter() {
         set_env_vars();
-        let app = Server::builder()
-            .host("localhost".to_owned())
-            .port(8080)
+        let app = test_server()
             .build()
             .test_start()
             .await
@@ -320,10 +1181,10 @@ mod tests {
         let response = app.call(request).await.unwrap();
         let response_status = response.status();
         assert_eq!(response_status, StatusCode::OK);
-        let response_body = actix_web::test::read_body(response).await;
-        let response_body = String::from_utf8(response_body.to_vec()).unwrap();
-        let response_body: i32 = response_body.trim().parse().unwrap();
-        assert_eq!(100, response_body);
+        let response_body: ApiResponse<Employee> = actix_web::test::read_body_json(response).await;
+        assert_eq!(1, response_body.data.id);
+        assert_eq!("Test Employee", response_body.data.name);
+        assert_eq!(Money::new(10000, Currency::Rub), response_body.data.salary);
     }
 
 
@@ -331,9 +1192,7 @@ mod tests {
     #[serial]
     async fn test_employee_salary_getter_failing() {
         set_env_vars();
-        let app = Server::builder()
-            .host("localhost".to_owned())
-            .port(8080)
+        let app = test_server()
             .build()
             .test_start()
             .await
@@ -343,16 +1202,17 @@ mod tests {
             .to_request();
         let response = app.call(request).await.unwrap();
         let response_status = response.status();
-        assert_eq!(response_status, StatusCode::BAD_REQUEST);
+        assert_eq!(response_status, StatusCode::NOT_FOUND);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!("not_found", response_body.error.code);
+        assert_eq!(None, response_body.error.field);
     }
 
     #[actix_web::test]
     #[serial]
     async fn test_employee_increase_salary(){
         set_env_vars();
-        let app = Server::builder()
-            .host("localhost".to_owned())
-            .port(8080)
+        let app = test_server()
             .build()
             .test_start()
             .await
@@ -363,19 +1223,16 @@ mod tests {
         let response = app.call(request).await.unwrap();
         let response_status = response.status();
         assert_eq!(response_status, StatusCode::OK);
-        let response_body = actix_web::test::read_body(response).await;
-        let response_body = String::from_utf8(response_body.to_vec()).unwrap();
-        let response_body: i32 = response_body.trim().parse().unwrap();
-        assert_eq!(100, response_body);
+        let response_body: ApiResponse<SalaryChange> = actix_web::test::read_body_json(response).await;
+        assert_eq!(Money::new(10000, Currency::Rub), response_body.data.old_salary);
+        assert_eq!(Money::new(12500, Currency::Rub), response_body.data.employee.salary);
     }
 
     #[actix_web::test]
     #[serial]
     async fn test_employee_increase_salary_failing_1(){
         set_env_vars();
-        let app = Server::builder()
-            .host("localhost".to_owned())
-            .port(8080)
+        let app = test_server()
             .build()
             .test_start()
             .await
@@ -385,16 +1242,14 @@ mod tests {
             .to_request();
         let response = app.call(request).await.unwrap();
         let response_status = response.status();
-        assert_eq!(response_status, StatusCode::BAD_REQUEST);
+        assert_eq!(response_status, StatusCode::NOT_FOUND);
     }
 
     #[actix_web::test]
     #[serial]
     async fn test_employee_increase_salary_failing_2(){
         set_env_vars();
-        let app = Server::builder()
-            .host("localhost".to_owned())
-            .port(8080)
+        let app = test_server()
             .build()
             .test_start()
             .await
@@ -411,9 +1266,7 @@ mod tests {
     #[serial]
     async fn test_employee_increase_salary_failing_3(){
         set_env_vars();
-        let app = Server::builder()
-            .host("localhost".to_owned())
-            .port(8080)
+        let app = test_server()
             .build()
             .test_start()
             .await
@@ -425,4 +1278,851 @@ mod tests {
         let response_status = response.status();
         assert_eq!(response_status, StatusCode::BAD_REQUEST);
     }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_salary_getter_ambiguous() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/salary?name=Duplicate%20Employee")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        let response_status = response.status();
+        assert_eq!(response_status, StatusCode::CONFLICT);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!("ambiguous", response_body.error.code);
+        assert_eq!(Some(vec![2, 3]), response_body.error.candidates);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_by_id() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/3")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        let response_status = response.status();
+        assert_eq!(response_status, StatusCode::OK);
+        let response_body: ApiResponse<Employee> = actix_web::test::read_body_json(response).await;
+        assert_eq!(employee(3, "Duplicate Employee"), response_body.data);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/1/salary")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        let response_status = response.status();
+        assert_eq!(response_status, StatusCode::OK);
+        let response_body: ApiResponse<Employee> = actix_web::test::read_body_json(response).await;
+        assert_eq!(Money::new(10000, Currency::Rub), response_body.data.salary);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_by_id_failing() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/42/salary")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/0")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_increase_salary_by_id() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/increase?percentage=25")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        let response_status = response.status();
+        assert_eq!(response_status, StatusCode::OK);
+        let response_body: ApiResponse<SalaryChange> = actix_web::test::read_body_json(response).await;
+        assert_eq!(Money::new(10000, Currency::Rub), response_body.data.old_salary);
+        assert_eq!(Money::new(12500, Currency::Rub), response_body.data.employee.salary);
+
+        // Процент задается не точнее сотых
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/increase?percentage=0.125")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/increase?percentage=0.25&rounding=half_even")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<SalaryChange> = actix_web::test::read_body_json(response).await;
+        assert_eq!(Money::new(10025, Currency::Rub), response_body.data.employee.salary);
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/increase?percentage=10&rounding=sideways")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!(Some("rounding".to_owned()), response_body.error.field);
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/increase?percentage=0")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/2/increase?percentage=10")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_adjust_salary() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        for (query, expected) in [
+            ("operation=percentage&value=-12.5", 8750),
+            ("operation=delta&value=-20.01&currency=RUB", 7999),
+            ("operation=set&value=250", 25000),
+        ] {
+            let request = actix_web::test::TestRequest::post()
+                .uri(&format!("/employee/1/adjust?{query}"))
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{query}");
+            let response_body: ApiResponse<SalaryChange> = actix_web::test::read_body_json(response).await;
+            assert_eq!(Money::new(10000, Currency::Rub), response_body.data.old_salary);
+            assert_eq!(Money::new(expected, Currency::Rub), response_body.data.new_salary);
+        }
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/adjust?operation=delta&value=-100")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!(Some("value".to_owned()), response_body.error.field);
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/adjust?operation=bonus&value=100")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/7/adjust?operation=set&value=100")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_salary_batch() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/batch/increase")
+            .set_json(serde_json::json!({"mode": "best_effort", "items": [{"id": 1, "percentage": "2.5"}, {"id": 7, "percentage": 10}]}))
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<SalaryBatchReport> = actix_web::test::read_body_json(response).await;
+        assert_eq!((1, 1), (response_body.data.applied, response_body.data.failed));
+        assert_eq!(Money::new(10250, Currency::Rub), response_body.data.items[0].change.as_ref().unwrap().new_salary);
+        assert_eq!("not_found", response_body.data.items[1].error.as_ref().unwrap().code);
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/batch/increase")
+            .set_json(serde_json::json!({"items": [{"id": 1, "percentage": "2.5"}, {"id": 7, "percentage": 10}]}))
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert!(response_body.error.message.starts_with("employee 7:"));
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/batch/increase")
+            .set_json(serde_json::json!({"percentage": "4", "rounding": "floor", "filter": {"currency": "RUB", "max_salary": "100000"}}))
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<SalaryBatchReport> = actix_web::test::read_body_json(response).await;
+        assert_eq!((BatchMode::Atomic, 1), (response_body.data.mode, response_body.data.applied));
+
+        for body in [
+            serde_json::json!({"items": []}),
+            serde_json::json!({"items": [{"id": 1, "percentage": "1"}, {"id": 1, "percentage": "2"}]}),
+            serde_json::json!({"items": [{"id": 1, "percentage": "1"}], "percentage": "1"}),
+            serde_json::json!({"percentage": "1", "filter": {"min_salary": "100"}}),
+            serde_json::json!({"percentage": "1", "mode": "eventually"}),
+        ] {
+            let request = actix_web::test::TestRequest::post()
+                .uri("/employee/batch/increase")
+                .set_json(&body)
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{body}");
+        }
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/batch/increase")
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload("{not json")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!(Some("body".to_owned()), response_body.error.field);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_update() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::patch()
+            .uri("/employee/1?name=Renamed%20Employee&personnel_number=A-1")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<Employee> = actix_web::test::read_body_json(response).await;
+        assert_eq!(("Renamed Employee", Some("A-1")), (&*response_body.data.name, response_body.data.personnel_number.as_deref()));
+
+        for (uri, status) in [
+            ("/employee/1", StatusCode::BAD_REQUEST),
+            ("/employee/1?name=%20", StatusCode::BAD_REQUEST),
+            ("/employee/1?name=Duplicate%20Employee", StatusCode::CONFLICT),
+            ("/employee/7?name=Other", StatusCode::NOT_FOUND),
+        ] {
+            let request = actix_web::test::TestRequest::patch()
+                .uri(uri)
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), status, "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_request_bodies() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add")
+            .set_json(serde_json::json!({"name": "Test Employee", "salary": "2000.50", "currency": "RUB"}))
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<Employee> = actix_web::test::read_body_json(response).await;
+        assert_eq!(Money::new(200050, Currency::Rub), response_body.data.salary);
+
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add")
+            .set_form([("name", "Test Employee"), ("salary", "2000")])
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/increase")
+            .set_json(serde_json::json!({"percentage": 10, "rounding": "floor"}))
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<SalaryChange> = actix_web::test::read_body_json(response).await;
+        assert_eq!(11000, response_body.data.new_salary.amount_minor);
+
+        // Неизвестный формат тела
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/batch/increase")
+            .insert_header(("Content-Type", "text/plain"))
+            .set_payload("percentage=5")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!("unsupported_media_type", response_body.error.code);
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add")
+            .insert_header(("Content-Type", "text/plain"))
+            .set_payload("name=Test Employee")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        // Неправильный JSON и смешение строки запроса с телом
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add")
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload("{\"name\": ")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!(Some("body".to_owned()), response_body.error.field);
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add?name=Test%20Employee")
+            .set_json(serde_json::json!({"salary": "2000"}))
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!(Some("query".to_owned()), response_body.error.field);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_deletion() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::delete()
+            .uri("/employee/1")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<EmployeeTermination> = actix_web::test::read_body_json(response).await;
+        assert_eq!(1, response_body.data.employee.id);
+
+        let request = actix_web::test::TestRequest::delete()
+            .uri("/admin/employee/1")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<Employee> = actix_web::test::read_body_json(response).await;
+        assert_eq!(1, response_body.data.id);
+
+        for uri in ["/employee/7", "/admin/employee/7"] {
+            let request = actix_web::test::TestRequest::delete()
+                .uri(uri)
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_list() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<EmployeePage> = actix_web::test::read_body_json(response).await;
+        assert_eq!((3, 3, 20), (response_body.data.total, response_body.data.employees.len(), response_body.data.per_page));
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee?name_prefix=DUPLICATE&per_page=1&page=2&sort=-name")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<EmployeePage> = actix_web::test::read_body_json(response).await;
+        assert_eq!(2, response_body.data.total);
+        assert_eq!(vec![3], response_body.data.employees.iter().map(|employee| employee.id).collect::<Vec<_>>());
+
+        for uri in ["/employee?sort=age", "/employee?per_page=0", "/employee?min_salary=100", "/employee?currency=RUB&min_salary=abc"] {
+            let request = actix_web::test::TestRequest::get()
+                .uri(uri)
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_employee_search() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/search?q=%20TEST%20%20employee")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<Vec<EmployeeMatch>> = actix_web::test::read_body_json(response).await;
+        assert_eq!(1, response_body.data.len());
+        assert_eq!((1, 1.0), (response_body.data[0].employee.id, response_body.data[0].score));
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/search?q=employee&limit=2")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        let response_body: ApiResponse<Vec<EmployeeMatch>> = actix_web::test::read_body_json(response).await;
+        assert_eq!(2, response_body.data.len());
+
+        for uri in ["/employee/search", "/employee/search?q=%20", "/employee/search?q=x&limit=0"] {
+            let request = actix_web::test::TestRequest::get()
+                .uri(uri)
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_salary_history() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/1/salary/history")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<SalaryHistoryPage> = actix_web::test::read_body_json(response).await;
+        assert_eq!(1, response_body.data.total);
+        assert_eq!("Test Actor", response_body.data.entries[0].actor);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/1/salary/history?page=2&per_page=10")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<SalaryHistoryPage> = actix_web::test::read_body_json(response).await;
+        assert!(response_body.data.entries.is_empty());
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/1/salary/history?per_page=1000")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/1/salary/history?page=100000000000000000&per_page=100")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/5/salary/history")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_request_id() {
+        set_env_vars();
+        let log_file = std::env::temp_dir().join(format!("wildberries_test_request_id_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&log_file);
+        let logging_config = crate::config::LoggingConfig{
+            file: Some(log_file.clone()),
+            stdout: false,
+            format: logging::LogFormat::Json,
+            ..Default::default()
+        };
+        logging::init(&logging_config).unwrap();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let mut request_ids = Vec::new();
+        for (uri, status) in [("/employee/1", StatusCode::OK), ("/employee/1", StatusCode::OK), ("/employee/4", StatusCode::NOT_FOUND), ("/unknown", StatusCode::NOT_FOUND)] {
+            let request = actix_web::test::TestRequest::get()
+                .uri(uri)
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), status);
+            let request_id = response.headers().get(logging::REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_owned();
+            assert_eq!(36, request_id.len());
+            request_ids.push(request_id);
+        }
+
+        // Записи обработчика помечены идентификатором своего запроса
+        logging::init(&crate::config::LoggingConfig{file: None, ..logging_config}).unwrap();
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&log_file).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let sent = lines.iter().find(|line| line["message"] == "Sent employee with id 1").unwrap();
+        assert_eq!(request_ids[0], sent["request_id"]);
+        let failed = lines.iter().find(|line| line["message"].as_str().unwrap().starts_with("Request failed")).unwrap();
+        assert_eq!(request_ids[2], failed["request_id"]);
+        std::fs::remove_file(&log_file).unwrap();
+
+        request_ids.sort();
+        request_ids.dedup();
+        assert_eq!(4, request_ids.len());
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_tracing() {
+        set_env_vars();
+        let buffer = telemetry::SpanBuffer::default();
+        let app = test_server()
+            .telemetry(Telemetry::with_writer(buffer.clone()))
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/1")
+            .insert_header(("traceparent", "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"))
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let request_id = response.headers().get(logging::REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_owned();
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/4")
+            .to_request();
+        app.call(request).await.unwrap();
+        // Проверки состояния не трассируются
+        let request = actix_web::test::TestRequest::get()
+            .uri("/health/ready")
+            .to_request();
+        app.call(request).await.unwrap();
+
+        let spans = buffer.spans();
+        let names: Vec<&str> = spans.iter().map(|span| span["name"].as_str().unwrap()).collect();
+        assert_eq!(vec!["get_employee_by_id", "GET /employee/{id}", "get_employee_by_id", "GET /employee/{id}"], names);
+        // Трасса продолжает трассу вызывающего сервиса из заголовка traceparent
+        let (db_call, request) = (&spans[0], &spans[1]);
+        assert_eq!("0af7651916cd43dd8448eb211c80319c", request["trace_id"]);
+        assert_eq!("b7ad6b7169203331", request["parent_span_id"]);
+        assert_eq!("server", request["kind"]);
+        assert_eq!("/employee/{id}", request["attributes"]["http.route"]);
+        assert_eq!(200, request["attributes"]["http.response.status_code"]);
+        assert_eq!(request_id, request["attributes"]["http.request.header.x-request-id"]);
+        assert_eq!(request["trace_id"], db_call["trace_id"]);
+        assert_eq!(request["span_id"], db_call["parent_span_id"]);
+        // Без traceparent начинается новая трасса, ответ 404 не считается ошибкой
+        assert_ne!(request["trace_id"], spans[3]["trace_id"]);
+        assert_eq!(404, spans[3]["attributes"]["http.response.status_code"]);
+        assert_eq!("unset", spans[3]["status"]);
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_metrics() {
+        set_env_vars();
+        let app = test_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        for uri in ["/employee/1", "/employee/1", "/employee/4"] {
+            let request = actix_web::test::TestRequest::get()
+                .uri(uri)
+                .to_request();
+            app.call(request).await.unwrap();
+        }
+        let request = actix_web::test::TestRequest::put()
+            .uri("/employee/add?name=Test%20Employee&salary=2000")
+            .to_request();
+        app.call(request).await.unwrap();
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/increase?percentage=25")
+            .to_request();
+        app.call(request).await.unwrap();
+        // Проверки состояния не учитываются в метриках запросов
+        let request = actix_web::test::TestRequest::get()
+            .uri("/health/live")
+            .to_request();
+        app.call(request).await.unwrap();
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/metrics")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/plain"));
+        let body = actix_web::test::read_body(response).await;
+        let text = std::str::from_utf8(&body).unwrap();
+        assert!(text.contains(r#"http_requests_total{method="GET",route="/employee/{id}",status="200"} 2"#));
+        assert!(text.contains(r#"http_requests_total{method="GET",route="/employee/{id}",status="404"} 1"#));
+        assert!(text.contains(r#"http_request_duration_seconds_count{method="PUT",route="/employee/add",status="200"} 1"#));
+        assert!(!text.contains("/health/live"));
+        assert!(text.contains(r#"db_call_duration_seconds_count{method="get_employee_by_id"} 3"#));
+        assert!(text.contains(r#"db_call_errors_total{code="not_found",method="get_employee_by_id"} 1"#));
+        assert!(text.contains("employees_added_total 1"));
+        assert!(text.contains("salary_raises_total 1"));
+        assert!(text.contains(r#"salary_raise_percent_bucket{le="30"} 1"#));
+        assert!(text.contains("db_pool_max_connections 7"));
+    }
+
+    /// Сервер с проверкой подлинности по тестовым ключам
+    fn authenticated_server() -> ServerBuilder {
+        let testdata = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/auth");
+        let auth = AuthConfig{
+            enabled: true,
+            api_keys_file: Some(testdata.join("api_keys.toml")),
+            jwt_secret: Some("0123456789abcdef0123456789abcdef".to_owned()),
+            jwks_file: Some(testdata.join("jwks.json")),
+            jwt_issuer: None,
+            jwt_audience: None,
+            ..AuthConfig::default()
+        };
+        Server::builder()
+            .config(Config{auth, ..Config::default()})
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_authentication() {
+        set_env_vars();
+        let app = authenticated_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        for uri in ["/employee/1", "/admin/employee/1"] {
+            let request = actix_web::test::TestRequest::get()
+                .uri(uri)
+                .to_request();
+            let error = app.call(request).await.err().unwrap();
+            let response = error.error_response();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+            assert_eq!("Bearer", response.headers().get("www-authenticate").unwrap());
+        }
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/1")
+            .insert_header(("X-Api-Key", "wrong-key"))
+            .to_request();
+        let response = app.call(request).await.err().unwrap().error_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+        let response_body: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!("unauthorized", response_body.error.code);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/employee/1")
+            .insert_header(("X-Api-Key", "test-api-key"))
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let exp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() + 600;
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({"sub": "alice", "exp": exp, "roles": ["hr"]}),
+            &jsonwebtoken::EncodingKey::from_secret(b"0123456789abcdef0123456789abcdef"),
+        ).unwrap();
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/increase?percentage=10")
+            .insert_header(("Authorization", format!("Bearer {token}")))
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Проверки состояния доступны без учетных данных, а метрики - только с ними
+        let request = actix_web::test::TestRequest::get()
+            .uri("/health/live")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let request = actix_web::test::TestRequest::get()
+            .uri("/metrics")
+            .to_request();
+        let response = app.call(request).await.err().unwrap().error_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    /// Статус ответа на запрос с ключом API
+    async fn status_with_key<S>(app: &S, key: &str, request: actix_web::test::TestRequest) -> StatusCode
+    where
+        S: actix_service::Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let request = request.insert_header(("X-Api-Key", key)).to_request();
+        app.call(request).await.unwrap().status()
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_authorization() {
+        use actix_web::test::TestRequest;
+        set_env_vars();
+        let app = authenticated_server()
+            .build()
+            .test_start()
+            .await
+            .unwrap();
+        let cases = [
+            // Сотрудник 1 видит только свою запись, зарплату и журнал
+            ("viewer-key", TestRequest::get().uri("/employee/1/salary"), StatusCode::OK),
+            ("viewer-key", TestRequest::get().uri("/employee/1"), StatusCode::OK),
+            ("viewer-key", TestRequest::get().uri("/employee/1/salary/history"), StatusCode::OK),
+            ("viewer-key", TestRequest::get().uri("/employee/salary?name=Test%20Employee"), StatusCode::OK),
+            ("viewer-key", TestRequest::get().uri("/employee/2/salary"), StatusCode::FORBIDDEN),
+            ("viewer-key", TestRequest::get().uri("/employee/salary?name=Nobody"), StatusCode::FORBIDDEN),
+            ("viewer-key", TestRequest::get().uri("/employee"), StatusCode::FORBIDDEN),
+            ("viewer-key", TestRequest::get().uri("/employee/search?q=Test"), StatusCode::FORBIDDEN),
+            ("viewer-key", TestRequest::put().uri("/employee/add?name=Test%20Employee&salary=2000"), StatusCode::FORBIDDEN),
+            ("viewer-key", TestRequest::post().uri("/employee/1/increase?percentage=1"), StatusCode::FORBIDDEN),
+            // Отдел кадров ведет сотрудников и повышает зарплаты не больше чем на 10%
+            ("hr-portal-key", TestRequest::get().uri("/employee/2/salary"), StatusCode::OK),
+            ("hr-portal-key", TestRequest::get().uri("/employee"), StatusCode::OK),
+            ("hr-portal-key", TestRequest::put().uri("/employee/add?name=Test%20Employee&salary=2000"), StatusCode::OK),
+            ("hr-portal-key", TestRequest::patch().uri("/employee/1?name=Test%20Employee"), StatusCode::OK),
+            ("hr-portal-key", TestRequest::post().uri("/employee/1/increase?percentage=10"), StatusCode::OK),
+            ("hr-portal-key", TestRequest::post().uri("/employee/1/increase?percentage=10.01"), StatusCode::FORBIDDEN),
+            ("hr-portal-key", TestRequest::post().uri("/employee/increase?name=Test%20Employee&percentage=15"), StatusCode::FORBIDDEN),
+            ("hr-portal-key", TestRequest::post().uri("/employee/1/adjust?operation=percentage&value=-30"), StatusCode::OK),
+            ("hr-portal-key", TestRequest::post().uri("/employee/1/adjust?operation=set&value=110"), StatusCode::OK),
+            ("hr-portal-key", TestRequest::post().uri("/employee/1/adjust?operation=set&value=200"), StatusCode::FORBIDDEN),
+            ("hr-portal-key", TestRequest::post().uri("/employee/1/adjust?operation=delta&value=50"), StatusCode::FORBIDDEN),
+            ("hr-portal-key", TestRequest::post().uri("/employee/batch/increase").set_json(serde_json::json!({"items": [{"id": 1, "percentage": "5"}]})), StatusCode::OK),
+            ("hr-portal-key", TestRequest::post().uri("/employee/batch/increase").set_json(serde_json::json!({"items": [{"id": 1, "percentage": "5"}, {"id": 2, "percentage": "50"}]})), StatusCode::FORBIDDEN),
+            ("hr-portal-key", TestRequest::delete().uri("/admin/employee/1"), StatusCode::FORBIDDEN),
+            // Администратор расчета зарплаты не ограничен
+            ("test-api-key", TestRequest::post().uri("/employee/1/increase?percentage=50"), StatusCode::OK),
+            ("test-api-key", TestRequest::post().uri("/employee/1/adjust?operation=set&value=200"), StatusCode::OK),
+            ("test-api-key", TestRequest::delete().uri("/admin/employee/1"), StatusCode::OK),
+            ("test-api-key", TestRequest::get().uri("/metrics"), StatusCode::OK),
+            // Метрики читает только роль monitoring, а ей недоступны данные сотрудников
+            ("metrics-key", TestRequest::get().uri("/metrics"), StatusCode::OK),
+            ("metrics-key", TestRequest::get().uri("/employee/1"), StatusCode::FORBIDDEN),
+            ("hr-portal-key", TestRequest::get().uri("/metrics"), StatusCode::FORBIDDEN),
+            ("viewer-key", TestRequest::get().uri("/metrics"), StatusCode::FORBIDDEN),
+        ];
+        for (i, (key, request, status)) in cases.into_iter().enumerate() {
+            assert_eq!(status, status_with_key(&app, key, request).await, "case {i} with {key}");
+        }
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/employee/1/increase?percentage=15")
+            .insert_header(("X-Api-Key", "hr-portal-key"))
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+        assert_eq!("forbidden", response_body.error.code);
+        assert!(response_body.error.message.contains("payroll_admin"));
+
+        // Поиск по чужому имени не выдает ни существование записи, ни идентификаторы однофамильцев
+        let mut messages = Vec::new();
+        for name in ["Duplicate%20Employee", "Nobody"] {
+            let request = actix_web::test::TestRequest::get()
+                .uri(&format!("/employee/salary?name={name}"))
+                .insert_header(("X-Api-Key", "viewer-key"))
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            let response_body: ErrorResponse = actix_web::test::read_body_json(response).await;
+            assert_eq!(None, response_body.error.candidates);
+            messages.push(response_body.error.message);
+        }
+        assert_eq!(messages[0], messages[1]);
+
+        // Роли токена берутся из утверждения roles
+        let exp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() + 600;
+        for (roles, status) in [(serde_json::json!(["hr"]), StatusCode::OK), (serde_json::json!([]), StatusCode::FORBIDDEN)] {
+            let token = jsonwebtoken::encode(
+                &jsonwebtoken::Header::default(),
+                &serde_json::json!({"sub": "alice", "exp": exp, "roles": roles}),
+                &jsonwebtoken::EncodingKey::from_secret(b"0123456789abcdef0123456789abcdef"),
+            ).unwrap();
+            let request = actix_web::test::TestRequest::get()
+                .uri("/employee/2")
+                .insert_header(("Authorization", format!("Bearer {token}")))
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(status, response.status(), "{roles}");
+        }
+    }
+
+    #[actix_web::test]
+    #[serial]
+    async fn test_health() {
+        set_env_vars();
+        let server = test_server()
+            .build();
+        let readiness = server.readiness();
+        let app = server.test_start().await.unwrap();
+        let request = actix_web::test::TestRequest::get()
+            .uri("/health/live")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/health/ready")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response_body: ApiResponse<ReadinessReport> = actix_web::test::read_body_json(response).await;
+        assert!(response_body.data.database.up);
+        assert_eq!(Some(MigrationStatus{applied: vec![1], pending: Vec::new()}), response_body.data.migrations);
+        assert_eq!(7, response_body.data.pool.max_connections);
+
+        // После сигнала остановки приложение живо, но не готово
+        readiness.begin_shutdown();
+        let request = actix_web::test::TestRequest::get()
+            .uri("/health/ready")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let response_body: ApiResponse<ReadinessReport> = actix_web::test::read_body_json(response).await;
+        assert!(response_body.data.shutting_down);
+        let request = actix_web::test::TestRequest::get()
+            .uri("/health/live")
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
 }
@@ -0,0 +1,511 @@
+use std::fmt::{self, Debug, Display};
+use std::future::Future;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use actix_service::Service;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::HeaderMap;
+use actix_web::{web, ResponseError};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::future::BoxFuture;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{FutureExt, SpanKind, Status, TraceContextExt, Tracer as _, TracerProvider as _};
+use opentelemetry::{Context, KeyValue, Value};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use crate::access::RaiseLimit;
+use crate::config::TracingConfig;
+use crate::errors::AppError;
+use crate::logging::{current_request_id, REQUEST_ID_HEADER};
+use crate::metrics::route_label;
+use crate::migrations::MigrationStatus;
+use crate::models::{Employee, EmployeeData, EmployeeUpdate, EmployeeTermination, EmployeeId, EmployeeName, SalaryMultiplier, SalaryChange, SalaryPercentage, SalaryAdjustment, SalaryBatch, SalaryBatchReport, EmployeeListQuery, EmployeePage, PoolStatus, EmployeeSearch, EmployeeMatch, SalaryHistoryPage, Page};
+use crate::postgres_client::DBClient;
+
+
+/// Запросы, для которых спаны не создаются
+///
+/// Проверки состояния и сбор метрик приходят каждые несколько секунд и только засоряли бы трассы
+const UNTRACED_PATHS: &[&str] = &["/health/live", "/health/ready", "/metrics"];
+
+
+/// Куда отправляются завершенные спаны
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TraceExporter{
+    /// Трассировка выключена
+    #[default]
+    None,
+    /// Спаны пишутся в стандартный вывод по одному JSON-объекту на строку
+    Stdout,
+    /// Спаны отправляются в коллектор по OTLP/HTTP
+    Otlp,
+}
+
+impl FromStr for TraceExporter{
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(TraceExporter::None),
+            "stdout" => Ok(TraceExporter::Stdout),
+            "otlp" => Ok(TraceExporter::Otlp),
+            _ => Err(AppError::validation("TRACING_EXPORTER", "trace exporter must be one of none, stdout, otlp")),
+        }
+    }
+}
+
+
+/// Экспорт спанов построчно в формате JSON
+///
+/// Используется экспортером stdout и в тестах, где вместо стандартного вывода подставляется буфер
+struct JsonSpanExporter{
+    writer: Box<dyn Write + Send + Sync>,
+}
+
+impl Debug for JsonSpanExporter{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonSpanExporter").finish_non_exhaustive()
+    }
+}
+
+/// Значение атрибута спана в JSON
+fn json_value(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Bool(value) => (*value).into(),
+        Value::I64(value) => (*value).into(),
+        Value::F64(value) => (*value).into(),
+        value => value.as_str().into(),
+    }
+}
+
+/// Строка с завершенным спаном
+fn format_span(span: &SpanData) -> String {
+    let time = |time: SystemTime| DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Micros, true);
+    let attributes: serde_json::Map<String, serde_json::Value> = span.attributes.iter()
+        .map(|attribute| (attribute.key.to_string(), json_value(&attribute.value)))
+        .collect();
+    let (status, message) = match &span.status {
+        Status::Unset => ("unset", None),
+        Status::Ok => ("ok", None),
+        Status::Error{description} => ("error", Some(description.to_string())),
+    };
+    let line = serde_json::json!({
+        "trace_id": span.span_context.trace_id().to_string(),
+        "span_id": span.span_context.span_id().to_string(),
+        "parent_span_id": span.parent_span_id.to_string(),
+        "name": span.name,
+        "kind": format!("{:?}", span.span_kind).to_lowercase(),
+        "start": time(span.start_time),
+        "duration_us": span.end_time.duration_since(span.start_time).unwrap_or_default().as_micros() as u64,
+        "status": status,
+        "status_message": message,
+        "attributes": attributes,
+    });
+    format!("{line}\n")
+}
+
+impl SpanExporter for JsonSpanExporter{
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let result = batch.iter()
+            .try_for_each(|span| self.writer.write_all(format_span(span).as_bytes()))
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| e.to_string().into());
+        Box::pin(std::future::ready(result))
+    }
+}
+
+
+/// Буфер в памяти для трассировки из Telemetry::with_writer
+///
+/// Клоны пишут в один и тот же буфер, поэтому спаны можно прочитать после передачи клона трассировке
+#[derive(Clone, Default)]
+pub struct SpanBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SpanBuffer{
+    /// Записанные спаны в порядке завершения
+    pub fn spans(&self) -> Vec<serde_json::Value> {
+        let buffer = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        String::from_utf8_lossy(&buffer)
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+}
+
+impl Write for SpanBuffer{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+
+/// Трассировка запросов в формате OpenTelemetry
+///
+/// Создается одна на сервер. Спаны запросов наследуют контекст из заголовка traceparent,
+/// вызовы DBClient, ожидание соединения из пула и SQL-запросы становятся их дочерними спанами
+#[derive(Clone, Debug)]
+pub struct Telemetry{
+    provider: TracerProvider,
+    tracer: Tracer,
+}
+
+impl Telemetry{
+    /// Трассировка по настройкам
+    ///
+    /// Экспортер otlp отправляет спаны пачками в отдельном потоке, чтобы не задерживать запросы
+    pub fn new(config: &TracingConfig) -> Result<Telemetry, AppError> {
+        let builder = TracerProvider::builder()
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(config.sample_ratio))))
+            .with_resource(Resource::new([KeyValue::new("service.name", config.service_name.clone())]));
+        let provider = match config.exporter {
+            TraceExporter::None => return Ok(Telemetry::disabled()),
+            TraceExporter::Stdout => builder
+                .with_simple_exporter(JsonSpanExporter{writer: Box::new(io::stdout())})
+                .build(),
+            TraceExporter::Otlp => {
+                let exporter = opentelemetry_otlp::SpanExporter::builder()
+                    .with_http()
+                    .with_endpoint(&config.endpoint)
+                    .build()
+                    .map_err(|e| AppError::validation("tracing.endpoint", &format!("cannot create OTLP exporter: {e}")))?;
+                builder
+                    .with_batch_exporter(exporter, runtime::TokioCurrentThread)
+                    .build()
+            },
+        };
+        Ok(Telemetry::from_provider(provider))
+    }
+
+    /// Выключенная трассировка: спаны не записываются и никуда не отправляются
+    pub fn disabled() -> Telemetry {
+        Telemetry::from_provider(TracerProvider::builder()
+            .with_sampler(Sampler::AlwaysOff)
+            .build())
+    }
+
+    /// Трассировка, которая пишет каждый спан в writer сразу по завершении
+    ///
+    /// Формат тот же, что у экспортера stdout
+    pub fn with_writer(writer: impl Write + Send + Sync + 'static) -> Telemetry {
+        Telemetry::from_provider(TracerProvider::builder()
+            .with_simple_exporter(JsonSpanExporter{writer: Box::new(writer)})
+            .build())
+    }
+
+    fn from_provider(provider: TracerProvider) -> Telemetry {
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        Telemetry{provider, tracer}
+    }
+
+    pub fn tracer(&self) -> Tracer {
+        self.tracer.clone()
+    }
+
+    /// Отправить накопленные спаны и остановить экспорт
+    pub fn shutdown(&self) -> Result<(), AppError> {
+        self.provider.shutdown()
+            .map_err(|e| AppError::Storage(format!("failed to flush traces: {e}")))
+    }
+
+    /// Начать спан входящего запроса
+    ///
+    /// Родительский контекст берется из заголовков W3C traceparent и tracestate
+    fn start_request(&self, request: &ServiceRequest) -> Context {
+        let parent = TraceContextPropagator::new().extract(&HeaderExtractor(request.headers()));
+        let mut attributes = vec![
+            KeyValue::new("http.request.method", request.method().to_string()),
+            KeyValue::new("url.path", request.path().to_owned()),
+        ];
+        if let Some(request_id) = current_request_id() {
+            attributes.push(KeyValue::new(format!("http.request.header.{REQUEST_ID_HEADER}"), request_id));
+        }
+        let span = self.tracer.span_builder(request.method().to_string())
+            .with_kind(SpanKind::Server)
+            .with_attributes(attributes)
+            .start_with_context(&self.tracer, &parent);
+        Context::current_with_span(span).with_value(self.tracer.clone())
+    }
+}
+
+
+/// Доступ к заголовкам actix для извлечения контекста трассировки
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_>{
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+
+/// Записать запрос в спан
+///
+/// Подключается через wrap_fn на уровне приложения. Имя спана уточняется шаблоном
+/// сработавшего маршрута после ответа, ответы 5xx отмечаются как ошибки
+pub fn trace_request<S, B>(request: ServiceRequest, service: &S) -> impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let method = request.method().to_string();
+    let cx = request.app_data::<web::Data<Telemetry>>()
+        .filter(|_| !UNTRACED_PATHS.contains(&request.path()))
+        .map(|telemetry| telemetry.start_request(&request))
+        .unwrap_or_default();
+    let response = {
+        // Часть обработчиков выполняется уже при вызове, а не при опросе ответа
+        let _guard = cx.clone().attach();
+        service.call(request)
+    };
+    async move {
+        let response = response.with_context(cx.clone()).await;
+        let span = cx.span();
+        if span.is_recording() {
+            let (route, status) = match &response {
+                Ok(response) => (response.request().match_pattern(), response.status()),
+                Err(e) => (None, e.as_response_error().status_code()),
+            };
+            if let Some(route) = route.map(|route| route_label(&route)) {
+                span.update_name(format!("{method} {route}"));
+                span.set_attribute(KeyValue::new("http.route", route));
+            }
+            span.set_attribute(KeyValue::new("http.response.status_code", i64::from(status.as_u16())));
+            if status.is_server_error() {
+                span.set_status(Status::error(status.to_string()));
+            }
+            span.end();
+        }
+        response
+    }
+}
+
+/// Выполнить вызов в дочернем спане текущего контекста
+///
+/// Трассировщик берется из контекста: вне трассируемого запроса спан не создается
+async fn in_span<T, E: Display>(name: &'static str, kind: SpanKind, attributes: Vec<KeyValue>, call: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+    let parent = Context::current();
+    let Some(tracer) = parent.get::<Tracer>() else {
+        return call.await;
+    };
+    let span = tracer.span_builder(name)
+        .with_kind(kind)
+        .with_attributes(attributes)
+        .start_with_context(tracer, &parent);
+    let cx = parent.with_span(span);
+    let result = call.with_context(cx.clone()).await;
+    if let Err(e) = &result {
+        cx.span().set_status(Status::error(e.to_string()));
+    }
+    result
+}
+
+/// Выполнить SQL-запрос в спане с именем запроса
+pub async fn statement<T, E: Display>(name: &'static str, call: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+    in_span(name, SpanKind::Client, vec![
+        KeyValue::new("db.system", "postgresql"),
+        KeyValue::new("db.statement.name", name),
+    ], call).await
+}
+
+/// Дождаться соединения из пула в отдельном спане
+///
+/// Длительность спана - время ожидания соединения, в атрибутах записывается загруженность пула на момент запроса
+pub async fn pool_wait<T, E: Display>(name: &'static str, pool: PoolStatus, call: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+    in_span(name, SpanKind::Internal, vec![
+        KeyValue::new("db.system", "postgresql"),
+        KeyValue::new("db.client.connection.pool.size", i64::from(pool.size)),
+        KeyValue::new("db.client.connection.pool.idle", i64::from(pool.idle)),
+        KeyValue::new("db.client.connection.pool.max", i64::from(pool.max_connections)),
+    ], call).await
+}
+
+
+/// Клиент базы данных, записывающий вызовы в спаны
+///
+/// Оборачивает другой клиент и для каждого метода создает спан с его именем.
+/// Трассировщик кладется в контекст вызова, чтобы внутренний клиент мог отметить ожидание пула и SQL-запросы
+pub struct TracedDBClient{
+    inner: Arc<dyn DBClient>,
+    tracer: Tracer,
+}
+
+impl TracedDBClient{
+    pub fn new(inner: Arc<dyn DBClient>, tracer: Tracer) -> Self {
+        TracedDBClient{inner, tracer}
+    }
+
+    /// Выполнить вызов в спане метода
+    ///
+    /// Вызовы вне запроса, например из проверки готовности, не трассируются, чтобы не порождать отдельные трассы
+    async fn trace<T>(&self, method: &'static str, call: impl Future<Output = Result<T, AppError>>) -> Result<T, AppError> {
+        let parent = Context::current();
+        if !parent.has_active_span() {
+            return call.await;
+        }
+        let span = self.tracer.span_builder(method)
+            .with_kind(SpanKind::Internal)
+            .with_attributes([
+                KeyValue::new("db.system", "postgresql"),
+                KeyValue::new("db.operation.name", method),
+            ])
+            .start_with_context(&self.tracer, &parent);
+        let cx = parent.with_span(span).with_value(self.tracer.clone());
+        let result = call.with_context(cx.clone()).await;
+        if let Err(e) = &result {
+            let span = cx.span();
+            span.set_attribute(KeyValue::new("error.type", e.code()));
+            // Ошибки клиента вроде отсутствующего сотрудника - обычный ответ, а не сбой
+            if e.status_code().is_server_error() {
+                span.set_status(Status::error(e.to_string()));
+            }
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl DBClient for TracedDBClient{
+    async fn init_db(&self) -> Result<(), AppError> {
+        self.trace("init_db", self.inner.init_db()).await
+    }
+
+    async fn init_db_clear(&self) -> Result<(), AppError> {
+        self.trace("init_db_clear", self.inner.init_db_clear()).await
+    }
+
+    async fn get_employee_salary(&self, data: EmployeeName) -> Result<Employee, AppError> {
+        self.trace("get_employee_salary", self.inner.get_employee_salary(data)).await
+    }
+
+    async fn add_new_employee(&self, data: EmployeeData) -> Result<Employee, AppError> {
+        self.trace("add_new_employee", self.inner.add_new_employee(data)).await
+    }
+
+    async fn increase_employee_salary(&self, data: SalaryMultiplier, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError> {
+        self.trace("increase_employee_salary", self.inner.increase_employee_salary(data, limit, actor)).await
+    }
+
+    async fn get_employee_by_id(&self, id: EmployeeId) -> Result<Employee, AppError> {
+        self.trace("get_employee_by_id", self.inner.get_employee_by_id(id)).await
+    }
+
+    async fn increase_employee_salary_by_id(&self, id: EmployeeId, percentage: SalaryPercentage, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError> {
+        self.trace("increase_employee_salary_by_id", self.inner.increase_employee_salary_by_id(id, percentage, limit, actor)).await
+    }
+
+    async fn adjust_employee_salary(&self, id: EmployeeId, adjustment: SalaryAdjustment, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryChange, AppError> {
+        self.trace("adjust_employee_salary", self.inner.adjust_employee_salary(id, adjustment, limit, actor)).await
+    }
+
+    async fn increase_salaries(&self, batch: SalaryBatch, limit: Option<RaiseLimit>, actor: String) -> Result<SalaryBatchReport, AppError> {
+        self.trace("increase_salaries", self.inner.increase_salaries(batch, limit, actor)).await
+    }
+
+    async fn update_employee(&self, id: EmployeeId, update: EmployeeUpdate) -> Result<Employee, AppError> {
+        self.trace("update_employee", self.inner.update_employee(id, update)).await
+    }
+
+    async fn terminate_employee(&self, id: EmployeeId) -> Result<EmployeeTermination, AppError> {
+        self.trace("terminate_employee", self.inner.terminate_employee(id)).await
+    }
+
+    async fn delete_employee(&self, id: EmployeeId) -> Result<Employee, AppError> {
+        self.trace("delete_employee", self.inner.delete_employee(id)).await
+    }
+
+    async fn list_employees(&self, query: EmployeeListQuery) -> Result<EmployeePage, AppError> {
+        self.trace("list_employees", self.inner.list_employees(query)).await
+    }
+
+    async fn search_employees(&self, search: EmployeeSearch) -> Result<Vec<EmployeeMatch>, AppError> {
+        self.trace("search_employees", self.inner.search_employees(search)).await
+    }
+
+    async fn get_salary_history(&self, id: EmployeeId, page: Page) -> Result<SalaryHistoryPage, AppError> {
+        self.trace("get_salary_history", self.inner.get_salary_history(id, page)).await
+    }
+
+    async fn ping(&self) -> Result<(), AppError> {
+        self.trace("ping", self.inner.ping()).await
+    }
+
+    async fn migration_status(&self) -> Result<MigrationStatus, AppError> {
+        self.trace("migration_status", self.inner.migration_status()).await
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        self.inner.pool_status()
+    }
+}
+
+
+#[cfg(test)]
+mod tests{
+    use std::sync::Arc;
+    use opentelemetry::trace::{FutureExt, TraceContextExt, Tracer as _};
+    use opentelemetry::Context;
+    use crate::errors::AppError;
+    use crate::models::{Employee, EmployeeId, PersonName};
+    use crate::money::{Currency, Money};
+    use crate::postgres_client::{DBClient, MockDBClient};
+    use super::{statement, SpanBuffer, Telemetry, TracedDBClient};
+
+    #[actix_web::test]
+    async fn untraced_statement_test(){
+        // Вне трассируемого вызова в контексте нет трассировщика, и запрос выполняется как есть
+        let result: Result<i32, AppError> = statement("select_one", async { Ok(1) }).await;
+        assert_eq!(1, result.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn traced_client_test(){
+        let mut db_client = MockDBClient::new();
+        db_client.expect_get_employee_by_id()
+            .returning(|id| match id.id {
+                1 => Ok(Employee{id: 1, name_parts: PersonName::parse_legacy("Test Employee"), name: "Test Employee".to_owned(), salary: Money::new(10000, Currency::Rub), personnel_number: None}),
+                _ => Err(AppError::NotFound("employee does not exist".to_owned())),
+            });
+        db_client.expect_ping()
+            .returning(|| Err(AppError::StorageUnavailable("database is down".to_owned())));
+        let buffer = SpanBuffer::default();
+        let telemetry = Telemetry::with_writer(buffer.clone());
+        let client = TracedDBClient::new(Arc::new(db_client), telemetry.tracer());
+
+        // Без родительского спана вызовы не трассируются
+        client.get_employee_by_id(EmployeeId{id: 1}).await.unwrap();
+        assert!(buffer.spans().is_empty());
+
+        let cx = Context::current_with_span(telemetry.tracer().start("request"));
+        client.get_employee_by_id(EmployeeId{id: 2}).with_context(cx.clone()).await.unwrap_err();
+        client.ping().with_context(cx.clone()).await.unwrap_err();
+        cx.span().end();
+
+        let spans = buffer.spans();
+        let names: Vec<&str> = spans.iter().map(|span| span["name"].as_str().unwrap()).collect();
+        assert_eq!(vec!["get_employee_by_id", "ping", "request"], names);
+        let request = &spans[2];
+        for span in &spans[..2] {
+            assert_eq!(request["trace_id"], span["trace_id"]);
+            assert_eq!(request["span_id"], span["parent_span_id"]);
+            assert_eq!("postgresql", span["attributes"]["db.system"]);
+        }
+        // Отсутствующий сотрудник - не сбой базы
+        assert_eq!("not_found", spans[0]["attributes"]["error.type"]);
+        assert_eq!("unset", spans[0]["status"]);
+        assert_eq!("storage_unavailable", spans[1]["attributes"]["error.type"]);
+        assert_eq!("error", spans[1]["status"]);
+    }
+}